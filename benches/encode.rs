@@ -6,6 +6,7 @@ struct Image<T> {
     data: Vec<T>,
     size: Size,
     channels: Channels,
+    #[allow(dead_code)]
     name: String,
 }
 impl<T: 'static> Image<T> {
@@ -44,7 +45,7 @@ impl<T: 'static> Image<T> {
 trait ImageAsBytes {
     fn color(&self) -> ColorFormat;
     fn as_bytes(&self) -> &[u8];
-    fn view(&self) -> ImageView;
+    fn view(&self) -> ImageView<'_>;
 }
 impl ImageAsBytes for Image<u8> {
     fn color(&self) -> ColorFormat {
@@ -53,7 +54,7 @@ impl ImageAsBytes for Image<u8> {
     fn as_bytes(&self) -> &[u8] {
         &self.data
     }
-    fn view(&self) -> ImageView {
+    fn view(&self) -> ImageView<'_> {
         ImageView::new(self.as_bytes(), self.size, self.color()).unwrap()
     }
 }
@@ -64,7 +65,7 @@ impl ImageAsBytes for Image<u16> {
     fn as_bytes(&self) -> &[u8] {
         zerocopy::IntoBytes::as_bytes(self.data.as_slice())
     }
-    fn view(&self) -> ImageView {
+    fn view(&self) -> ImageView<'_> {
         ImageView::new(self.as_bytes(), self.size, self.color()).unwrap()
     }
 }
@@ -75,7 +76,7 @@ impl ImageAsBytes for Image<f32> {
     fn as_bytes(&self) -> &[u8] {
         zerocopy::IntoBytes::as_bytes(self.data.as_slice())
     }
-    fn view(&self) -> ImageView {
+    fn view(&self) -> ImageView<'_> {
         ImageView::new(self.as_bytes(), self.size, self.color()).unwrap()
     }
 }
@@ -165,9 +166,9 @@ pub fn encode_compressed(c: &mut Criterion) {
     use Channels::*;
 
     // images
-    let random: Image<f32> = Image::random(Size::new(128, 128), Rgba);
+    let _random: Image<f32> = Image::random(Size::new(128, 128), Rgba);
     let random_rgb: Image<f32> = Image::random(Size::new(128, 128), Rgb);
-    let random_tiny: Image<f32> = Image::random(Size::new(16, 16), Rgba);
+    let _random_tiny: Image<f32> = Image::random(Size::new(16, 16), Rgba);
 
     // options
     let mut fast = EncodeOptions::default();
@@ -189,7 +190,7 @@ pub fn encode_compressed(c: &mut Criterion) {
     let high = &high;
     let dither = &dither;
     let perceptual = &perceptual;
-    let unreasonable = &unreasonable;
+    let _unreasonable = &unreasonable;
 
     // uncompressed formats
     bench_encoder(c, Format::BC1_UNORM, fast, &random_rgb);
@@ -0,0 +1,148 @@
+use std::ops::Sub;
+
+use crate::{cast, Precision};
+
+use super::Norm;
+
+/// The source of a channel in a [`ChannelMapping`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelSource {
+    /// The red channel.
+    Red,
+    /// The green channel.
+    Green,
+    /// The blue channel.
+    Blue,
+    /// The alpha channel.
+    Alpha,
+    /// The inverted red channel, i.e. `1 - R`.
+    InvertedRed,
+    /// The inverted green channel, i.e. `1 - G`.
+    InvertedGreen,
+    /// The inverted blue channel, i.e. `1 - B`.
+    InvertedBlue,
+    /// The inverted alpha channel, i.e. `1 - A`.
+    InvertedAlpha,
+    /// A constant 0.
+    Zero,
+    /// A constant 1.
+    One,
+}
+impl ChannelSource {
+    fn get<T: Norm + Sub<Output = T>>(self, [r, g, b, a]: [T; 4]) -> T {
+        match self {
+            Self::Red => r,
+            Self::Green => g,
+            Self::Blue => b,
+            Self::Alpha => a,
+            Self::InvertedRed => T::ONE - r,
+            Self::InvertedGreen => T::ONE - g,
+            Self::InvertedBlue => T::ONE - b,
+            Self::InvertedAlpha => T::ONE - a,
+            Self::Zero => T::ZERO,
+            Self::One => T::ONE,
+        }
+    }
+}
+
+/// A mapping that builds each channel of an RGBA pixel from the channels of
+/// another RGBA pixel.
+///
+/// Images with fewer channels are converted to RGBA before the mapping is
+/// applied (e.g. a grayscale value `v` becomes `(v, v, v, 1)`), and converted
+/// back to their channels afterward.
+///
+/// E.g. the following mapping packs 3 grayscale masks stored in the R, G, and
+/// A channels of an image into the R, G, and B channels and makes the image
+/// opaque:
+///
+/// ```
+/// use dds::{ChannelMapping, ChannelSource::*};
+///
+/// let mapping = ChannelMapping::new(Red, Green, Alpha, One);
+/// ```
+///
+/// The default mapping is [`ChannelMapping::IDENTITY`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelMapping {
+    /// The source of the red channel.
+    pub r: ChannelSource,
+    /// The source of the green channel.
+    pub g: ChannelSource,
+    /// The source of the blue channel.
+    pub b: ChannelSource,
+    /// The source of the alpha channel.
+    pub a: ChannelSource,
+}
+impl ChannelMapping {
+    /// The mapping that leaves all channels unchanged.
+    pub const IDENTITY: Self = Self::new(
+        ChannelSource::Red,
+        ChannelSource::Green,
+        ChannelSource::Blue,
+        ChannelSource::Alpha,
+    );
+
+    /// Creates a new mapping from the sources of the R, G, B, and A channels.
+    pub const fn new(
+        r: ChannelSource,
+        g: ChannelSource,
+        b: ChannelSource,
+        a: ChannelSource,
+    ) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Whether this mapping leaves all channels unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    pub(crate) fn apply<T: Norm + Sub<Output = T>>(&self, pixel: [T; 4]) -> [T; 4] {
+        [
+            self.r.get(pixel),
+            self.g.get(pixel),
+            self.b.get(pixel),
+            self.a.get(pixel),
+        ]
+    }
+
+    /// Applies the mapping to all pixels in place.
+    pub(crate) fn apply_rgba_f32(&self, pixels: &mut [[f32; 4]]) {
+        if self.is_identity() {
+            return;
+        }
+        pixels.iter_mut().for_each(|p| *p = self.apply(*p));
+    }
+
+    /// Applies the mapping in place to RGBA pixels of the given precision
+    /// stored as native-endian bytes.
+    pub(crate) fn apply_rgba_bytes(&self, precision: Precision, bytes: &mut [u8]) {
+        fn apply<T>(mapping: &ChannelMapping, bytes: &mut [u8])
+        where
+            T: Norm + Sub<Output = T>,
+            [T; 4]: cast::IntoNeBytes,
+        {
+            let pixels: &mut [<[T; 4] as cast::IntoNeBytes>::Bytes] =
+                cast::from_bytes_mut(bytes).expect("invalid buffer");
+            for pixel in pixels {
+                let mapped = mapping.apply(cast::IntoNeBytes::from_ne_bytes(*pixel));
+                *pixel = cast::IntoNeBytes::into_ne_bytes(mapped);
+            }
+        }
+
+        if self.is_identity() {
+            return;
+        }
+        match precision {
+            Precision::U8 => apply::<u8>(self, bytes),
+            Precision::U16 => apply::<u16>(self, bytes),
+            Precision::F32 => apply::<f32>(self, bytes),
+        }
+    }
+}
+impl Default for ChannelMapping {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
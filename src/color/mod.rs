@@ -4,9 +4,11 @@ use crate::{cast, Size};
 
 pub(crate) mod ch;
 mod formats;
+mod mapping;
 mod oklab;
 
 pub(crate) use formats::*;
+pub use mapping::*;
 pub(crate) use oklab::*;
 
 /// The number and semantics of the color channels in a surface.
@@ -119,7 +119,9 @@ fn oklab_to_srgb_impl<O: Operations>(lab: Vec3A) -> Vec3A {
     )
 }
 
-#[allow(unused)]
+pub(crate) fn linear_to_srgb(c: f32) -> f32 {
+    Reference::linear_to_srgb(c)
+}
 pub(crate) fn srgb_to_oklab(rgb: Vec3A) -> Vec3A {
     srgb_to_oklab_impl::<Reference>(rgb)
 }
@@ -0,0 +1,280 @@
+use std::io::{Read, Seek, Write};
+
+use crate::{
+    header::{Dx10Header, Dx9Header, Dx9PixelFormat, DxgiFormat, Header},
+    iter::SurfaceIterator,
+    sub_progress, ConvertError, CubeMapFaces, Decoder, DecodingError, EncodeOptions, Encoder,
+    Format, ImageView, ImageViewMut, LayoutError, Progress, ProgressRange, Report, ResizeFilter,
+    WriteOptions,
+};
+
+/// Which kind of header [`convert`] writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HeaderVersion {
+    /// Use the same kind of header as the source file.
+    ///
+    /// If the target format cannot be represented with this kind of header,
+    /// the other kind is used instead. E.g. converting a DX10 file to
+    /// `BC3_UNORM_RXGB` will produce a DX9 file.
+    #[default]
+    Preserve,
+    /// Always write a DX9 header.
+    ///
+    /// DX9 headers do not support texture arrays and many formats.
+    Dx9,
+    /// Always write a DX10 header.
+    ///
+    /// DX10 headers do not support partial cube maps and some older formats.
+    Dx10,
+}
+
+/// How [`convert`] handles mipmaps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MipmapMode {
+    /// Every mipmap of the source file is decoded and re-encoded.
+    #[default]
+    Convert,
+    /// The same number of mipmaps as the source file has, but all of them are
+    /// generated from the level 0 surfaces. The mipmaps of the source file
+    /// are skipped.
+    Regenerate,
+    /// A full mipmap chain down to 1x1 is generated from the level 0
+    /// surfaces. The mipmaps of the source file are skipped.
+    Full,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// The options used to encode surfaces.
+    ///
+    /// Default: `EncodeOptions::default()`
+    pub encode: EncodeOptions,
+    /// Which kind of header to write.
+    ///
+    /// Default: [`HeaderVersion::Preserve`]
+    pub header: HeaderVersion,
+    /// How to handle mipmaps.
+    ///
+    /// Note: Generating mipmaps for volumes is not supported. For volumes,
+    /// mipmaps are always converted as with [`MipmapMode::Convert`].
+    ///
+    /// Default: [`MipmapMode::Convert`]
+    pub mipmaps: MipmapMode,
+    /// Whether the alpha channel (if any) is straight alpha. This is only
+    /// used when generating mipmaps.
+    ///
+    /// See [`WriteOptions::resize_straight_alpha`].
+    ///
+    /// Default: `true`
+    pub resize_straight_alpha: bool,
+    /// The filter to use when generating mipmaps.
+    ///
+    /// Default: [`ResizeFilter::Box`]
+    pub resize_filter: ResizeFilter,
+}
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            encode: EncodeOptions::default(),
+            header: HeaderVersion::Preserve,
+            mipmaps: MipmapMode::Convert,
+            resize_straight_alpha: true,
+            resize_filter: ResizeFilter::Box,
+        }
+    }
+}
+
+/// Converts an entire DDS file to a different format.
+///
+/// The header of the new file is derived from the header of the decoder.
+/// Dimensions, mipmaps, cube map faces, array elements, and volume depth are
+/// preserved (unless changed by [`ConvertOptions::mipmaps`]). Each surface is
+/// decoded with the native color format of the source file and encoded again
+/// in the target format. The new file (header and data) is written to the
+/// writer.
+///
+/// The decoder is rewound to the start before converting, so it does not
+/// matter which surfaces have already been read. After the operation
+/// completes successfully, all surfaces of the decoder will have been read.
+///
+/// Progress is reported for the whole file. Surfaces are weighted by their
+/// number of pixels.
+///
+/// Each surface is decoded into a buffer that holds the entire surface. This
+/// buffer counts towards the [`crate::DecodeOptions::memory_limit`] of the
+/// decoder, so the limit may have to be raised to convert large files.
+///
+/// If the target format cannot be represented with the requested
+/// [`HeaderVersion`], [`ConvertError::UnsupportedHeader`] is returned.
+pub fn convert<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    writer: &mut dyn Write,
+    target: Format,
+    mut progress: Option<&mut Progress>,
+    options: &ConvertOptions,
+) -> Result<(), ConvertError> {
+    let source_header = decoder.header();
+    let is_volume = decoder.layout().volume().is_some();
+    let mipmaps = if is_volume {
+        MipmapMode::Convert
+    } else {
+        options.mipmaps
+    };
+
+    let mut header = target_header(source_header, target, options.header)?;
+    if mipmaps == MipmapMode::Full {
+        header = header.with_mipmaps();
+    }
+    let generate_mipmaps = mipmaps != MipmapMode::Convert;
+
+    decoder.rewind_to_start()?;
+    let mut encoder = Encoder::new(writer, target, &header)?;
+    encoder.options = options.encode.clone();
+    let write_options = WriteOptions {
+        generate_mipmaps,
+        resize_straight_alpha: options.resize_straight_alpha,
+        resize_filter: options.resize_filter,
+    };
+
+    // Surfaces are weighted by their number of pixels for progress.
+    let mut surfaces = SurfaceIterator::new(encoder.layout());
+    let total_pixels = total_pixels(surfaces.clone()).max(1);
+    let mut done_pixels = 0;
+
+    let color = decoder.native_color();
+    let mut buffer = Vec::new();
+    while let Some(info) = decoder.surface_info() {
+        if generate_mipmaps && info.is_mipmap() {
+            decoder.skip_mipmaps()?;
+            continue;
+        }
+
+        let size = info.size();
+        let len = size
+            .pixels()
+            .checked_mul(color.bytes_per_pixel() as u64)
+            .ok_or(DecodingError::Layout(LayoutError::DataLayoutTooBig))?;
+        if len > decoder.options.memory_limit as u64 {
+            return Err(DecodingError::MemoryLimitExceeded.into());
+        }
+        buffer.resize(len as usize, 0);
+        let view = ImageViewMut::new(&mut buffer, size, color).expect("invalid buffer");
+        decoder.read_surface(view)?;
+
+        let pixels = surface_pixels(surfaces.clone(), generate_mipmaps);
+        let range = ProgressRange::from_to(
+            done_pixels as f32 / total_pixels as f32,
+            (done_pixels + pixels).min(total_pixels) as f32 / total_pixels as f32,
+        );
+        done_pixels += pixels;
+
+        let view = ImageView::new(&buffer, size, color).expect("invalid buffer");
+        encoder.write_surface_with(
+            view,
+            sub_progress(&mut progress, range).as_mut(),
+            &write_options,
+        )?;
+
+        // keep the iterator in sync with the encoder
+        advance_surface(&mut surfaces, generate_mipmaps);
+    }
+
+    encoder.finish()?;
+    progress.report(1.0);
+
+    Ok(())
+}
+
+/// Creates the header of the converted file.
+fn target_header(
+    source: &Header,
+    target: Format,
+    version: HeaderVersion,
+) -> Result<Header, ConvertError> {
+    fn to_dx9(source: &Header, target: Format) -> Option<Header> {
+        let pixel_format = Dx9PixelFormat::try_from(target).ok()?;
+        let header = match source {
+            Header::Dx9(header) => header.clone().with_pixel_format(pixel_format),
+            Header::Dx10(header) => {
+                if header.array_size != 1 {
+                    // DX9 does not support texture arrays
+                    return None;
+                }
+                let (width, height) = (header.width, header.height);
+                let new = if header.is_cube_map() {
+                    Dx9Header::new_cube_map(width, height, pixel_format)
+                } else if let Some(depth) = header.depth {
+                    Dx9Header::new_volume(width, height, depth, pixel_format)
+                } else {
+                    Dx9Header::new_image(width, height, pixel_format)
+                };
+                new.with_mipmap_count(header.mipmap_count)
+            }
+        };
+        Some(header.into())
+    }
+    fn to_dx10(source: &Header, target: Format) -> Option<Header> {
+        let dxgi_format = DxgiFormat::try_from(target).ok()?;
+        let header = match source {
+            Header::Dx9(header) => {
+                let (width, height) = (header.width, header.height);
+                let new = match header.cube_map_faces() {
+                    Some(CubeMapFaces::ALL) => Dx10Header::new_cube_map(width, height, dxgi_format),
+                    // DX10 does not support partial cube maps
+                    Some(_) => return None,
+                    None => match header.depth {
+                        Some(depth) => Dx10Header::new_volume(width, height, depth, dxgi_format),
+                        None => Dx10Header::new_image(width, height, dxgi_format),
+                    },
+                };
+                new.with_mipmap_count(header.mipmap_count)
+            }
+            Header::Dx10(header) => header.clone().with_dxgi_format(dxgi_format),
+        };
+        Some(header.into())
+    }
+
+    let header = match version {
+        HeaderVersion::Preserve => match source {
+            Header::Dx9(_) => to_dx9(source, target).or_else(|| to_dx10(source, target)),
+            Header::Dx10(_) => to_dx10(source, target).or_else(|| to_dx9(source, target)),
+        },
+        HeaderVersion::Dx9 => to_dx9(source, target),
+        HeaderVersion::Dx10 => to_dx10(source, target),
+    };
+
+    header.ok_or(ConvertError::UnsupportedHeader(target))
+}
+
+/// Advances the iterator past the current surface. If `with_mipmaps` is
+/// true, the mipmaps of the current surface are skipped as well.
+fn advance_surface(iter: &mut SurfaceIterator, with_mipmaps: bool) {
+    iter.advance();
+    if with_mipmaps {
+        while iter.current().map_or(false, |info| info.is_mipmap()) {
+            iter.advance();
+        }
+    }
+}
+/// Counts the pixels of the current surface (and its mipmaps if
+/// `with_mipmaps` is true).
+fn surface_pixels(mut iter: SurfaceIterator, with_mipmaps: bool) -> u64 {
+    let mut pixels = 0;
+    while let Some(info) = iter.current() {
+        if pixels > 0 && !(with_mipmaps && info.is_mipmap()) {
+            break;
+        }
+        pixels += info.size().pixels();
+        iter.advance();
+    }
+    pixels
+}
+/// Counts the pixels of all remaining surfaces.
+fn total_pixels(mut iter: SurfaceIterator) -> u64 {
+    let mut pixels = 0;
+    while let Some(info) = iter.current() {
+        pixels += info.size().pixels();
+        iter.advance();
+    }
+    pixels
+}
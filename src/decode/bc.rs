@@ -5,7 +5,7 @@ use super::{Args, Decoder, DecoderSet, RArgs};
 use crate::{NormConvert, WithPrecision};
 
 use crate::util::closure_types;
-use crate::{Channels::*, ColorFormat};
+use crate::{Channels::*, ColorFormat, Precision};
 
 // helpers
 
@@ -155,7 +155,8 @@ pub(crate) const BC4_SNORM: DecoderSet = DecoderSet::new(&[
     gray!(u8, 8, blocks::bc4s_gray),
     gray!(u16, 8, blocks::bc4s_gray),
     gray!(f32, 8, blocks::bc4s_gray),
-]);
+])
+.snorm(Precision::U8);
 
 pub(crate) const BC5_UNORM: DecoderSet = DecoderSet::new(&[
     rgb!(u8, 16, blocks::bc5u_rgb),
@@ -167,7 +168,8 @@ pub(crate) const BC5_SNORM: DecoderSet = DecoderSet::new(&[
     rgb!(u8, 16, blocks::bc5s_rgb),
     rgb!(u16, 16, blocks::bc5s_rgb),
     rgb!(f32, 16, blocks::bc5s_rgb),
-]);
+])
+.snorm(Precision::U8);
 
 pub(crate) const BC6H_UF16: DecoderSet = DecoderSet::new(&[
     rgb!(f32, 16, blocks::bc6_u_f32),
@@ -187,7 +189,7 @@ pub(crate) const BC7_UNORM: DecoderSet = DecoderSet::new(&[
 ]);
 
 /// Internal module for the underlying logic of decoding BC1-7 blocks.
-mod blocks {
+pub(crate) mod blocks {
     // use crate::decode::convert::{bc6h_uf16, fp16, n4, n8, s8, Norm, ToRgba, B5G6R5};
     use crate::{bc6h_uf16, fp16, n4, n8, s8, Norm, ToRgba, B5G6R5};
 
@@ -232,7 +234,10 @@ mod blocks {
         pixels
     }
 
-    fn bc1_no_default_u8_rgba(block_bytes: [u8; 8]) -> [[u8; 4]; 16] {
+    /// Decodes the color block of BC2 and BC3 into 16 RGBA pixels.
+    ///
+    /// Unlike BC1, these color blocks always use 4 colors.
+    pub(crate) fn bc1_no_default_u8_rgba(block_bytes: [u8; 8]) -> [[u8; 4]; 16] {
         // https://learn.microsoft.com/en-us/windows/win32/direct3d10/d3d10-graphics-programming-guide-resources-block-compression#bc1
         let color0_u16 = u16::from_le_bytes([block_bytes[0], block_bytes[1]]);
         let color1_u16 = u16::from_le_bytes([block_bytes[2], block_bytes[3]]);
@@ -2,10 +2,11 @@ use std::io::{Read, Seek};
 use std::mem::size_of;
 
 use crate::{
-    Channels, ColorFormat, ColorFormatSet, DecodingError, ImageViewMut, Precision, Rect, Size,
+    cast, convert_channels, convert_channels_for, fp, ChannelMapping, Channels, ColorFormat,
+    ColorFormatSet, DecodingError, ImageViewMut, Precision, Rect, Size,
 };
 
-use super::DecodeOptions;
+use super::{DecodeOptions, ToneMapping};
 
 pub(crate) type DecodeFn = fn(args: Args) -> Result<(), DecodingError>;
 pub(crate) type DecodeRectFn = fn(args: RArgs) -> Result<(), DecodingError>;
@@ -151,6 +152,9 @@ struct SpecializedDecodeFn {
 pub(crate) struct DecoderSet {
     decoders: &'static [Decoder],
     optimized: Option<SpecializedDecodeFn>,
+    /// For SNORM formats, half the denominator of all values decoded to F32.
+    /// See [`DecoderSet::snorm`].
+    snorm: Option<f32>,
 }
 impl DecoderSet {
     pub const fn new(decoders: &'static [Decoder]) -> Self {
@@ -160,6 +164,7 @@ impl DecoderSet {
         Self {
             decoders,
             optimized: None,
+            snorm: None,
         }
     }
     #[cfg(debug_assertions)]
@@ -196,8 +201,24 @@ impl DecoderSet {
                 decode_fn,
                 color: ColorFormat::new(channels, precision),
             }),
+            snorm: self.snorm,
         }
     }
+    /// Marks the format as SNORM with the given precision of its values.
+    ///
+    /// SNORM values are decoded to F32 as `(x + 1) / 2`. For 8-bit SNORM
+    /// (including BC4 and BC5), all decoded values are multiples of
+    /// `1/8890` (`8890 = 254 * 5 * 7`), and for 16-bit SNORM, multiples of
+    /// `1/65534`. This allows [`DecodeOptions::signed_snorm`] to recover the
+    /// exact signed value.
+    pub const fn snorm(mut self, precision: Precision) -> Self {
+        self.snorm = Some(match precision {
+            Precision::U8 => 4445.0,
+            Precision::U16 => 32767.0,
+            Precision::F32 => panic!("SNORM formats have at most 16 bits"),
+        });
+        self
+    }
 
     pub const fn native_color(&self) -> ColorFormat {
         self.decoders[0].native_color
@@ -216,22 +237,87 @@ impl DecoderSet {
             .expect("All color formats should be supported")
     }
 
+    /// Decodes the image with the given options.
+    ///
+    /// `strip_height` is the number of rows that can be decoded independently
+    /// of the rows after them. If post-processing requires an intermediate
+    /// buffer, the image is decoded in strips of a multiple of this height.
+    /// `None` means that the image has to be decoded at once.
     pub fn decode(
         &self,
         reader: &mut dyn Read,
         mut image: ImageViewMut,
+        strip_height: Option<u32>,
         options: &DecodeOptions,
     ) -> Result<(), DecodingError> {
         let color = image.color();
         let size = image.size();
+        let output = image.data();
+
+        let post = match PostProcess::new(self, color, options) {
+            Some(post) => post,
+            None => return self.decode_plain(reader, color, size, output, options.memory_limit),
+        };
+
+        let work = post.work_color(color);
+        if work == color {
+            self.decode_plain(reader, color, size, output, options.memory_limit)?;
+            post.apply(work, output);
+            return Ok(());
+        }
+        if size.is_empty() {
+            return self.decode_plain(reader, color, size, output, options.memory_limit);
+        }
+
+        // decode to an intermediate buffer first, one strip of rows at a time
+        const STRIP_BYTES: usize = 1 << 20;
+        let strip_rows = match strip_height {
+            Some(height) => {
+                let row_bytes = size.width as usize * work.bytes_per_pixel() as usize;
+                let rows = (STRIP_BYTES / row_bytes).min(size.height as usize) as u32;
+                (rows / height * height).max(height).min(size.height)
+            }
+            None => size.height,
+        };
 
+        let mut context = DecodeContext {
+            color,
+            size,
+            memory_limit: options.memory_limit,
+        };
+        let mut buffer: Box<[u8]> =
+            context.alloc(buffer_len(work, Size::new(size.width, strip_rows))?)?;
+
+        let output_strip_len = size.width as usize * color.bytes_per_pixel() as usize;
+        let output_strip_len = output_strip_len * strip_rows as usize;
+        for (y, output) in (0..size.height)
+            .step_by(strip_rows as usize)
+            .zip(output.chunks_mut(output_strip_len))
+        {
+            let strip = Size::new(size.width, strip_rows.min(size.height - y));
+            let buffer = &mut buffer[..buffer_len(work, strip)?];
+
+            self.decode_plain(reader, work, strip, buffer, context.memory_limit)?;
+            post.apply(work, buffer);
+            convert_color(work, color, buffer, output);
+        }
+        Ok(())
+    }
+    fn decode_plain(
+        &self,
+        reader: &mut dyn Read,
+        color: ColorFormat,
+        size: Size,
+        output: &mut [u8],
+        memory_limit: usize,
+    ) -> Result<(), DecodingError> {
         let args = Args::new(
             reader,
-            image.data(),
+            output,
             DecodeContext {
                 color,
                 size,
-                memory_limit: options.memory_limit,
+                memory_limit,
             },
         )?;
 
@@ -261,6 +347,83 @@ impl DecoderSet {
         output: &mut [u8],
         row_pitch: usize,
         options: &DecodeOptions,
+    ) -> Result<(), DecodingError> {
+        let post = match PostProcess::new(self, color, options) {
+            Some(post) => post,
+            None => {
+                return self.decode_rect_plain(
+                    color,
+                    reader,
+                    size,
+                    rect,
+                    output,
+                    row_pitch,
+                    options.memory_limit,
+                )
+            }
+        };
+
+        let row_bytes = rect.width as usize * color.bytes_per_pixel() as usize;
+        let rows = (0..rect.height as usize).map(|y| y * row_pitch..y * row_pitch + row_bytes);
+
+        let work = post.work_color(color);
+        if work == color {
+            self.decode_rect_plain(
+                color,
+                reader,
+                size,
+                rect,
+                output,
+                row_pitch,
+                options.memory_limit,
+            )?;
+            for row in rows {
+                post.apply(work, &mut output[row]);
+            }
+            return Ok(());
+        }
+
+        // validate the output before decoding anything
+        let context = || DecodeContext {
+            color,
+            size,
+            memory_limit: options.memory_limit,
+        };
+        RArgs::new(reader, output, row_pitch, rect, context())?;
+        if rect.size().is_empty() {
+            return Ok(());
+        }
+
+        // decode to an intermediate buffer first
+        let mut context = context();
+        let mut buffer: Box<[u8]> = context.alloc(buffer_len(work, rect.size())?)?;
+        let work_row_pitch = rect.width as usize * work.bytes_per_pixel() as usize;
+
+        self.decode_rect_plain(
+            work,
+            reader,
+            size,
+            rect,
+            &mut buffer,
+            work_row_pitch,
+            context.memory_limit,
+        )?;
+        post.apply(work, &mut buffer);
+        for (row, work_row) in rows.zip(buffer.chunks(work_row_pitch)) {
+            convert_color(work, color, work_row, &mut output[row]);
+        }
+        Ok(())
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn decode_rect_plain(
+        &self,
+        color: ColorFormat,
+        reader: &mut dyn ReadSeek,
+        size: Size,
+        rect: Rect,
+        output: &mut [u8],
+        row_pitch: usize,
+        memory_limit: usize,
     ) -> Result<(), DecodingError> {
         let args = RArgs::new(
             reader,
@@ -270,7 +433,7 @@ impl DecoderSet {
             DecodeContext {
                 color,
                 size,
-                memory_limit: options.memory_limit,
+                memory_limit,
             },
         )?;
 
@@ -283,3 +446,121 @@ impl DecoderSet {
         (decoder.decode_rect_fn)(args)
     }
 }
+
+/// Processing steps that are applied after decoding.
+struct PostProcess {
+    mapping: ChannelMapping,
+    tone_mapping: Option<ToneMapping>,
+    snorm: Option<f32>,
+}
+impl PostProcess {
+    /// Returns `None` if there is nothing to do.
+    fn new(set: &DecoderSet, color: ColorFormat, options: &DecodeOptions) -> Option<Self> {
+        // tone mapping is only necessary if HDR values would be clamped
+        let native = set.native_color();
+        let tone_mapping = options
+            .tone_mapping
+            .filter(|_| native.precision == Precision::F32 && color.precision != Precision::F32);
+        let snorm = set
+            .snorm
+            .filter(|_| options.signed_snorm && color.precision == Precision::F32);
+
+        if options.channel_mapping.is_identity() && tone_mapping.is_none() && snorm.is_none() {
+            return None;
+        }
+        Some(Self {
+            mapping: options.channel_mapping,
+            tone_mapping,
+            snorm,
+        })
+    }
+
+    /// The color format the image has to be decoded to before the
+    /// post-processing steps can be applied.
+    fn work_color(&self, color: ColorFormat) -> ColorFormat {
+        // the mapping needs all channels
+        let channels = if self.mapping.is_identity() {
+            color.channels
+        } else {
+            Channels::Rgba
+        };
+        // tone mapping needs the HDR values
+        let precision = if self.tone_mapping.is_some() {
+            Precision::F32
+        } else {
+            color.precision
+        };
+        ColorFormat::new(channels, precision)
+    }
+
+    fn apply(&self, work: ColorFormat, bytes: &mut [u8]) {
+        if let Some(tone_mapping) = &self.tone_mapping {
+            tone_mapping.apply(work.channels, bytes);
+        }
+        if let Some(half) = self.snorm {
+            // Round to the nearest multiple of 1/(2*half) to get the exact
+            // SNORM value and then map it to -1 to 1.
+            let values: &mut [[u8; 4]] = cast::from_bytes_mut(bytes).expect("invalid buffer");
+            for value in values {
+                let x = f32::from_ne_bytes(*value);
+                *value = (((x * (2.0 * half)).round() - half) / half).to_ne_bytes();
+            }
+        }
+        // the mapping comes last, so its constants are exact output values
+        self.mapping.apply_rgba_bytes(work.precision, bytes);
+    }
+}
+
+/// The number of bytes of a buffer with the given color and size.
+fn buffer_len(color: ColorFormat, size: Size) -> Result<usize, DecodingError> {
+    usize::try_from(size.pixels())
+        .ok()
+        .and_then(|pixels| pixels.checked_mul(color.bytes_per_pixel() as usize))
+        .ok_or(DecodingError::MemoryLimitExceeded)
+}
+
+/// Converts the channels and the precision of the given pixels.
+///
+/// Only conversions from F32 to other precisions are supported.
+fn convert_color(from: ColorFormat, to: ColorFormat, from_buffer: &[u8], to_buffer: &mut [u8]) {
+    if from.precision == to.precision {
+        convert_channels_for(from, to.channels, from_buffer, to_buffer);
+        return;
+    }
+    debug_assert_eq!(from.precision, Precision::F32);
+
+    const CHUNK_PIXELS: usize = 256;
+    let mut values = [0_f32; CHUNK_PIXELS * 4];
+
+    let from_bytes = from.bytes_per_pixel() as usize;
+    let to_bytes = to.bytes_per_pixel() as usize;
+    let to_channels = to.channels.count() as usize;
+    for (from_chunk, to_chunk) in from_buffer
+        .chunks(CHUNK_PIXELS * from_bytes)
+        .zip(to_buffer.chunks_mut(CHUNK_PIXELS * to_bytes))
+    {
+        let pixels = from_chunk.len() / from_bytes;
+        let values = &mut values[..pixels * to_channels];
+        convert_channels::<f32>(
+            from.channels,
+            to.channels,
+            from_chunk,
+            cast::as_bytes_mut(values),
+        );
+
+        match to.precision {
+            Precision::U8 => {
+                for (out, value) in to_chunk.iter_mut().zip(values.iter()) {
+                    *out = fp::n8(*value);
+                }
+            }
+            Precision::U16 => {
+                let out: &mut [[u8; 2]] = cast::from_bytes_mut(to_chunk).expect("invalid buffer");
+                for (out, value) in out.iter_mut().zip(values.iter()) {
+                    *out = fp::n16(*value).to_ne_bytes();
+                }
+            }
+            Precision::F32 => unreachable!(),
+        }
+    }
+}
@@ -7,18 +7,21 @@ mod bi_planar;
 mod decoder;
 mod read_write;
 mod sub_sampled;
+mod tone_mapping;
 mod uncompressed;
 
 use std::io::{Read, Seek};
 
 use astc::*;
+pub(crate) use bc::blocks as bc_blocks;
 use bc::*;
 use bi_planar::*;
 pub(crate) use decoder::*;
 use sub_sampled::*;
+pub use tone_mapping::*;
 use uncompressed::*;
 
-use crate::{ColorFormat, DecodingError, Format, ImageViewMut, Rect, Size};
+use crate::{ChannelMapping, ColorFormat, DecodingError, Format, ImageViewMut, Rect, Size};
 
 pub(crate) const fn get_decoders(format: Format) -> DecoderSet {
     match format {
@@ -133,7 +136,13 @@ pub fn decode(
     format: Format,
     options: &DecodeOptions,
 ) -> Result<(), DecodingError> {
-    get_decoders(format).decode(reader, image, options)
+    // the number of rows that can be decoded without the rows after them
+    let strip_height = match crate::PixelInfo::from(format) {
+        crate::PixelInfo::Fixed { .. } => Some(1),
+        crate::PixelInfo::Block(info) => Some(info.size().1 as u32),
+        crate::PixelInfo::BiPlanar(_) => None,
+    };
+    get_decoders(format).decode(reader, image, strip_height, options)
 }
 
 /// Decodes a rectangle of the image data of a surface from the given reader
@@ -189,6 +198,8 @@ pub struct DecodeOptions {
     /// [`NV12`](https://learn.microsoft.com/en-us/windows/win32/medfound/recommended-8-bit-yuv-formats-for-video-rendering#nv12)
     /// is a bi-planar format and decoding it generally requires reading the
     /// entire Y plane into memory. This can be a problem for large images.
+    /// [`crate::Decoder::read_surface_rows`] can be used to decode such images
+    /// in strips with bounded memory.
     ///
     /// Default: 33 MiB
     ///
@@ -196,11 +207,67 @@ pub struct DecodeOptions {
     /// and `P010` images. All other formats require at most 256 KiB for 16K
     /// images.)
     pub memory_limit: usize,
+    /// How the channels of the decoded image are built from the channels of
+    /// the decoded format.
+    ///
+    /// This is the decoding counterpart of
+    /// [`crate::EncodeOptions::channel_mapping`]. See [`ChannelMapping`] for
+    /// details.
+    ///
+    /// If the output color format is not RGBA, the image is decoded to RGBA
+    /// first. [`decode`] does this in strips of rows with a buffer of about
+    /// 1 MiB, while [`decode_rect`] requires a buffer for the entire
+    /// rectangle. This buffer counts towards [`DecodeOptions::memory_limit`].
+    ///
+    /// Default: [`ChannelMapping::IDENTITY`]
+    pub channel_mapping: ChannelMapping,
+    /// How HDR values are mapped to the range 0 to 1.
+    ///
+    /// This only applies when a format that natively decodes to
+    /// [`Precision::F32`](crate::Precision::F32) (e.g. `BC6H_UF16`,
+    /// `R16G16B16A16_FLOAT`, `R9G9B9E5_SHAREDEXP`) is decoded to
+    /// [`Precision::U8`](crate::Precision::U8) or
+    /// [`Precision::U16`](crate::Precision::U16). If `None`, values outside
+    /// the range 0 to 1 are simply clamped.
+    ///
+    /// Tone mapping is applied to the color channels before
+    /// [`DecodeOptions::channel_mapping`]. The alpha channel is only clamped.
+    ///
+    /// Like [`DecodeOptions::channel_mapping`], tone mapping requires an
+    /// intermediate buffer with F32 precision, which counts towards
+    /// [`DecodeOptions::memory_limit`]. See there for its size.
+    ///
+    /// Default: `None`
+    pub tone_mapping: Option<ToneMapping>,
+    /// Whether SNORM formats are decoded to the range -1 to 1.
+    ///
+    /// By default, SNORM values are remapped to the range 0 to 1 for all
+    /// precisions, i.e. -1 is decoded as 0, 0 as 0.5, and 1 as 1. If this
+    /// option is enabled, SNORM formats (e.g. `R8_SNORM`, `R16G16_SNORM`,
+    /// `BC5_SNORM`) decoded to [`Precision::F32`](crate::Precision::F32) keep
+    /// their signed values instead. This is typically what consumers of
+    /// normal maps want. Channels that the format doesn't store are 0 (e.g.
+    /// blue of `R8G8_SNORM`) or 1 (alpha).
+    ///
+    /// The values are exact, e.g. the SNORM8 value 64 is decoded as the f32
+    /// closest to 64/127. [`crate::EncodeOptions::signed_snorm`] is the
+    /// encoding counterpart of this option.
+    ///
+    /// This option is ignored for non-SNORM formats and U8/U16 outputs. It is
+    /// applied before [`DecodeOptions::channel_mapping`], so the mapping
+    /// operates on signed values. E.g. [`crate::ChannelSource::Zero`] is
+    /// decoded as 0 and [`crate::ChannelSource::InvertedRed`] as `1 - R`.
+    ///
+    /// Default: `false`
+    pub signed_snorm: bool,
 }
 impl Default for DecodeOptions {
     fn default() -> Self {
         Self {
             memory_limit: 33 * 1024 * 1024,
+            channel_mapping: ChannelMapping::IDENTITY,
+            tone_mapping: None,
+            signed_snorm: false,
         }
     }
 }
@@ -0,0 +1,136 @@
+use std::hash::{Hash, Hasher};
+
+use crate::{cast, linear_to_srgb, Channels};
+
+/// The operator used to map HDR values to the range 0 to 1.
+///
+/// See [`ToneMapping::operator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ToneMapOperator {
+    /// Values are clamped to the range 0 to 1.
+    ///
+    /// This is the same as decoding without tone mapping, except that the
+    /// exposure and sRGB encoding of [`ToneMapping`] are applied.
+    Clamp,
+    /// The Reinhard operator `x / (1 + x)`.
+    ///
+    /// This compresses all values into the range 0 to 1, so no detail is lost
+    /// in bright areas. The image will look darker and less saturated than
+    /// with other operators.
+    #[default]
+    Reinhard,
+    /// An approximation of the ACES filmic tone curve.
+    ///
+    /// This gives a contrasty, film-like look and is a good choice for
+    /// previews of HDR environment maps.
+    AcesFilmic,
+}
+impl ToneMapOperator {
+    fn apply(self, x: f32) -> f32 {
+        // negative values (and NaN) can't be displayed
+        let x = x.max(0.0);
+        match self {
+            Self::Clamp => x.min(1.0),
+            Self::Reinhard => x / (1.0 + x),
+            Self::AcesFilmic => {
+                // Krzysztof Narkowicz's fit of the ACES curve
+                const A: f32 = 2.51;
+                const B: f32 = 0.03;
+                const C: f32 = 2.43;
+                const D: f32 = 0.59;
+                const E: f32 = 0.14;
+                ((x * (A * x + B)) / (x * (C * x + D) + E)).min(1.0)
+            }
+        }
+    }
+}
+
+/// Options for mapping HDR values to the range 0 to 1 when decoding.
+///
+/// See [`crate::DecodeOptions::tone_mapping`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct ToneMapping {
+    /// The tone map operator.
+    ///
+    /// Default: [`ToneMapOperator::Reinhard`]
+    pub operator: ToneMapOperator,
+    /// The exposure in stops (EV).
+    ///
+    /// All color values are multiplied by `2^exposure` before the operator is
+    /// applied. E.g. an exposure of `1.0` doubles the brightness of the image
+    /// and an exposure of `-1.0` halves it.
+    ///
+    /// Default: `0.0`
+    pub exposure: f32,
+    /// Whether to encode the tone-mapped values with the sRGB transfer
+    /// function.
+    ///
+    /// HDR formats store linear values, so this should be enabled if the
+    /// decoded image is displayed directly or saved as a regular (sRGB)
+    /// image.
+    ///
+    /// Default: `false`
+    pub srgb: bool,
+}
+impl ToneMapping {
+    /// Creates tone mapping options with the given operator and default
+    /// values for all other options.
+    pub fn new(operator: ToneMapOperator) -> Self {
+        Self {
+            operator,
+            ..Default::default()
+        }
+    }
+
+    fn map(&self, scale: f32, x: f32) -> f32 {
+        let x = self.operator.apply(x * scale);
+        if self.srgb {
+            linear_to_srgb(x)
+        } else {
+            x
+        }
+    }
+
+    /// Applies the tone mapping to the color channels of the given F32
+    /// pixels in place. Alpha is left unchanged.
+    pub(crate) fn apply(&self, channels: Channels, bytes: &mut [u8]) {
+        let scale = self.exposure.exp2();
+        let values: &mut [[u8; 4]] = cast::from_bytes_mut(bytes).expect("invalid buffer");
+        let color_channels = match channels {
+            Channels::Grayscale | Channels::Rgb => channels.count() as usize,
+            Channels::Rgba => 3,
+            Channels::Alpha => return,
+        };
+
+        for pixel in values.chunks_exact_mut(channels.count() as usize) {
+            for value in &mut pixel[..color_channels] {
+                *value = self.map(scale, f32::from_ne_bytes(*value)).to_ne_bytes();
+            }
+        }
+    }
+}
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            operator: ToneMapOperator::Reinhard,
+            exposure: 0.0,
+            srgb: false,
+        }
+    }
+}
+impl PartialEq for ToneMapping {
+    fn eq(&self, other: &Self) -> bool {
+        self.operator == other.operator
+            && self.exposure.to_bits() == other.exposure.to_bits()
+            && self.srgb == other.srgb
+    }
+}
+impl Eq for ToneMapping {}
+impl Hash for ToneMapping {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.operator.hash(state);
+        self.exposure.to_bits().hash(state);
+        self.srgb.hash(state);
+    }
+}
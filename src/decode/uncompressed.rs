@@ -187,7 +187,8 @@ pub(crate) const R8G8B8A8_SNORM: DecoderSet = DecoderSet::new(&[
     rgba!(u16, [u8; 4], process_fn = S8_TO_U16),
     rgba!(f32, [u8; 4], process_fn = S8_TO_F32),
 ])
-.add_specialized(Rgba, U8, COPY_S8);
+.add_specialized(Rgba, U8, COPY_S8)
+.snorm(U8);
 
 pub(crate) const B8G8R8A8_UNORM: DecoderSet = DecoderSet::new(&[
     rgba!(u8, [u8; 4], |bgra| bgra.swap_rb()),
@@ -270,7 +271,8 @@ pub(crate) const R8_SNORM: DecoderSet = DecoderSet::new(&[
     gray!(u16, [u8; 1], process_fn = S8_TO_U16),
     gray!(f32, [u8; 1], process_fn = S8_TO_F32),
 ])
-.add_specialized(Grayscale, U8, COPY_S8);
+.add_specialized(Grayscale, U8, COPY_S8)
+.snorm(U8);
 
 pub(crate) const R8G8_UNORM: DecoderSet = DecoderSet::new(&[
     rgb!(u8, [u8; 2], |rg| [rg[0], rg[1], 0]),
@@ -286,7 +288,8 @@ pub(crate) const R8G8_SNORM: DecoderSet = DecoderSet::new(&[
         s8::uf32(g),
         Norm::HALF
     ]),
-]);
+])
+.snorm(U8);
 
 pub(crate) const A8_UNORM: DecoderSet = DecoderSet::new(&[
     alpha!(u8, [u8; 1], process_fn = N8_TO_U8),
@@ -306,7 +309,8 @@ pub(crate) const R16_SNORM: DecoderSet = DecoderSet::new(&[
     gray!(u16, [u16; 1], process_fn = S16_TO_U16),
     gray!(u8, [u16; 1], process_fn = S16_TO_U8),
     gray!(f32, [u16; 1], process_fn = S16_TO_F32),
-]);
+])
+.snorm(U16);
 
 pub(crate) const R16G16_UNORM: DecoderSet = DecoderSet::new(&[
     rgb!(u16, [u16; 2], |rg| [rg[0], rg[1], 0]),
@@ -326,7 +330,8 @@ pub(crate) const R16G16_SNORM: DecoderSet = DecoderSet::new(&[
         s16::uf32(g),
         Norm::HALF
     ]),
-]);
+])
+.snorm(U16);
 
 pub(crate) const R16G16B16A16_UNORM: DecoderSet = DecoderSet::new(&[
     rgba!(u16, [u16; 4], process_fn = N16_TO_U16),
@@ -339,7 +344,8 @@ pub(crate) const R16G16B16A16_SNORM: DecoderSet = DecoderSet::new(&[
     rgba!(u16, [u16; 4], process_fn = S16_TO_U16),
     rgba!(u8, [u16; 4], process_fn = S16_TO_U8),
     rgba!(f32, [u16; 4], process_fn = S16_TO_F32),
-]);
+])
+.snorm(U16);
 
 #[inline(always)]
 fn unpack_rgba1010102(rgba: u32) -> (u16, u16, u16, u8) {
@@ -4,10 +4,25 @@ use crate::{
     decode, decode_rect,
     header::{Header, ParseOptions},
     iter::{SurfaceInfo, SurfaceIterator},
-    util, ColorFormat, CubeMapFaces, DataLayout, DecodeOptions, DecodingError, Format,
-    ImageViewMut, Rect, Size, TextureArrayKind,
+    resize::{resize_buffer_bytes, Aligner, ResizeState},
+    util, Box3, Channels, ColorFormat, CubeMapFaces, DataLayout, DataRegion, DecodeOptions,
+    DecodingError, Format, ImageView, ImageViewMut, LayoutError, PixelInfo, Rect, ResizeFilter,
+    Size, TextureArrayKind,
 };
 
+/// The position of each cube map face in the unfolded cube (in units of
+/// faces), in the order faces are stored in DDS files.
+///
+/// See [`Decoder::read_cube_map`] for the layout.
+const CUBE_MAP_FACE_OFFSETS: [(CubeMapFaces, u32, u32); 6] = [
+    (CubeMapFaces::POSITIVE_X, 2, 1),
+    (CubeMapFaces::NEGATIVE_X, 0, 1),
+    (CubeMapFaces::POSITIVE_Y, 1, 0),
+    (CubeMapFaces::NEGATIVE_Y, 1, 2),
+    (CubeMapFaces::POSITIVE_Z, 1, 1),
+    (CubeMapFaces::NEGATIVE_Z, 3, 1),
+];
+
 /// A decoder for reading the pixel data of a DDS file.
 ///
 /// See crate-level documentation for usage examples.
@@ -157,6 +172,171 @@ impl<R> Decoder<R> {
         Ok(())
     }
 
+    /// Reads the next surface in horizontal strips and passes each strip to
+    /// the given callback.
+    ///
+    /// This is useful for surfaces that are too large to be decoded into
+    /// memory at once. Only a single strip of `rows_per_chunk` rows is kept in
+    /// memory, and the same buffer is reused for all strips. The callback
+    /// receives the y coordinate of the first row of the strip and the decoded
+    /// rows. All strips have `rows_per_chunk` rows, except for the last one,
+    /// which may be shorter.
+    ///
+    /// `rows_per_chunk` is rounded up to a multiple of the block height of the
+    /// format (e.g. 4 for BC1), so that no block row has to be decoded twice.
+    ///
+    /// The strip buffer counts towards [`DecodeOptions::memory_limit`]. Since
+    /// every strip is decoded with [`decode_rect`], formats that usually need
+    /// to buffer large parts of the surface (e.g. the Y plane of `NV12`) only
+    /// need memory proportional to the strip size.
+    ///
+    /// Similarly to [`Decoder::read_surface`], this operation will consume the
+    /// current surface and advance to the next one.
+    pub fn read_surface_rows(
+        &mut self,
+        color: ColorFormat,
+        rows_per_chunk: u32,
+        mut on_rows: impl FnMut(u32, ImageView),
+    ) -> Result<(), DecodingError>
+    where
+        R: Read + Seek,
+    {
+        let current = self.iter.current().ok_or(DecodingError::NoMoreSurfaces)?;
+        let size = current.size();
+        let surface_len = current.data_len();
+
+        let block_height = match self.layout.pixel_info() {
+            PixelInfo::Fixed { .. } => 1,
+            PixelInfo::Block(block) => block.size().1 as u32,
+            PixelInfo::BiPlanar(bi_planar) => bi_planar.plane2_sub_sampling().1 as u32,
+        };
+        let rows_per_chunk = util::div_ceil(rows_per_chunk.max(1), block_height)
+            .saturating_mul(block_height)
+            .min(size.height);
+
+        // the strip buffer is part of the memory budget
+        let row_pitch = size.width as usize * color.bytes_per_pixel() as usize;
+        let buffer_len = row_pitch.saturating_mul(rows_per_chunk as usize);
+        if buffer_len > self.options.memory_limit {
+            return Err(DecodingError::MemoryLimitExceeded);
+        }
+        let mut options = self.options.clone();
+        options.memory_limit -= buffer_len;
+        let mut buffer = vec![0_u8; buffer_len];
+
+        let mut y = 0;
+        while y < size.height {
+            if y > 0 {
+                // decoding a rect leaves the reader at the end of the surface
+                self.reader
+                    .seek(std::io::SeekFrom::Current(-(surface_len as i64)))?;
+            }
+
+            let rows = rows_per_chunk.min(size.height - y);
+            let strip = &mut buffer[..row_pitch * rows as usize];
+            decode_rect(
+                &mut self.reader,
+                strip,
+                row_pitch,
+                color,
+                size,
+                Rect::new(0, y, size.width, rows),
+                self.format,
+                &options,
+            )?;
+
+            let view = ImageView::new(strip, Size::new(size.width, rows), color)
+                .expect("strip buffer has the correct size");
+            on_rows(y, view);
+
+            y += rows;
+        }
+
+        self.iter.advance();
+        Ok(())
+    }
+
+    /// Reads a box of the given mipmap level of a volume into the given buffer.
+    ///
+    /// The box is read one depth slice at a time. Slice `z` of the box is
+    /// written to `buffer[(z - volume_box.z) * slice_pitch..]` with the given
+    /// `row_pitch` (see [`decode_rect`]). Depth slices and rows outside the
+    /// box are skipped by seeking, so they are never decoded.
+    ///
+    /// Unlike [`Decoder::read_surface_rect`], this method does not need to be
+    /// called at a specific position. It seeks to the requested mipmap level
+    /// and, after reading, leaves the decoder at the first depth slice of the
+    /// next mipmap level (or at the end of the file). So reading a box of
+    /// level 0 and then a box of level 1 only ever seeks forward.
+    ///
+    /// Returns [`DecodingError::NotAVolume`] if the DDS file is not a volume.
+    pub fn read_volume_box(
+        &mut self,
+        level: u8,
+        buffer: &mut [u8],
+        row_pitch: usize,
+        slice_pitch: usize,
+        volume_box: Box3,
+        color: ColorFormat,
+    ) -> Result<(), DecodingError>
+    where
+        R: Read + Seek,
+    {
+        let volume = self.layout.volume().ok_or(DecodingError::NotAVolume)?;
+        let mip = volume
+            .get(level)
+            .ok_or(DecodingError::MipmapLevelOutOfBounds(level))?;
+
+        if !volume_box.is_within_bounds(mip.size(), mip.depth()) {
+            return Err(DecodingError::BoxOutOfBounds);
+        }
+
+        // Check the slice pitch and buffer length before touching the reader.
+        // Row pitch and the rest of the rect are validated by `decode_rect`.
+        if !volume_box.is_empty() {
+            let min_slice_pitch = usize::saturating_mul(row_pitch, volume_box.height as usize);
+            if slice_pitch < min_slice_pitch {
+                return Err(DecodingError::SlicePitchTooSmall {
+                    required_minimum: min_slice_pitch,
+                });
+            }
+            let required_bytes =
+                usize::saturating_mul(slice_pitch, (volume_box.depth - 1) as usize)
+                    .saturating_add(min_slice_pitch);
+            if buffer.len() < required_bytes {
+                return Err(DecodingError::RectBufferTooSmall {
+                    required_minimum: required_bytes,
+                });
+            }
+        }
+
+        let mut position = self.iter.elapsed_bytes();
+        if !volume_box.is_empty() {
+            for z in 0..volume_box.depth {
+                // PANIC SAFETY: the box was checked to be within bounds
+                let slice = mip.get_depth_slice(volume_box.z + z).unwrap();
+                seek_relative(&mut self.reader, position, slice.data_offset())?;
+
+                let output = &mut buffer[z as usize * slice_pitch..];
+                decode_rect(
+                    &mut self.reader,
+                    output,
+                    row_pitch,
+                    color,
+                    slice.size(),
+                    volume_box.rect(),
+                    self.format,
+                    &self.options,
+                )?;
+                position = slice.data_end();
+            }
+        }
+        seek_relative(&mut self.reader, position, mip.data_end())?;
+
+        self.iter.set_volume_level(level + 1);
+        Ok(())
+    }
+
     /// Skips over the next surface.
     ///
     /// Returns an error if there are no more surfaces.
@@ -248,22 +428,13 @@ impl<R> Decoder<R> {
             return Err(DecodingError::UnexpectedSurfaceSize);
         }
 
-        let face_offsets = [
-            (CubeMapFaces::POSITIVE_X, 2, 1),
-            (CubeMapFaces::NEGATIVE_X, 0, 1),
-            (CubeMapFaces::POSITIVE_Y, 1, 0),
-            (CubeMapFaces::NEGATIVE_Y, 1, 2),
-            (CubeMapFaces::POSITIVE_Z, 1, 1),
-            (CubeMapFaces::NEGATIVE_Z, 3, 1),
-        ];
-
         let color = image.color();
         let bytes_per_pixel = color.bytes_per_pixel() as usize;
         let row_pitch = image.row_pitch();
         let rect = Rect::new(0, 0, face_size.width, face_size.height);
         let image_bytes = image.data();
 
-        for (_, x, y) in face_offsets
+        for (_, x, y) in CUBE_MAP_FACE_OFFSETS
             .into_iter()
             .filter(|(face, _, _)| faces.contains(*face))
         {
@@ -283,6 +454,217 @@ impl<R> Decoder<R> {
         Ok(())
     }
 
+    /// Returns the largest size that fits within `max_size`x`max_size` pixels
+    /// while keeping the aspect ratio of the preview image of this DDS file.
+    ///
+    /// The preview image is the level 0 texture for textures and texture
+    /// arrays, the first depth slice for volumes, and the unfolded cube (see
+    /// [`Self::read_cube_map`]) for cube maps. Previews are never upscaled, so
+    /// small images will return their own size.
+    ///
+    /// This is intended to be used with [`Self::read_thumbnail_into`].
+    pub fn thumbnail_size(&self, max_size: u32) -> Size {
+        let size = self.preview_size(0);
+        let longest = size.width.max(size.height);
+        if longest <= max_size {
+            return size;
+        }
+
+        let scale = |x: u32| -> u32 {
+            let scaled = (x as u64 * max_size as u64 + longest as u64 / 2) / longest as u64;
+            (scaled as u32).max(1)
+        };
+        Size::new(scale(size.width), scale(size.height))
+    }
+
+    /// Reads a preview of the DDS file that fits within `max_size`x`max_size`
+    /// pixels.
+    ///
+    /// This is a convenience wrapper around [`Self::thumbnail_size`] and
+    /// [`Self::read_thumbnail_into`] using [`ResizeFilter::Box`]. The
+    /// thumbnail is returned as a tightly packed buffer of the given color
+    /// format together with its size.
+    pub fn read_thumbnail(
+        &mut self,
+        max_size: u32,
+        color: ColorFormat,
+    ) -> Result<(Vec<u8>, Size), DecodingError>
+    where
+        R: Read + Seek,
+    {
+        let size = self.thumbnail_size(max_size);
+        let len = size.pixels() * color.bytes_per_pixel() as u64;
+        let len = usize::try_from(len).map_err(|_| LayoutError::DataLayoutTooBig)?;
+        let mut thumbnail = vec![0_u8; len];
+
+        let image = ImageViewMut::new(&mut thumbnail, size, color)
+            .expect("thumbnail buffer has the correct size");
+        self.read_thumbnail_into(image, ResizeFilter::Box)?;
+
+        Ok((thumbnail, size))
+    }
+
+    /// Reads a preview of the DDS file and resizes it to the size of the given
+    /// image.
+    ///
+    /// The preview image is determined as follows:
+    ///
+    /// - Textures: The texture itself.
+    /// - Texture arrays: The first texture in the array.
+    /// - Cube maps: The faces of the first cube map arranged as an unfolded
+    ///   cube. See [`Self::read_cube_map`] for the exact layout.
+    /// - Volumes: The first depth slice.
+    ///
+    /// Instead of decoding the full-resolution image, the decoder picks the
+    /// smallest mipmap level that is at least as large as the given image and
+    /// skips all other surfaces. The selected level is then resized to the
+    /// size of the given image using the given filter. If the selected level
+    /// already has the right size, it is used as is.
+    ///
+    /// Use [`Self::thumbnail_size`] to get a size that keeps the aspect ratio
+    /// of the preview.
+    ///
+    /// The preview is read independently of the current surface. After
+    /// reading, the decoder is moved back to the surface it was at before.
+    ///
+    /// The buffers for the selected level and for resizing count towards
+    /// [`DecodeOptions::memory_limit`].
+    pub fn read_thumbnail_into(
+        &mut self,
+        mut image: ImageViewMut,
+        filter: ResizeFilter,
+    ) -> Result<(), DecodingError>
+    where
+        R: Read + Seek,
+    {
+        let target = image.size();
+        if target.is_empty() {
+            return Ok(());
+        }
+
+        let mipmaps = match self.layout {
+            DataLayout::Texture(texture) => texture.mipmaps(),
+            DataLayout::Volume(volume) => volume.mipmaps(),
+            DataLayout::TextureArray(array) => array.mipmaps(),
+        };
+        let level = (0..mipmaps)
+            .rev()
+            .find(|&level| {
+                let size = self.preview_size(level);
+                size.width >= target.width && size.height >= target.height
+            })
+            .unwrap_or(0);
+
+        let color = image.color();
+        let preview_size = self.preview_size(level);
+        let preview_len = preview_size
+            .pixels()
+            .saturating_mul(color.bytes_per_pixel() as u64);
+        let resize_len = if preview_size == target {
+            0
+        } else {
+            resize_buffer_bytes(preview_size, target, color)
+        };
+        if preview_len.saturating_add(resize_len) > self.options.memory_limit as u64 {
+            return Err(DecodingError::MemoryLimitExceeded);
+        }
+        let mut preview = vec![0_u8; preview_len as usize];
+
+        // remember the current surface, so we can return to it afterward
+        let position = self.reader.stream_position()?;
+        let iter = self.iter.clone();
+
+        self.rewind_to_start()?;
+        let result = self.read_preview(level, &mut preview, preview_size, color);
+        self.reader.seek(std::io::SeekFrom::Start(position))?;
+        self.iter = iter;
+        result?;
+
+        if preview_size == target {
+            image.data().copy_from_slice(&preview);
+        } else {
+            let preview = ImageView::new(&preview, preview_size, color)
+                .expect("preview buffer has the correct size");
+            let mut aligner = Aligner::new();
+            let mut resize = ResizeState::new();
+            let src = aligner.align(preview);
+            let straight_alpha = color.channels == Channels::Rgba;
+            let resized = resize.resize(&src, target, straight_alpha, filter);
+            image.data().copy_from_slice(resized);
+        }
+
+        Ok(())
+    }
+
+    /// The size of the preview image at the given mipmap level.
+    fn preview_size(&self, level: u8) -> Size {
+        let size = self.main_size().get_mipmap(level);
+        if self.layout.is_cube_map() {
+            Size::new(size.width.saturating_mul(4), size.height.saturating_mul(3))
+        } else {
+            size
+        }
+    }
+
+    /// Reads the preview image at the given mipmap level. The decoder has to
+    /// be at the start of the data section.
+    fn read_preview(
+        &mut self,
+        level: u8,
+        buffer: &mut [u8],
+        size: Size,
+        color: ColorFormat,
+    ) -> Result<(), DecodingError>
+    where
+        R: Read + Seek,
+    {
+        match self.layout {
+            DataLayout::Texture(_) | DataLayout::TextureArray(_) if !self.layout.is_cube_map() => {
+                for _ in 0..level {
+                    self.skip_surface()?;
+                }
+                self.read_surface(ImageViewMut::new(buffer, size, color).unwrap())
+            }
+            DataLayout::Volume(volume) => {
+                for mip in volume.iter_mips().take(level as usize) {
+                    for _ in 0..mip.depth() {
+                        self.skip_surface()?;
+                    }
+                }
+                self.read_surface(ImageViewMut::new(buffer, size, color).unwrap())
+            }
+            DataLayout::Texture(_) | DataLayout::TextureArray(_) => {
+                let faces = match self.layout.texture_array().map(|array| array.kind()) {
+                    Some(TextureArrayKind::PartialCubeMap(faces)) => faces,
+                    _ => CubeMapFaces::ALL,
+                };
+
+                let face_size = self.main_size().get_mipmap(level);
+                let bytes_per_pixel = color.bytes_per_pixel() as usize;
+                let row_pitch = size.width as usize * bytes_per_pixel;
+                let rect = Rect::new(0, 0, face_size.width, face_size.height);
+
+                for (_, x, y) in CUBE_MAP_FACE_OFFSETS
+                    .into_iter()
+                    .filter(|(face, _, _)| faces.contains(*face))
+                {
+                    for _ in 0..level {
+                        self.skip_surface()?;
+                    }
+
+                    let offset_x = x * face_size.width;
+                    let offset_y = y * face_size.height;
+                    let offset =
+                        offset_y as usize * row_pitch + offset_x as usize * bytes_per_pixel;
+
+                    self.read_surface_rect(&mut buffer[offset..], row_pitch, rect, color)?;
+                    self.skip_mipmaps()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
     /// Moves to the reader back the previous surface, allowing it to be read
     /// again.
     ///
@@ -321,6 +703,24 @@ impl<R> Decoder<R> {
         Ok(())
     }
 
+    /// Reads the raw (encoded) data section with the given function.
+    ///
+    /// The reader passed to `read` is positioned at the start of the data
+    /// section. Afterwards, the decoder is rewound to the start.
+    pub(crate) fn read_raw_data<T>(
+        &mut self,
+        read: impl FnOnce(&mut R) -> std::io::Result<T>,
+    ) -> Result<T, DecodingError>
+    where
+        R: Read + Seek,
+    {
+        self.rewind_to_start()?;
+        let start = self.reader.stream_position()?;
+        let result = read(&mut self.reader);
+        self.reader.seek(std::io::SeekFrom::Start(start))?;
+        Ok(result?)
+    }
+
     /// Returns information about the surface about to be read.
     ///
     /// The returned value is not valid after calling `next_surface`.
@@ -348,3 +748,11 @@ impl<R> Decoder<R> {
         self.reader
     }
 }
+
+/// Seeks the reader from one offset in the data section to another.
+fn seek_relative<R: Seek>(reader: &mut R, from: u64, to: u64) -> std::io::Result<()> {
+    if from != to {
+        reader.seek(std::io::SeekFrom::Current(to as i64 - from as i64))?;
+    }
+    Ok(())
+}
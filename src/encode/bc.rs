@@ -8,17 +8,25 @@ use crate::{
 
 use super::{
     bc1, bc4, bcn_util,
-    encoder::{Args, Encoder, EncoderSet, Flags},
+    encoder::{transform_input, Args, Encoder, EncoderSet, Flags},
+    normalize_weights,
+    rdo::{BlockPart, BlockSource, Rdo},
     CompressionQuality, EncodeOptions, ErrorMetric, PreferredGroupSize,
 };
 
+/// Encodes a single block. The source pixels of each part are recorded for
+/// RDO.
+type EncodeBlock<const BLOCK_BYTES: usize> =
+    fn(&[[f32; 4]], usize, &EncodeOptions, &mut [u8; BLOCK_BYTES], &mut BlockSource);
+
 fn block_universal<
     const BLOCK_WIDTH: usize,
     const BLOCK_HEIGHT: usize,
     const BLOCK_BYTES: usize,
 >(
     args: Args,
-    encode_block: fn(&[[f32; 4]], usize, &EncodeOptions, &mut [u8; BLOCK_BYTES]),
+    parts: &'static [BlockPart],
+    encode_block: EncodeBlock<BLOCK_BYTES>,
 ) -> Result<(), EncodingError> {
     let Args {
         data,
@@ -34,6 +42,8 @@ fn block_universal<
 
     let mut intermediate_buffer = vec![[0_f32; 4]; width * BLOCK_HEIGHT];
     let mut encoded_buffer = vec![[0_u8; BLOCK_BYTES]; util::div_ceil(width, BLOCK_WIDTH)];
+    let mut rdo = options.rdo.map(|rdo| Rdo::<BLOCK_BYTES>::new(rdo, parts));
+    let mut source = BlockSource::new(rdo.is_some());
 
     // Report frequencies were chosen manually.
     // I just tried to pick frequencies such that every quality level reports
@@ -47,11 +57,12 @@ fn block_universal<
     };
     let block_count = util::div_ceil(width, BLOCK_WIDTH) * util::div_ceil(height, BLOCK_HEIGHT);
     let mut block_index: usize = 0;
-    let mut report_block = || {
+    let mut report_block = || -> Result<(), EncodingError> {
         if block_index % report_frequency == 0 {
-            progress.report(block_index as f32 / block_count as f32);
+            progress.checked_report(block_index as f32 / block_count as f32)?;
         }
         block_index += 1;
+        Ok(())
     };
 
     let row_pitch = width * bytes_per_pixel;
@@ -65,6 +76,7 @@ fn block_universal<
             line_group,
             &mut intermediate_buffer[..rows_in_group * width],
         );
+        transform_input(&options, &mut intermediate_buffer[..rows_in_group * width]);
         for i in 0..(BLOCK_HEIGHT - rows_in_group) {
             // copy the first line to fill the rest
             intermediate_buffer.copy_within(..width, (rows_in_group + i) * width);
@@ -77,8 +89,11 @@ fn block_universal<
             let block = &intermediate_buffer[block_start..];
             let encoded = &mut encoded_buffer[block_index];
 
-            encode_block(block, width, &options, encoded);
-            report_block();
+            encode_block(block, width, &options, encoded, &mut source);
+            if let Some(rdo) = &mut rdo {
+                rdo.optimize(encoded, &source);
+            }
+            report_block()?;
         }
 
         // handle last partial block
@@ -98,8 +113,11 @@ fn block_universal<
             }
 
             let encoded = &mut encoded_buffer[block_index];
-            encode_block(&block_data, BLOCK_WIDTH, &options, encoded);
-            report_block();
+            encode_block(&block_data, BLOCK_WIDTH, &options, encoded, &mut source);
+            if let Some(rdo) = &mut rdo {
+                rdo.optimize(encoded, &source);
+            }
+            report_block()?;
         }
 
         writer.write_all(cast::as_bytes(&encoded_buffer))?;
@@ -160,13 +178,26 @@ const BC1_GROUP_SIZE: PreferredGroupSize = PreferredGroupSize::group(256 * 256,
 const BC3_GROUP_SIZE: PreferredGroupSize = BC1_GROUP_SIZE.combine(BC4_GROUP_SIZE);
 const BC4_GROUP_SIZE: PreferredGroupSize = PreferredGroupSize::group(512 * 256, 128 * 128, 8 * 8);
 
+// The 8-byte parts of each block format for RDO.
+const BC1_PARTS: &[BlockPart] = &[BlockPart::Bc1];
+const BC2_PARTS: &[BlockPart] = &[BlockPart::Bc2Alpha, BlockPart::Bc1FourColor];
+const BC3_PARTS: &[BlockPart] = &[BlockPart::Bc4 { snorm: false }, BlockPart::Bc1FourColor];
+const BC4U_PARTS: &[BlockPart] = &[BlockPart::Bc4 { snorm: false }];
+const BC4S_PARTS: &[BlockPart] = &[BlockPart::Bc4 { snorm: true }];
+const BC5U_PARTS: &[BlockPart] = &[BlockPart::Bc4 { snorm: false }; 2];
+const BC5S_PARTS: &[BlockPart] = &[BlockPart::Bc4 { snorm: true }; 2];
+
 // encoders
 
 fn get_bc1_options(options: &EncodeOptions) -> bc1::Bc1Options {
     bc1::Bc1Options {
         dither: options.dithering.color(),
         perceptual: options.error_metric == ErrorMetric::Perceptual,
-        opaque_always_p4: options.quality <= CompressionQuality::Normal,
+        weights: options.channel_weights.rgb(),
+        alpha_threshold: options.bc.alpha_threshold,
+        opaque_always_p4: !options.bc.opaque_three_color
+            || options.quality <= CompressionQuality::Normal,
+        three_color_black: options.bc.opaque_three_color && options.bc.three_color_black,
         refine_max_iter: match options.quality {
             CompressionQuality::Fast => 0,
             CompressionQuality::Normal => 3,
@@ -176,19 +207,34 @@ fn get_bc1_options(options: &EncodeOptions) -> bc1::Bc1Options {
     }
 }
 pub(crate) const BC1_UNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-    block_universal::<4, 4, 8>(args, |data, row_pitch, options, out| {
+    block_universal::<4, 4, 8>(args, BC1_PARTS, |data, row_pitch, options, out, source| {
         let bc1_options = get_bc1_options(options);
         let mut block = get_4x4_rgba(data, row_pitch);
 
         if options.dithering.alpha() {
             let alpha = get_alpha(&block);
             bcn_util::block_dither(&alpha, |i, pixel| {
-                let alpha = if pixel >= 0.5 { 1.0 } else { 0.0 };
+                let alpha = if pixel >= bc1_options.alpha_threshold {
+                    1.0
+                } else {
+                    0.0
+                };
                 block[i][3] = alpha;
                 alpha
             });
         }
 
+        // transparent pixels decode as black
+        let threshold = bc1_options.alpha_threshold;
+        let visible = block.map(|[r, g, b, a]| {
+            if a >= threshold {
+                [r, g, b, 1.0]
+            } else {
+                [0.0; 4]
+            }
+        });
+        source.set_color(0, &visible, true);
+
         *out = bc1::compress_bc1_block(block, bc1_options);
     })
 })
@@ -219,11 +265,14 @@ fn bc2_alpha(alpha: [f32; 16], options: &EncodeOptions) -> [u8; 8] {
 }
 
 pub(crate) const BC2_UNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-    block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
+    block_universal::<4, 4, 16>(args, BC2_PARTS, |data, row_pitch, options, out, source| {
         let (bc1_options, _) = get_bc3_options(options);
 
         let block = get_4x4_rgba(data, row_pitch);
 
+        source.set_single(0, &get_alpha(&block));
+        source.set_color(1, &block, false);
+
         let alpha_block = bc2_alpha(get_alpha(&block), options);
         let bc1_block = bc1::compress_bc1_block(block, bc1_options);
 
@@ -235,12 +284,15 @@ pub(crate) const BC2_UNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_unive
 
 pub(crate) const BC2_UNORM_PREMULTIPLIED_ALPHA: EncoderSet =
     EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-        block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
+        block_universal::<4, 4, 16>(args, BC2_PARTS, |data, row_pitch, options, out, source| {
             let (bc1_options, _) = get_bc3_options(options);
 
             let mut block = get_4x4_rgba(data, row_pitch);
             pre_multiply_alpha(&mut block);
 
+            source.set_single(0, &get_alpha(&block));
+            source.set_color(1, &block, false);
+
             let alpha_block = bc2_alpha(get_alpha(&block), options);
             let bc1_block = bc1::compress_bc1_block(block, bc1_options);
 
@@ -254,17 +306,20 @@ fn get_bc3_options(options: &EncodeOptions) -> (bc1::Bc1Options, bc4::Bc4Options
     let mut bc1_options = get_bc1_options(options);
     bc1_options.no_default = true;
 
-    let mut bc4_options = get_bc4_options(options);
+    let mut bc4_options = get_bc4_options_for_channel(options, 3);
     bc4_options.snorm = false;
 
     (bc1_options, bc4_options)
 }
 pub(crate) const BC3_UNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-    block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
+    block_universal::<4, 4, 16>(args, BC3_PARTS, |data, row_pitch, options, out, source| {
         let (bc1_options, bc4_options) = get_bc3_options(options);
 
         let block = get_4x4_rgba(data, row_pitch);
 
+        source.set_single(0, &get_alpha(&block));
+        source.set_color(1, &block, false);
+
         let bc4_block = bc4::compress_bc4_block(get_alpha(&block), bc4_options);
         let bc1_block = bc1::compress_bc1_block(block, bc1_options);
 
@@ -276,12 +331,15 @@ pub(crate) const BC3_UNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_unive
 
 pub(crate) const BC3_UNORM_PREMULTIPLIED_ALPHA: EncoderSet =
     EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-        block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
+        block_universal::<4, 4, 16>(args, BC3_PARTS, |data, row_pitch, options, out, source| {
             let (bc1_options, bc4_options) = get_bc3_options(options);
 
             let mut block = get_4x4_rgba(data, row_pitch);
             pre_multiply_alpha(&mut block);
 
+            source.set_single(0, &get_alpha(&block));
+            source.set_color(1, &block, false);
+
             let bc4_block = bc4::compress_bc4_block(get_alpha(&block), bc4_options);
             let bc1_block = bc1::compress_bc1_block(block, bc1_options);
 
@@ -293,8 +351,14 @@ pub(crate) const BC3_UNORM_PREMULTIPLIED_ALPHA: EncoderSet =
 
 pub(crate) const BC3_UNORM_RXGB: EncoderSet =
     EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-        block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
-            let (bc1_options, bc4_options) = get_bc3_options(options);
+        block_universal::<4, 4, 16>(args, BC3_PARTS, |data, row_pitch, options, out, source| {
+            let (mut bc1_options, _) = get_bc3_options(options);
+            let mut bc4_options = get_bc4_options_for_channel(options, 0);
+            bc4_options.snorm = false;
+            // R is constant in the color block, so only G and B are weighted
+            let [_, g, b, _] = options.channel_weights.0;
+            let [g, b] = normalize_weights([g, b]);
+            bc1_options.weights = [1.0, g, b];
 
             let block_r = get_4x4_select_channel::<0>(data, row_pitch);
             let mut block_gb = get_4x4_rgba(data, row_pitch);
@@ -309,6 +373,9 @@ pub(crate) const BC3_UNORM_RXGB: EncoderSet =
                 pixel[0] = 1.0;
             });
 
+            source.set_single(0, &block_r);
+            source.set_color(1, &block_gb, false);
+
             let bc4_block = bc4::compress_bc4_block(block_r, bc4_options);
             let bc1_block = bc1::compress_bc1_block(block_gb, bc1_options);
 
@@ -320,8 +387,12 @@ pub(crate) const BC3_UNORM_RXGB: EncoderSet =
 
 pub(crate) const BC3_UNORM_NORMAL: EncoderSet =
     EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-        block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
-            let (bc1_options, bc4_options) = get_bc3_options(options);
+        block_universal::<4, 4, 16>(args, BC3_PARTS, |data, row_pitch, options, out, source| {
+            let (mut bc1_options, _) = get_bc3_options(options);
+            let mut bc4_options = get_bc4_options_for_channel(options, 0);
+            bc4_options.snorm = false;
+            // only G varies in the color block, so weights don't matter
+            bc1_options.weights = [1.0; 3];
 
             let block_a = get_4x4_select_channel::<0>(data, row_pitch);
             let mut block_rgb = get_4x4_rgba(data, row_pitch);
@@ -331,6 +402,9 @@ pub(crate) const BC3_UNORM_NORMAL: EncoderSet =
                 pixel[3] = 1.0;
             });
 
+            source.set_single(0, &block_a);
+            source.set_color(1, &block_rgb, false);
+
             let bc4_block = bc4::compress_bc4_block(block_a, bc4_options);
             let bc1_block = bc1::compress_bc1_block(block_rgb, bc1_options);
 
@@ -340,68 +414,113 @@ pub(crate) const BC3_UNORM_NORMAL: EncoderSet =
     .add_flags(Flags::DITHER_COLOR)
     .with_group_size(BC3_GROUP_SIZE)]);
 
-fn handle_bc4(data: &[[f32; 4]], row_pitch: usize, options: bc4::Bc4Options) -> [u8; 8] {
+fn handle_bc4(
+    data: &[[f32; 4]],
+    row_pitch: usize,
+    options: bc4::Bc4Options,
+    source: &mut BlockSource,
+) -> [u8; 8] {
     let block = get_4x4_grayscale(data, row_pitch);
+    source.set_single(0, &block);
     bc4::compress_bc4_block(block, options)
 }
 fn get_bc4_options(options: &EncodeOptions) -> bc4::Bc4Options {
+    get_bc4_options_with(options, options.quality)
+}
+fn get_bc4_options_with(options: &EncodeOptions, quality: CompressionQuality) -> bc4::Bc4Options {
     bc4::Bc4Options {
         dither: options.dithering.color(),
         snorm: false,
-        brute_force: options.quality == CompressionQuality::Unreasonable,
-        use_inter4: options.quality > CompressionQuality::Fast,
+        brute_force: quality == CompressionQuality::Unreasonable,
+        use_inter4: quality > CompressionQuality::Fast,
         use_inter4_heuristic: true,
-        high_quality_quantize: options.quality >= CompressionQuality::High,
-        fast_iter: options.quality <= CompressionQuality::Normal,
+        high_quality_quantize: quality >= CompressionQuality::High,
+        fast_iter: quality <= CompressionQuality::Normal,
     }
 }
+/// Returns the BC4 options for a single channel (R, G, B, A) that is encoded
+/// as its own BC4 block.
+///
+/// Since the channel is encoded independently, its weight can't trade error
+/// with other channels. Instead, less important channels are encoded with a
+/// lower quality. See [`crate::ChannelWeights`].
+fn get_bc4_options_for_channel(options: &EncodeOptions, channel: usize) -> bc4::Bc4Options {
+    let weight = options.channel_weights.normalized(channel);
+    let lower_by = if weight >= 0.5 {
+        0
+    } else if weight >= 0.1 {
+        1
+    } else if weight > 0.0 {
+        2
+    } else {
+        3
+    };
+    let quality = match (options.quality as u8).saturating_sub(lower_by) {
+        0 => CompressionQuality::Fast,
+        1 => CompressionQuality::Normal,
+        2 => CompressionQuality::High,
+        _ => CompressionQuality::Unreasonable,
+    };
+    get_bc4_options_with(options, quality)
+}
 
 pub(crate) const BC4_UNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-    block_universal::<4, 4, 8>(args, |data, row_pitch, options, out| {
+    block_universal::<4, 4, 8>(args, BC4U_PARTS, |data, row_pitch, options, out, source| {
         let mut options = get_bc4_options(options);
         options.snorm = false;
-        *out = handle_bc4(data, row_pitch, options);
+        *out = handle_bc4(data, row_pitch, options, source);
     })
 })
 .add_flags(Flags::DITHER_COLOR)
 .with_group_size(BC4_GROUP_SIZE)]);
 
 pub(crate) const BC4_SNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-    block_universal::<4, 4, 8>(args, |data, row_pitch, options, out| {
+    block_universal::<4, 4, 8>(args, BC4S_PARTS, |data, row_pitch, options, out, source| {
         let mut options = get_bc4_options(options);
         options.snorm = true;
-        *out = handle_bc4(data, row_pitch, options);
+        *out = handle_bc4(data, row_pitch, options, source);
     })
 })
 .add_flags(Flags::DITHER_COLOR)
-.with_group_size(BC4_GROUP_SIZE)]);
+.with_group_size(BC4_GROUP_SIZE)])
+.snorm();
+
+fn handle_bc5(
+    data: &[[f32; 4]],
+    row_pitch: usize,
+    options: &EncodeOptions,
+    snorm: bool,
+    source: &mut BlockSource,
+) -> [u8; 16] {
+    let mut red_options = get_bc4_options_for_channel(options, 0);
+    red_options.snorm = snorm;
+    let mut green_options = get_bc4_options_for_channel(options, 1);
+    green_options.snorm = snorm;
 
-fn handle_bc5(data: &[[f32; 4]], row_pitch: usize, options: bc4::Bc4Options) -> [u8; 16] {
     let red_block = get_4x4_select_channel::<0>(data, row_pitch);
     let green_block = get_4x4_select_channel::<1>(data, row_pitch);
+    source.set_single(0, &red_block);
+    source.set_single(1, &green_block);
 
-    let red = bc4::compress_bc4_block(red_block, options);
-    let green = bc4::compress_bc4_block(green_block, options);
+    let red = bc4::compress_bc4_block(red_block, red_options);
+    let green = bc4::compress_bc4_block(green_block, green_options);
 
     concat_blocks(red, green)
 }
 
 pub(crate) const BC5_UNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-    block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
-        let mut options = get_bc4_options(options);
-        options.snorm = false;
-        *out = handle_bc5(data, row_pitch, options);
+    block_universal::<4, 4, 16>(args, BC5U_PARTS, |data, row_pitch, options, out, source| {
+        *out = handle_bc5(data, row_pitch, options, false, source);
     })
 })
 .add_flags(Flags::DITHER_COLOR)
 .with_group_size(BC4_GROUP_SIZE)]);
 
 pub(crate) const BC5_SNORM: EncoderSet = EncoderSet::new_bc(&[Encoder::new_universal(|args| {
-    block_universal::<4, 4, 16>(args, |data, row_pitch, options, out| {
-        let mut options = get_bc4_options(options);
-        options.snorm = true;
-        *out = handle_bc5(data, row_pitch, options);
+    block_universal::<4, 4, 16>(args, BC5S_PARTS, |data, row_pitch, options, out, source| {
+        *out = handle_bc5(data, row_pitch, options, true, source);
     })
 })
 .add_flags(Flags::DITHER_COLOR)
-.with_group_size(BC4_GROUP_SIZE)]);
+.with_group_size(BC4_GROUP_SIZE)])
+.snorm();
@@ -14,7 +14,16 @@ pub struct Bc1Options {
     pub no_default: bool,
     pub alpha_threshold: f32,
     pub perceptual: bool,
+    /// The weights of the R, G, and B channels. Ignored if `perceptual` is
+    /// `true`. The largest weight should be 1.
+    pub weights: [f32; 3],
+    /// Setting this to `true` will disable the regular 3-color mode for opaque
+    /// blocks.
     pub opaque_always_p4: bool,
+    /// Whether near-black pixels of opaque blocks may use the default color
+    /// (transparent black) of 3-color mode. This is independent of
+    /// `opaque_always_p4`.
+    pub three_color_black: bool,
     pub refine_max_iter: u8,
 }
 impl Default for Bc1Options {
@@ -24,7 +33,9 @@ impl Default for Bc1Options {
             no_default: false,
             alpha_threshold: 0.5,
             perceptual: false,
+            weights: [1.0; 3],
             opaque_always_p4: false,
+            three_color_black: false,
             refine_max_iter: 10,
         }
     }
@@ -51,6 +62,8 @@ pub(crate) fn compress_bc1_block(mut block: [[f32; 4]; 16], options: Bc1Options)
 
     if options.perceptual {
         compress(block, Perceptual, options)
+    } else if options.weights != [1.0; 3] {
+        compress(block, Weighted::new(options.weights), options)
     } else {
         compress(block, Uniform, options)
     }
@@ -75,18 +88,58 @@ fn compress(block: [[f32; 4]; 16], error_metric: impl ErrorMetric, options: Bc1O
     }
 
     // We have a choice to make. P3 or P4?
-    if options.opaque_always_p4 {
+    if options.opaque_always_p4 && !options.three_color_black {
         return compress_p4(colors, error_metric, options).0;
     }
 
-    // Just try both and pick whichever is better
-    let (p4, p4_error) = compress_p4(colors, error_metric, options);
-    let (p3, p3_error) = compress_p3_default(colors, alpha_map, error_metric, options);
-    if p4_error < p3_error {
-        p4
-    } else {
-        p3
+    // Just try all options and pick whichever is best
+    let (mut best, mut best_error) = compress_p4(colors, error_metric, options);
+    if !options.opaque_always_p4 {
+        let (p3, p3_error) = compress_p3_default(colors, alpha_map, error_metric, options);
+        if p3_error <= best_error {
+            best = p3;
+            best_error = p3_error;
+        }
+    }
+    if options.three_color_black {
+        if let Some((p3, p3_error)) = compress_p3_black(colors, error_metric, options) {
+            if p3_error < best_error {
+                best = p3;
+            }
+        }
     }
+    best
+}
+/// Compresses an opaque block in P3 mode, using the default color (black) for
+/// all near-black pixels.
+///
+/// Returns `None` if the block doesn't contain any near-black pixels.
+fn compress_p3_black(
+    block: [Vec3A; 16],
+    error_metric: impl ErrorMetric,
+    options: Bc1Options,
+) -> Option<([u8; 8], f32)> {
+    /// Pixels with all channels below this threshold are considered
+    /// near-black. This is 4 steps of an 8-bit channel.
+    const NEAR_BLACK: f32 = 4.0 / 255.0;
+
+    // near-black pixels are treated as transparent
+    let mut black_map = AlphaMap::ALL_TRANSPARENT;
+    let mut black_error = 0.0;
+    let black = error_metric.srgb_to_color_space(Vec3A::ZERO);
+    for (i, &pixel) in block.iter().enumerate() {
+        if pixel.max_element() < NEAR_BLACK {
+            black_error += error_metric.error_sq(error_metric.srgb_to_color_space(pixel), black);
+        } else {
+            black_map.set_opaque(i);
+        }
+    }
+    if black_map == AlphaMap::ALL_OPAQUE {
+        return None;
+    }
+
+    let (encoded, error) = compress_p3_default(block, black_map, error_metric, options);
+    Some((encoded, error + black_error))
 }
 fn compress_p4(
     block: [Vec3A; 16],
@@ -993,3 +1046,35 @@ impl ErrorMetric for Perceptual {
         fast_oklab_to_srgb(color.0)
     }
 }
+/// A uniform error metric with per-channel weights.
+///
+/// Colors are scaled by the square root of the weights, so the squared
+/// Euclidean distance in the scaled space is the weighted squared error.
+#[derive(Debug, Clone, Copy)]
+struct Weighted {
+    scale: Vec3A,
+    inv_scale: Vec3A,
+}
+impl Weighted {
+    /// Channels with a weight of 0 still get a tiny weight. This keeps the
+    /// color space invertible and breaks ties in favor of smaller errors.
+    const MIN_WEIGHT: f32 = 1e-4;
+
+    fn new(weights: [f32; 3]) -> Self {
+        let scale = Vec3A::from(weights.map(|w| w.max(Self::MIN_WEIGHT).sqrt()));
+        Self {
+            scale,
+            inv_scale: scale.recip(),
+        }
+    }
+}
+impl ErrorMetric for Weighted {
+    #[inline]
+    fn srgb_to_color_space(&self, color: Vec3A) -> ColorSpace {
+        ColorSpace(color * self.scale)
+    }
+    #[inline]
+    fn color_space_to_srgb(&self, color: ColorSpace) -> Vec3A {
+        color.0 * self.inv_scale
+    }
+}
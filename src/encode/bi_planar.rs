@@ -3,8 +3,8 @@
 use std::num::NonZeroU32;
 
 use super::{
-    encoder::{Args, Encoder, EncoderSet},
-    EncodeOptions,
+    encoder::{transform_input, Args, Encoder, EncoderSet},
+    EncodeOptions, PreferredGroupSize,
 };
 use crate::{
     cast::{self, ToLe},
@@ -48,7 +48,7 @@ fn bi_planar_universal<P1: ToLe + cast::Castable + Default + Copy, P2: ToLe + ca
     for (group_index, line_group) in data.chunks(row_pitch * BLOCK_HEIGHT).enumerate() {
         if group_index % report_frequency == 0 {
             // occasionally report progress
-            progress.report(group_index as f32 / line_group_count as f32);
+            progress.checked_report(group_index as f32 / line_group_count as f32)?;
         }
 
         debug_assert!(line_group.len() % row_pitch == 0);
@@ -60,6 +60,7 @@ fn bi_planar_universal<P1: ToLe + cast::Castable + Default + Copy, P2: ToLe + ca
             line_group,
             &mut intermediate_buffer[..rows_in_group * width],
         );
+        transform_input(&options, &mut intermediate_buffer[..rows_in_group * width]);
 
         // handle full blocks
         for macro_x in 0..width / BLOCK_WIDTH {
@@ -91,6 +92,40 @@ fn bi_planar_universal<P1: ToLe + cast::Castable + Default + Copy, P2: ToLe + ca
     Ok(())
 }
 
+/// Writes the independently encoded line groups of a bi-planar surface.
+///
+/// Each encoded fragment is a complete bi-planar surface of its own, so it
+/// contains its plane 1 followed by its plane 2. The plane 1 of all fragments
+/// is written first, followed by the plane 2 of all fragments. Since all
+/// fragments (except the last) have a multiple of the vertical sub-sampling
+/// lines, this results in the same data as encoding the whole surface at
+/// once.
+#[cfg(feature = "rayon")]
+pub(crate) fn write_bi_planar_fragments(
+    writer: &mut dyn std::io::Write,
+    info: crate::BiPlanarPixelInfo,
+    fragments: &[crate::ImageView],
+    encoded: &[Vec<u8>],
+) -> Result<(), EncodingError> {
+    debug_assert_eq!(fragments.len(), encoded.len());
+
+    let plane1_bytes = |fragment: &crate::ImageView| {
+        fragment.size().pixels() as usize * info.plane1_bytes_per_pixel() as usize
+    };
+
+    for (fragment, encoded) in fragments.iter().zip(encoded) {
+        writer.write_all(&encoded[..plane1_bytes(fragment)])?;
+    }
+    for (fragment, encoded) in fragments.iter().zip(encoded) {
+        writer.write_all(&encoded[plane1_bytes(fragment)..])?;
+    }
+
+    Ok(())
+}
+
+const BI_PLANAR_GROUP_SIZE: PreferredGroupSize =
+    PreferredGroupSize::group(512 * 512, 512 * 512, 512 * 512);
+
 // encoders
 
 pub(crate) const NV12: EncoderSet = EncoderSet::new_bi_planar(&[Encoder::new_universal(|args| {
@@ -103,7 +138,8 @@ pub(crate) const NV12: EncoderSet = EncoderSet::new_bi_planar(&[Encoder::new_uni
 
         (block_y, [u as u8, v as u8])
     })
-})]);
+})
+.with_group_size(BI_PLANAR_GROUP_SIZE)]);
 
 pub(crate) const P010: EncoderSet = EncoderSet::new_bi_planar(&[Encoder::new_universal(|args| {
     bi_planar_universal(args, |block, _| {
@@ -115,7 +151,8 @@ pub(crate) const P010: EncoderSet = EncoderSet::new_bi_planar(&[Encoder::new_uni
 
         (block_y, [u << 6, v << 6])
     })
-})]);
+})
+.with_group_size(BI_PLANAR_GROUP_SIZE)]);
 
 pub(crate) const P016: EncoderSet = EncoderSet::new_bi_planar(&[Encoder::new_universal(|args| {
     bi_planar_universal(args, |block, _| {
@@ -127,4 +164,5 @@ pub(crate) const P016: EncoderSet = EncoderSet::new_bi_planar(&[Encoder::new_uni
 
         (block_y, [u as u16, v as u16])
     })
-})]);
+})
+.with_group_size(BI_PLANAR_GROUP_SIZE)]);
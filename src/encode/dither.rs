@@ -0,0 +1,465 @@
+//! Dithering algorithms for uncompressed and sub-sampled formats.
+//!
+//! There are 2 kinds of algorithms:
+//!
+//! 1. Error diffusion (Floyd-Steinberg, Atkinson, Sierra). The quantization
+//!    error of each pixel is distributed to its neighbors to the right and
+//!    below. This gives the best quality, but every pixel depends on all
+//!    pixels before it.
+//! 2. Position-based (ordered, blue noise). A threshold that only depends on
+//!    the position of the pixel is added before quantization. Since pixels
+//!    are independent, surfaces can be split and encoded in parallel as long
+//!    as every fragment starts at a multiple of the pattern size.
+
+use glam::Vec4;
+
+use super::{Dithering, DitheringAlgorithm, PreferredGroupSize};
+
+/// A single entry of an error diffusion kernel: `(dx, dy, weight)`.
+type KernelEntry = (isize, usize, f32);
+
+const FLOYD_STEINBERG: &[KernelEntry] = &[
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0),
+    (0, 1, 5.0 / 16.0),
+    (1, 1, 1.0 / 16.0),
+];
+/// Atkinson only diffuses 3/4 of the error. This reduces bleeding and gives
+/// more contrast at the cost of detail in very light and dark areas.
+const ATKINSON: &[KernelEntry] = &[
+    (1, 0, 1.0 / 8.0),
+    (2, 0, 1.0 / 8.0),
+    (-1, 1, 1.0 / 8.0),
+    (0, 1, 1.0 / 8.0),
+    (1, 1, 1.0 / 8.0),
+    (0, 2, 1.0 / 8.0),
+];
+const SIERRA: &[KernelEntry] = &[
+    (1, 0, 5.0 / 32.0),
+    (2, 0, 3.0 / 32.0),
+    (-2, 1, 2.0 / 32.0),
+    (-1, 1, 4.0 / 32.0),
+    (0, 1, 5.0 / 32.0),
+    (1, 1, 4.0 / 32.0),
+    (2, 1, 2.0 / 32.0),
+    (-1, 2, 2.0 / 32.0),
+    (0, 2, 3.0 / 32.0),
+    (1, 2, 2.0 / 32.0),
+];
+
+/// The largest `|dx|` of all kernels.
+const PADDING: usize = 2;
+/// The largest `dy` of all kernels + 1.
+const ROWS: usize = 3;
+
+/// The error buffer of an error diffusion algorithm.
+pub(crate) struct ErrorDiffusion {
+    kernel: &'static [KernelEntry],
+    /// The accumulated error of the current row and the next rows.
+    rows: [Vec<Vec4>; ROWS],
+}
+impl ErrorDiffusion {
+    /// Returns `None` if the algorithm is not an error diffusion algorithm.
+    pub fn new(algorithm: DitheringAlgorithm, width: usize) -> Option<Self> {
+        let kernel = match algorithm {
+            DitheringAlgorithm::FloydSteinberg => FLOYD_STEINBERG,
+            DitheringAlgorithm::Atkinson => ATKINSON,
+            DitheringAlgorithm::Sierra => SIERRA,
+            DitheringAlgorithm::Ordered | DitheringAlgorithm::BlueNoise => return None,
+        };
+        let row = vec![Vec4::ZERO; width + PADDING * 2];
+        Some(Self {
+            kernel,
+            rows: [row.clone(), row.clone(), row],
+        })
+    }
+
+    /// Moves on to the next row.
+    pub fn next_row(&mut self) {
+        self.rows.rotate_left(1);
+        self.rows[ROWS - 1].fill(Vec4::ZERO);
+    }
+
+    /// The accumulated error of the pixel at `x` in the current row.
+    #[inline]
+    pub fn error_at(&self, x: usize) -> Vec4 {
+        self.rows[0][x + PADDING]
+    }
+
+    /// Diffuses the quantization error of the pixel at `x` in the current row
+    /// to its neighbors.
+    #[inline]
+    pub fn diffuse(&mut self, x: usize, error: Vec4) {
+        let x = x + PADDING;
+        for &(dx, dy, weight) in self.kernel {
+            self.rows[dy][(x as isize + dx) as usize] += error * weight;
+        }
+    }
+}
+
+/// Returns a mask with 1 for all channels that should be dithered.
+pub(crate) fn channel_mask(dithering: Dithering) -> Vec4 {
+    match dithering {
+        Dithering::None => Vec4::ZERO,
+        Dithering::ColorAndAlpha => Vec4::ONE,
+        Dithering::Color => Vec4::new(1.0, 1.0, 1.0, 0.0),
+        Dithering::Alpha => Vec4::new(0.0, 0.0, 0.0, 1.0),
+    }
+}
+
+/// The threshold map of a position-based algorithm.
+#[derive(Clone, Copy)]
+pub(crate) struct ThresholdMap {
+    map: &'static [u8],
+    /// The width and height of the map. Must be a power of 2.
+    size: usize,
+    /// The number of different thresholds in the map.
+    levels: f32,
+}
+impl ThresholdMap {
+    /// Returns `None` if the algorithm is not position-based.
+    pub const fn new(algorithm: DitheringAlgorithm) -> Option<Self> {
+        match algorithm {
+            DitheringAlgorithm::Ordered => Some(Self {
+                map: &BAYER_8X8,
+                size: 8,
+                levels: 64.0,
+            }),
+            DitheringAlgorithm::BlueNoise => Some(Self {
+                map: &BLUE_NOISE_64X64,
+                size: 64,
+                levels: 256.0,
+            }),
+            DitheringAlgorithm::FloydSteinberg
+            | DitheringAlgorithm::Atkinson
+            | DitheringAlgorithm::Sierra => None,
+        }
+    }
+
+    /// The width and height of the repeating pattern.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the threshold of the pixel at the given position in the range
+    /// -0.5 to 0.5.
+    #[inline]
+    pub fn threshold(&self, x: usize, y: usize) -> f32 {
+        let mask = self.size - 1;
+        let value = self.map[(y & mask) * self.size + (x & mask)];
+        (value as f32 + 0.5) / self.levels - 0.5
+    }
+}
+
+/// Dithering is slow enough that it's worth encoding in parallel. This only
+/// applies to position-based algorithms, since error diffusion prevents
+/// splitting.
+pub(crate) const DITHER_GROUP_SIZE: PreferredGroupSize =
+    PreferredGroupSize::group(256 * 256, 256 * 256, 256 * 256);
+
+/// The quantization step of UNORM channels with the given number of bits.
+/// Channels with 0 bits have a step of 0.
+pub(crate) fn unorm_step(bits: [u8; 4]) -> Vec4 {
+    Vec4::from(bits.map(|b| {
+        if b == 0 {
+            0.0
+        } else {
+            1.0 / ((1_u32 << b) - 1) as f32
+        }
+    }))
+}
+/// The distance between the given value and the next larger value of a
+/// small float format with 5 exponent bits and the given number of mantissa
+/// bits (e.g. FP16, FP11, FP10).
+pub(crate) fn small_float_step(value: f32, mantissa_bits: i32) -> f32 {
+    // The smallest exponent of normal numbers. Subnormal numbers have the
+    // same step as the smallest normal numbers.
+    const MIN_EXP: i32 = -14;
+    let exp = ((value.abs().to_bits() >> 23) & 0xFF) as i32 - 127;
+    2_f32.powi(exp.max(MIN_EXP) - mantissa_bits)
+}
+
+/// The quantization step of YUV formats in RGB for a given maximum code
+/// value, e.g. 255 for 8-bit YUV. One step of Y changes R, G, and B by
+/// roughly 1.164 steps of RGB.
+pub(crate) fn yuv_step(max: f32, alpha_step: f32) -> Vec4 {
+    let step = 1.164383 / max;
+    Vec4::new(step, step, step, alpha_step)
+}
+
+#[rustfmt::skip]
+const BAYER_8X8: [u8; 64] = [
+     0, 32,  8, 40,  2, 34, 10, 42,
+    48, 16, 56, 24, 50, 18, 58, 26,
+    12, 44,  4, 36, 14, 46,  6, 38,
+    60, 28, 52, 20, 62, 30, 54, 22,
+     3, 35, 11, 43,  1, 33,  9, 41,
+    51, 19, 59, 27, 49, 17, 57, 25,
+    15, 47,  7, 39, 13, 45,  5, 37,
+    63, 31, 55, 23, 61, 29, 53, 21,
+];
+
+/// A tileable 64x64 blue noise texture with 256 levels.
+///
+/// This was generated with the void-and-cluster method (Ulichney 1993) using
+/// a Gaussian filter with sigma 1.9 and a random initial pattern with 10% of
+/// the pixels set.
+#[rustfmt::skip]
+const BLUE_NOISE_64X64: [u8; 4096] = [
+    75, 45, 202, 246, 72, 194, 226, 46, 15, 115, 187, 71, 123, 239, 16, 93,
+    189, 104, 179, 10, 139, 193, 108, 11, 222, 84, 26, 209, 190, 121, 57, 202,
+    80, 140, 47, 197, 163, 223, 112, 132, 203, 67, 220, 37, 2, 245, 147, 48,
+    179, 83, 56, 160, 0, 241, 130, 200, 9, 53, 234, 79, 206, 140, 23, 220,
+    126, 171, 217, 31, 165, 0, 139, 239, 162, 210, 100, 40, 216, 168, 139, 43,
+    206, 119, 241, 86, 233, 155, 43, 242, 187, 62, 247, 48, 105, 167, 254, 155,
+    8, 231, 106, 73, 21, 144, 239, 27, 48, 178, 110, 126, 231, 188, 24, 211,
+    10, 234, 123, 70, 205, 86, 106, 172, 245, 145, 219, 167, 47, 62, 99, 192,
+    87, 17, 103, 131, 53, 95, 201, 27, 88, 57, 250, 154, 25, 111, 58, 253,
+    5, 152, 50, 20, 170, 203, 71, 97, 130, 162, 141, 2, 88, 31, 214, 21,
+    173, 118, 243, 183, 2, 211, 64, 191, 88, 149, 18, 166, 54, 102, 68, 115,
+    197, 96, 170, 222, 24, 183, 61, 29, 117, 69, 93, 3, 128, 230, 151, 34,
+    240, 65, 147, 235, 183, 221, 66, 123, 177, 144, 8, 81, 203, 183, 229, 85,
+    175, 74, 226, 132, 113, 57, 25, 215, 15, 110, 201, 176, 221, 129, 70, 97,
+    51, 209, 37, 154, 95, 171, 122, 230, 7, 254, 214, 78, 203, 157, 138, 248,
+    36, 150, 16, 111, 137, 154, 46, 225, 191, 134, 37, 196, 254, 113, 178, 205,
+    161, 117, 197, 79, 14, 111, 154, 247, 38, 191, 225, 131, 47, 97, 14, 126,
+    144, 197, 34, 213, 92, 250, 179, 148, 230, 76, 35, 237, 60, 150, 240, 184,
+    137, 84, 61, 130, 251, 79, 34, 105, 141, 185, 99, 41, 241, 8, 86, 219,
+    61, 184, 51, 250, 92, 211, 238, 81, 13, 176, 212, 156, 25, 72, 11, 51,
+    223, 3, 249, 33, 170, 47, 205, 18, 103, 72, 113, 169, 244, 69, 159, 212,
+    26, 63, 105, 183, 0, 138, 41, 121, 191, 52, 94, 123, 19, 194, 113, 4,
+    224, 164, 197, 25, 222, 49, 205, 162, 55, 71, 30, 135, 120, 178, 27, 164,
+    127, 230, 76, 198, 35, 6, 127, 164, 109, 247, 45, 101, 84, 217, 143, 97,
+    41, 186, 59, 140, 91, 228, 133, 83, 236, 217, 30, 2, 141, 194, 37, 115,
+    247, 167, 233, 77, 157, 238, 84, 169, 11, 253, 154, 208, 166, 80, 45, 32,
+    249, 102, 12, 144, 114, 187, 13, 127, 243, 174, 227, 196, 59, 236, 45, 191,
+    106, 4, 143, 120, 172, 66, 194, 96, 22, 146, 61, 124, 186, 169, 244, 132,
+    86, 107, 156, 215, 120, 188, 6, 163, 50, 151, 182, 60, 231, 102, 219, 54,
+    91, 9, 123, 46, 207, 62, 28, 101, 217, 67, 181, 6, 103, 230, 143, 202,
+    124, 72, 179, 232, 66, 92, 152, 220, 22, 86, 0, 153, 109, 215, 95, 69,
+    207, 22, 89, 240, 218, 150, 53, 229, 75, 201, 221, 236, 17, 55, 30, 207,
+    176, 22, 233, 73, 26, 255, 64, 198, 116, 93, 207, 128, 86, 19, 173, 187,
+    135, 203, 149, 17, 188, 114, 227, 196, 141, 116, 43, 134, 244, 56, 175, 93,
+    155, 53, 210, 35, 170, 238, 43, 194, 117, 101, 210, 167, 80, 12, 147, 132,
+    254, 157, 178, 44, 26, 112, 244, 184, 34, 133, 1, 162, 112, 196, 69, 121,
+    251, 135, 196, 48, 98, 176, 38, 141, 239, 22, 166, 43, 253, 148, 73, 241,
+    31, 81, 222, 253, 96, 132, 163, 50, 21, 234, 90, 30, 214, 70, 23, 220,
+    9, 242, 85, 133, 109, 4, 77, 139, 62, 248, 48, 129, 24, 245, 171, 36,
+    222, 58, 199, 102, 74, 137, 8, 158, 119, 175, 91, 40, 79, 151, 225, 6,
+    58, 82, 14, 167, 209, 150, 107, 79, 13, 215, 69, 196, 109, 11, 120, 48,
+    163, 107, 60, 172, 37, 73, 8, 248, 80, 150, 201, 187, 158, 110, 131, 189,
+    42, 118, 192, 20, 160, 255, 213, 175, 30, 149, 200, 70, 231, 188, 53, 114,
+    19, 82, 124, 235, 168, 214, 86, 46, 206, 66, 252, 214, 140, 241, 96, 163,
+    218, 148, 112, 236, 128, 1, 244, 223, 180, 122, 138, 34, 223, 158, 208, 228,
+    4, 195, 142, 24, 216, 152, 181, 208, 107, 172, 59, 122, 16, 85, 253, 165,
+    100, 148, 229, 60, 203, 99, 51, 125, 227, 7, 180, 37, 118, 87, 205, 100,
+    183, 151, 1, 39, 187, 17, 249, 99, 227, 25, 107, 188, 51, 15, 35, 181,
+    43, 199, 67, 32, 88, 57, 192, 45, 96, 58, 246, 82, 176, 95, 65, 129,
+    180, 98, 234, 121, 54, 240, 90, 127, 38, 5, 245, 223, 46, 205, 0, 226,
+    55, 27, 176, 73, 142, 14, 188, 85, 110, 162, 96, 215, 137, 159, 7, 227,
+    135, 248, 212, 63, 129, 146, 194, 56, 153, 135, 7, 169, 117, 208, 131, 104,
+    9, 123, 247, 186, 220, 159, 115, 26, 169, 152, 4, 189, 21, 51, 250, 36,
+    76, 17, 206, 84, 187, 111, 20, 64, 219, 136, 75, 100, 146, 178, 67, 139,
+    80, 209, 248, 112, 39, 223, 154, 23, 236, 74, 252, 58, 26, 240, 66, 44,
+    74, 165, 92, 232, 108, 72, 31, 121, 180, 78, 235, 61, 87, 194, 74, 229,
+    157, 92, 170, 16, 139, 76, 201, 133, 229, 206, 106, 235, 143, 115, 200, 154,
+    137, 242, 43, 158, 1, 141, 201, 231, 156, 185, 28, 166, 239, 116, 37, 198,
+    126, 11, 159, 91, 131, 244, 66, 209, 44, 129, 15, 193, 148, 111, 172, 198,
+    15, 117, 28, 49, 176, 203, 240, 11, 220, 199, 34, 159, 246, 22, 142, 242,
+    29, 212, 54, 107, 41, 253, 8, 63, 85, 40, 127, 72, 214, 167, 8, 87,
+    221, 108, 170, 70, 252, 33, 169, 101, 44, 87, 198, 54, 20, 89, 229, 183,
+    105, 237, 49, 196, 2, 182, 117, 173, 144, 199, 168, 104, 41, 219, 86, 244,
+    142, 189, 208, 155, 19, 84, 161, 104, 44, 92, 146, 123, 216, 46, 173, 63,
+    189, 81, 227, 150, 182, 95, 213, 172, 240, 17, 162, 56, 31, 100, 231, 183,
+    27, 57, 191, 129, 220, 52, 77, 239, 10, 120, 255, 211, 132, 155, 60, 17,
+    147, 32, 171, 220, 82, 28, 98, 55, 6, 90, 228, 79, 182, 2, 127, 31,
+    101, 225, 61, 131, 251, 217, 140, 63, 173, 255, 14, 108, 185, 0, 98, 116,
+    137, 6, 126, 22, 235, 119, 33, 145, 109, 186, 93, 254, 197, 132, 45, 67,
+    121, 209, 15, 97, 151, 115, 190, 130, 175, 145, 69, 107, 4, 173, 215, 250,
+    94, 72, 119, 59, 139, 240, 160, 219, 250, 33, 122, 60, 236, 208, 153, 54,
+    179, 81, 6, 114, 96, 36, 2, 234, 128, 73, 209, 53, 81, 152, 223, 202,
+    237, 70, 168, 199, 59, 161, 73, 196, 49, 136, 222, 5, 176, 81, 241, 146,
+    163, 249, 39, 230, 85, 7, 215, 60, 22, 227, 37, 191, 234, 78, 114, 43,
+    202, 188, 234, 154, 207, 41, 192, 109, 69, 204, 138, 160, 24, 114, 71, 254,
+    164, 41, 237, 195, 169, 52, 184, 112, 193, 26, 166, 231, 133, 249, 35, 49,
+    177, 103, 247, 45, 87, 220, 2, 249, 27, 209, 66, 118, 149, 20, 108, 202,
+    3, 78, 139, 177, 199, 30, 157, 247, 91, 203, 162, 51, 97, 30, 139, 164,
+    5, 130, 22, 104, 10, 77, 127, 17, 150, 178, 11, 246, 47, 94, 199, 134,
+    20, 216, 149, 68, 142, 212, 78, 226, 151, 40, 99, 197, 65, 19, 161, 90,
+    25, 144, 12, 113, 184, 138, 100, 124, 179, 84, 158, 41, 228, 59, 216, 35,
+    186, 102, 59, 119, 238, 49, 106, 139, 75, 113, 13, 151, 182, 206, 242, 62,
+    223, 86, 48, 254, 172, 94, 229, 185, 50, 84, 101, 221, 172, 189, 9, 230,
+    108, 121, 89, 12, 247, 125, 16, 91, 58, 245, 143, 9, 181, 109, 124, 213,
+    59, 222, 157, 205, 31, 232, 153, 56, 237, 11, 105, 245, 191, 168, 125, 89,
+    239, 155, 225, 13, 71, 165, 210, 184, 42, 223, 128, 250, 71, 122, 15, 105,
+    156, 180, 213, 146, 197, 32, 61, 244, 134, 213, 39, 118, 65, 140, 85, 36,
+    182, 50, 205, 188, 32, 104, 178, 161, 24, 216, 120, 86, 208, 240, 74, 192,
+    253, 40, 80, 129, 67, 18, 212, 37, 166, 202, 134, 29, 95, 73, 13, 136,
+    51, 27, 195, 172, 130, 94, 20, 242, 1, 169, 58, 213, 23, 88, 196, 232,
+    33, 76, 114, 66, 135, 219, 118, 162, 23, 192, 232, 153, 28, 238, 211, 159,
+    75, 243, 165, 63, 224, 45, 200, 236, 132, 75, 174, 47, 33, 149, 3, 135,
+    115, 167, 98, 239, 191, 173, 78, 93, 117, 68, 184, 53, 143, 210, 251, 181,
+    221, 113, 86, 41, 254, 148, 64, 121, 84, 190, 98, 143, 44, 176, 149, 53,
+    128, 245, 8, 27, 166, 47, 0, 98, 68, 110, 6, 79, 168, 124, 57, 102,
+    1, 132, 21, 95, 152, 138, 67, 113, 3, 192, 252, 105, 228, 163, 52, 89,
+    25, 179, 218, 9, 52, 109, 252, 146, 216, 15, 235, 222, 0, 114, 42, 161,
+    66, 145, 208, 4, 218, 180, 34, 198, 233, 155, 32, 236, 110, 221, 5, 170,
+    94, 204, 186, 230, 89, 248, 183, 143, 207, 173, 247, 52, 201, 18, 250, 191,
+    146, 229, 175, 118, 254, 10, 84, 210, 39, 156, 61, 15, 128, 183, 202, 232,
+    148, 65, 36, 141, 123, 159, 4, 195, 45, 128, 87, 153, 170, 194, 81, 101,
+    21, 242, 125, 75, 100, 55, 226, 108, 134, 17, 69, 202, 126, 80, 253, 65,
+    139, 41, 158, 105, 126, 199, 80, 235, 42, 125, 88, 147, 221, 113, 91, 44,
+    216, 30, 80, 53, 207, 181, 163, 242, 97, 221, 144, 83, 215, 69, 100, 13,
+    211, 193, 246, 88, 204, 230, 30, 63, 242, 178, 106, 35, 59, 125, 237, 10,
+    202, 50, 168, 190, 139, 161, 9, 74, 173, 49, 244, 165, 14, 187, 30, 210,
+    115, 20, 218, 54, 69, 13, 154, 28, 59, 224, 16, 186, 37, 138, 179, 70,
+    126, 199, 109, 235, 38, 18, 126, 55, 28, 187, 115, 172, 26, 40, 243, 120,
+    78, 162, 107, 16, 73, 180, 101, 137, 164, 21, 72, 255, 208, 27, 224, 149,
+    177, 109, 32, 229, 22, 117, 247, 206, 89, 216, 119, 94, 54, 154, 103, 233,
+    178, 85, 243, 148, 34, 174, 214, 107, 137, 195, 160, 100, 66, 7, 236, 165,
+    14, 61, 157, 92, 142, 194, 104, 227, 78, 133, 11, 248, 198, 157, 138, 55,
+    2, 131, 44, 235, 150, 54, 222, 81, 115, 229, 198, 140, 97, 185, 70, 135,
+    90, 252, 64, 83, 201, 44, 151, 185, 36, 145, 3, 193, 225, 136, 74, 47,
+    161, 2, 194, 132, 228, 116, 254, 93, 3, 240, 76, 120, 252, 205, 151, 84,
+    224, 187, 249, 5, 219, 69, 151, 174, 201, 48, 232, 59, 93, 109, 223, 176,
+    255, 96, 216, 167, 21, 129, 192, 38, 213, 10, 50, 158, 17, 116, 53, 37,
+    217, 5, 156, 131, 238, 95, 62, 20, 105, 250, 66, 175, 37, 239, 18, 200,
+    124, 60, 100, 75, 184, 46, 164, 65, 178, 31, 211, 44, 171, 26, 105, 41,
+    117, 27, 131, 171, 46, 120, 24, 251, 0, 101, 165, 149, 73, 18, 188, 32,
+    144, 198, 63, 185, 112, 250, 1, 148, 93, 184, 127, 80, 232, 174, 245, 163,
+    193, 121, 209, 182, 15, 170, 218, 126, 228, 158, 83, 128, 213, 109, 88, 146,
+    246, 225, 28, 209, 10, 84, 19, 204, 149, 111, 133, 230, 58, 196, 134, 245,
+    211, 53, 101, 77, 204, 239, 89, 62, 137, 209, 34, 218, 127, 241, 50, 86,
+    228, 13, 124, 29, 90, 204, 71, 238, 172, 62, 248, 40, 211, 11, 106, 75,
+    25, 99, 40, 54, 112, 143, 79, 195, 50, 11, 203, 26, 58, 166, 10, 183,
+    39, 170, 113, 157, 245, 142, 126, 234, 51, 187, 80, 18, 156, 94, 72, 1,
+    147, 178, 233, 141, 33, 186, 111, 157, 182, 82, 114, 192, 6, 169, 206, 115,
+    39, 175, 77, 242, 42, 160, 51, 122, 24, 104, 152, 190, 88, 145, 130, 225,
+    240, 151, 80, 227, 244, 0, 34, 178, 115, 240, 141, 100, 190, 255, 118, 217,
+    69, 92, 134, 49, 191, 102, 221, 36, 91, 247, 6, 216, 122, 183, 226, 167,
+    87, 200, 65, 9, 164, 224, 15, 42, 235, 19, 245, 45, 91, 140, 68, 159,
+    58, 213, 153, 136, 217, 105, 179, 141, 225, 208, 5, 118, 56, 32, 202, 62,
+    9, 174, 136, 189, 65, 161, 222, 97, 70, 168, 43, 234, 153, 77, 50, 140,
+    21, 204, 6, 235, 63, 25, 174, 72, 199, 163, 142, 102, 239, 22, 51, 36,
+    112, 19, 124, 253, 95, 132, 56, 214, 124, 143, 65, 175, 224, 103, 23, 237,
+    110, 3, 97, 195, 20, 233, 12, 83, 34, 73, 168, 242, 218, 161, 181, 94,
+    48, 215, 106, 19, 90, 201, 128, 252, 23, 215, 89, 123, 3, 33, 228, 193,
+    104, 248, 178, 81, 216, 122, 154, 13, 110, 57, 29, 173, 66, 203, 137, 242,
+    217, 185, 155, 43, 206, 72, 193, 168, 99, 76, 199, 152, 31, 249, 188, 133,
+    85, 181, 251, 52, 68, 118, 190, 253, 200, 129, 46, 96, 18, 76, 254, 121,
+    196, 31, 165, 249, 119, 39, 153, 56, 136, 12, 181, 62, 208, 171, 85, 160,
+    125, 57, 146, 165, 40, 95, 189, 251, 135, 213, 231, 43, 87, 116, 160, 75,
+    98, 57, 228, 82, 108, 148, 3, 249, 29, 231, 12, 54, 125, 79, 10, 203,
+    232, 149, 33, 129, 169, 92, 155, 58, 101, 150, 185, 230, 138, 110, 5, 149,
+    233, 132, 73, 52, 208, 7, 82, 188, 108, 198, 147, 226, 102, 133, 241, 14,
+    38, 223, 28, 110, 204, 1, 227, 47, 83, 119, 186, 151, 255, 16, 195, 4,
+    144, 30, 172, 13, 239, 35, 180, 117, 86, 160, 210, 111, 178, 215, 165, 47,
+    118, 16, 75, 209, 225, 7, 42, 174, 21, 237, 66, 29, 205, 177, 40, 65,
+    87, 14, 224, 180, 143, 238, 173, 228, 30, 242, 76, 42, 18, 186, 67, 206,
+    98, 76, 195, 130, 243, 70, 143, 179, 21, 67, 7, 97, 207, 60, 177, 129,
+    248, 209, 119, 197, 134, 217, 64, 225, 45, 189, 135, 237, 38, 96, 143, 64,
+    193, 156, 243, 104, 140, 201, 244, 125, 208, 2, 88, 120, 53, 158, 239, 211,
+    189, 115, 156, 96, 26, 104, 68, 47, 160, 94, 120, 166, 251, 111, 48, 142,
+    179, 253, 154, 19, 88, 52, 164, 105, 203, 242, 169, 126, 223, 37, 106, 230,
+    46, 89, 68, 162, 52, 97, 154, 129, 17, 102, 61, 4, 74, 254, 24, 222,
+    38, 89, 176, 49, 25, 64, 81, 108, 220, 136, 166, 196, 224, 80, 100, 21,
+    170, 245, 59, 42, 197, 122, 212, 133, 2, 217, 58, 25, 203, 154, 4, 232,
+    119, 12, 62, 174, 215, 234, 28, 123, 224, 41, 143, 77, 24, 148, 83, 166,
+    18, 189, 104, 22, 252, 9, 82, 204, 174, 247, 150, 200, 159, 186, 128, 102,
+    204, 234, 0, 122, 188, 165, 148, 31, 52, 74, 251, 105, 11, 145, 125, 50,
+    138, 3, 79, 218, 255, 12, 152, 83, 248, 176, 195, 128, 88, 221, 77, 168,
+    92, 210, 44, 113, 140, 100, 194, 152, 13, 92, 56, 194, 237, 182, 117, 64,
+    219, 151, 236, 137, 210, 167, 38, 229, 71, 27, 120, 87, 225, 50, 9, 172,
+    55, 136, 71, 214, 255, 93, 236, 177, 191, 153, 42, 25, 186, 246, 35, 199,
+    228, 108, 185, 129, 167, 63, 193, 34, 110, 144, 71, 235, 37, 137, 56, 193,
+    33, 131, 238, 189, 8, 38, 78, 64, 252, 180, 113, 212, 0, 49, 247, 198,
+    7, 125, 40, 78, 182, 109, 123, 191, 141, 53, 214, 35, 110, 141, 241, 81,
+    164, 29, 108, 145, 41, 11, 115, 22, 228, 96, 127, 214, 62, 174, 75, 154,
+    92, 207, 33, 146, 18, 89, 233, 181, 52, 23, 100, 8, 184, 108, 248, 21,
+    226, 68, 157, 83, 245, 166, 204, 134, 218, 159, 29, 129, 100, 156, 136, 33,
+    99, 175, 55, 227, 26, 63, 243, 1, 98, 235, 182, 167, 19, 68, 211, 116,
+    249, 184, 226, 159, 198, 57, 132, 207, 65, 6, 242, 160, 90, 114, 234, 23,
+    56, 163, 69, 238, 102, 43, 117, 220, 158, 241, 206, 170, 48, 213, 161, 97,
+    145, 181, 104, 25, 217, 57, 118, 4, 46, 84, 240, 70, 175, 233, 88, 74,
+    208, 250, 158, 90, 195, 149, 47, 85, 160, 15, 131, 251, 95, 197, 148, 16,
+    90, 63, 7, 99, 76, 221, 87, 168, 144, 108, 199, 50, 140, 2, 210, 133,
+    251, 8, 120, 222, 175, 203, 138, 73, 16, 130, 81, 116, 148, 68, 15, 122,
+    207, 0, 48, 125, 143, 176, 230, 103, 189, 142, 17, 202, 43, 12, 220, 187,
+    20, 141, 107, 6, 214, 133, 173, 219, 199, 112, 77, 60, 39, 231, 177, 45,
+    213, 191, 124, 34, 244, 181, 16, 251, 36, 75, 180, 29, 222, 191, 40, 98,
+    179, 196, 46, 81, 26, 247, 3, 95, 198, 61, 224, 254, 31, 192, 238, 82,
+    60, 233, 199, 254, 72, 16, 89, 33, 247, 171, 107, 227, 146, 60, 124, 112,
+    168, 66, 36, 237, 115, 19, 255, 68, 32, 153, 212, 188, 122, 3, 104, 132,
+    26, 151, 233, 171, 140, 118, 48, 155, 218, 124, 236, 85, 119, 65, 166, 77,
+    111, 141, 212, 158, 128, 56, 169, 147, 185, 41, 162, 4, 90, 134, 218, 38,
+    167, 113, 91, 162, 41, 212, 150, 198, 67, 54, 121, 79, 190, 162, 30, 241,
+    51, 225, 204, 178, 74, 95, 42, 121, 233, 9, 49, 138, 221, 158, 74, 240,
+    83, 107, 51, 68, 23, 205, 104, 189, 58, 10, 170, 20, 249, 156, 230, 17,
+    241, 61, 12, 93, 190, 111, 236, 32, 211, 106, 125, 181, 55, 103, 175, 150,
+    10, 190, 28, 135, 185, 110, 235, 126, 163, 24, 215, 6, 248, 91, 211, 138,
+    17, 85, 127, 146, 55, 192, 165, 142, 182, 102, 243, 83, 23, 174, 57, 200,
+    8, 163, 217, 194, 93, 1, 231, 82, 135, 97, 211, 147, 103, 51, 128, 186,
+    33, 152, 226, 252, 37, 70, 218, 87, 19, 243, 74, 232, 199, 24, 249, 73,
+    128, 243, 219, 65, 5, 83, 50, 12, 221, 94, 179, 135, 41, 105, 71, 180,
+    194, 101, 161, 13, 246, 210, 3, 227, 89, 61, 168, 205, 111, 248, 34, 141,
+    183, 253, 40, 129, 240, 147, 166, 29, 246, 194, 67, 39, 204, 11, 217, 90,
+    200, 74, 105, 133, 179, 8, 161, 119, 136, 48, 153, 12, 142, 116, 45, 211,
+    87, 53, 103, 157, 204, 249, 176, 139, 77, 238, 150, 200, 25, 232, 154, 2,
+    251, 44, 233, 31, 119, 105, 77, 28, 130, 195, 38, 13, 147, 94, 227, 119,
+    89, 14, 76, 113, 56, 212, 72, 116, 45, 159, 229, 130, 79, 173, 114, 144,
+    43, 169, 20, 206, 51, 146, 198, 60, 223, 172, 95, 207, 64, 227, 159, 19,
+    180, 144, 37, 226, 121, 22, 99, 195, 35, 112, 48, 65, 120, 173, 55, 130,
+    114, 78, 202, 63, 186, 224, 151, 52, 249, 156, 220, 124, 68, 192, 47, 209,
+    133, 223, 177, 158, 32, 184, 14, 224, 175, 5, 109, 27, 184, 255, 56, 0,
+    244, 221, 118, 82, 234, 99, 245, 78, 2, 193, 252, 27, 84, 187, 99, 123,
+    202, 237, 15, 72, 171, 146, 59, 243, 160, 1, 210, 254, 85, 196, 226, 23,
+    213, 170, 149, 135, 87, 42, 173, 203, 110, 19, 79, 236, 179, 0, 161, 64,
+    152, 103, 21, 244, 137, 101, 201, 127, 91, 62, 241, 153, 94, 226, 71, 160,
+    101, 190, 60, 157, 14, 126, 29, 185, 106, 39, 114, 131, 170, 36, 246, 2,
+    61, 163, 111, 197, 86, 42, 216, 127, 69, 187, 133, 164, 15, 99, 145, 70,
+    36, 95, 10, 219, 21, 244, 122, 7, 63, 185, 98, 44, 136, 107, 232, 30,
+    53, 205, 190, 66, 232, 83, 49, 252, 144, 209, 190, 49, 138, 18, 198, 125,
+    24, 137, 35, 177, 224, 44, 214, 164, 147, 229, 71, 156, 54, 218, 140, 79,
+    222, 94, 135, 255, 5, 231, 181, 27, 92, 234, 106, 31, 216, 45, 241, 159,
+    192, 248, 57, 181, 101, 162, 71, 238, 143, 213, 165, 253, 23, 197, 82, 246,
+    9, 118, 93, 42, 3, 171, 155, 20, 36, 77, 10, 120, 214, 38, 171, 82,
+    237, 205, 90, 253, 109, 71, 134, 88, 56, 16, 211, 237, 9, 193, 113, 174,
+    45, 30, 186, 55, 155, 117, 103, 207, 13, 148, 55, 79, 184, 136, 5, 112,
+    83, 127, 230, 116, 204, 35, 134, 190, 90, 32, 119, 57, 150, 216, 127, 171,
+    226, 145, 163, 218, 123, 195, 109, 220, 180, 101, 160, 238, 64, 104, 147, 222,
+    47, 67, 4, 145, 194, 171, 8, 249, 200, 123, 175, 87, 102, 24, 64, 151,
+    207, 243, 122, 19, 204, 75, 142, 50, 168, 223, 193, 246, 122, 169, 58, 223,
+    177, 17, 47, 155, 1, 80, 225, 49, 12, 229, 176, 76, 6, 95, 40, 70,
+    200, 32, 255, 76, 142, 55, 245, 68, 134, 232, 200, 85, 177, 250, 7, 114,
+    181, 162, 124, 212, 53, 23, 231, 98, 183, 29, 44, 145, 202, 251, 129, 230,
+    7, 82, 168, 66, 237, 33, 177, 250, 85, 38, 114, 11, 67, 232, 200, 102,
+    29, 209, 137, 67, 255, 172, 111, 208, 157, 103, 132, 205, 189, 233, 112, 182,
+    88, 61, 106, 18, 207, 28, 92, 6, 117, 46, 30, 15, 130, 54, 193, 31,
+    94, 243, 18, 103, 82, 155, 118, 63, 139, 79, 244, 115, 166, 73, 38, 97,
+    183, 107, 141, 191, 96, 220, 16, 126, 64, 202, 138, 156, 24, 91, 39, 149,
+    77, 164, 236, 91, 187, 145, 60, 24, 248, 68, 43, 241, 28, 157, 139, 14,
+    169, 239, 131, 186, 231, 175, 152, 214, 192, 167, 145, 98, 208, 158, 231, 76,
+    134, 218, 40, 188, 246, 219, 33, 206, 159, 0, 225, 55, 12, 188, 220, 161,
+    57, 22, 216, 43, 131, 159, 111, 240, 4, 165, 98, 235, 210, 180, 130, 250,
+    54, 193, 108, 14, 34, 201, 98, 124, 182, 144, 16, 116, 81, 59, 250, 46,
+    206, 153, 5, 49, 114, 81, 40, 238, 57, 76, 246, 225, 67, 119, 20, 150,
+    201, 61, 170, 142, 70, 131, 47, 239, 190, 106, 171, 212, 92, 138, 26, 121,
+    234, 147, 252, 1, 80, 54, 197, 91, 183, 217, 29, 52, 73, 111, 3, 219,
+    22, 121, 226, 44, 131, 222, 5, 237, 84, 197, 162, 219, 174, 102, 215, 123,
+    78, 96, 195, 70, 164, 140, 99, 126, 20, 179, 112, 4, 39, 185, 91, 48,
+    236, 108, 14, 88, 25, 177, 113, 90, 21, 72, 127, 34, 153, 242, 82, 46,
+    195, 72, 116, 206, 169, 231, 26, 145, 46, 77, 128, 253, 167, 190, 142, 86,
+    156, 173, 71, 245, 153, 78, 165, 49, 32, 227, 94, 51, 134, 1, 187, 25,
+    36, 234, 213, 23, 243, 219, 8, 205, 155, 93, 201, 136, 164, 251, 212, 174,
+    1, 122, 196, 228, 161, 203, 5, 147, 229, 50, 251, 197, 63, 110, 207, 166,
+    9, 93, 177, 35, 103, 136, 69, 210, 116, 228, 152, 103, 14, 42, 239, 62,
+    200, 8, 97, 184, 58, 210, 178, 112, 137, 65, 12, 247, 201, 69, 228, 146,
+    56, 107, 135, 177, 59, 35, 184, 254, 64, 32, 221, 52, 83, 107, 28, 132,
+    78, 246, 148, 52, 99, 255, 60, 212, 182, 136, 96, 7, 176, 17, 226, 130,
+    246, 62, 152, 238, 13, 188, 250, 172, 7, 23, 192, 59, 206, 121, 224, 29,
+    106, 215, 140, 27, 116, 92, 18, 252, 214, 189, 123, 152, 39, 89, 164, 117,
+    252, 155, 10, 90, 120, 150, 106, 80, 133, 171, 235, 10, 149, 196, 62, 224,
+    160, 35, 67, 215, 31, 125, 78, 167, 36, 117, 156, 233, 75, 144, 38, 99,
+    185, 28, 217, 125, 86, 58, 39, 96, 157, 243, 84, 139, 174, 93, 75, 163,
+    130, 253, 39, 192, 229, 147, 42, 74, 158, 100, 27, 180, 110, 241, 11, 182,
+];
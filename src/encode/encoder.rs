@@ -3,7 +3,8 @@ use std::{io::Write, num::NonZeroU8};
 use bitflags::bitflags;
 
 use crate::{
-    cast, ColorFormat, ColorFormatSet, EncodingError, ImageView, Precision, Progress, Report,
+    as_rgba_f32, cast, convert_to_rgba_f32, ColorFormat, ColorFormatSet, EncodingError, ImageView,
+    Precision, Progress, Report,
 };
 
 use super::{
@@ -139,12 +140,16 @@ bitflags! {
         const DITHER_ALPHA = 0x2;
         /// Whether dithering is done within a block, instead of globally.
         const LOCAL_DITHERING = 0x4;
+        /// Whether the format is SNORM and supports signed input.
+        const SNORM = 0x8;
     }
 }
 pub(crate) struct EncoderSet {
     flags: EncodeFormatFlags,
     split_height: Option<NonZeroU8>,
     size_multiple: Option<SizeMultiple>,
+    /// The smallest preferred group size of all encoders.
+    group_size: PreferredGroupSize,
     encoders: &'static [Encoder],
 }
 impl EncoderSet {
@@ -153,11 +158,13 @@ impl EncoderSet {
 
         let mut combined_flags = Flags::empty();
         let mut combined_colors = ColorFormatSet::EMPTY;
+        let mut group_size = PreferredGroupSize::EntireImage;
         let mut i = 0;
         while i < encoders.len() {
             let e = &encoders[i];
             combined_flags = combined_flags.union(e.flags);
             combined_colors = combined_colors.union(e.color_formats);
+            group_size = group_size.combine(e.group_size);
             i += 1;
         }
 
@@ -180,6 +187,7 @@ impl EncoderSet {
             flags,
             split_height: NonZeroU8::new(1),
             size_multiple: None,
+            group_size,
             encoders,
         }
     }
@@ -196,6 +204,12 @@ impl EncoderSet {
         set
     }
 
+    /// Marks the format as SNORM. See [`EncodeOptions::signed_snorm`].
+    pub const fn snorm(mut self) -> Self {
+        self.flags = self.flags.union(EncodeFormatFlags::SNORM);
+        self
+    }
+
     pub const fn supported_dithering(&self) -> Dithering {
         Dithering::new(
             self.flags.contains(EncodeFormatFlags::DITHER_COLOR),
@@ -212,7 +226,7 @@ impl EncoderSet {
             split_height: self.split_height,
             local_dithering: self.local_dithering(),
             size_multiple: self.size_multiple,
-            group_size: self.encoders[0].group_size,
+            group_size: self.group_size,
         }
     }
 
@@ -222,10 +236,18 @@ impl EncoderSet {
             .filter(move |e| e.color_formats.contains(color))
     }
     fn pick_encoder(&self, color: ColorFormat, options: &EncodeOptions) -> &Encoder {
+        // Only universal encoders support input transformations, because
+        // they convert the input to RGBA anyway.
+        let transformed = transforms_input(options);
+        let candidates = || {
+            self.encoders_for_color(color)
+                .filter(move |e| !transformed || e.color_formats == ColorFormatSet::ALL)
+        };
+
         // Firstly, if we have an encoder that can encode the current precision
         // exactly, use it.
         let precision_flag = Flags::exact_for(color.precision);
-        for encoder in self.encoders_for_color(color) {
+        for encoder in candidates() {
             if encoder.flags.contains(precision_flag) {
                 return encoder;
             }
@@ -233,7 +255,7 @@ impl EncoderSet {
 
         // Secondly, search for encoders that perform the requested dithering.
         if options.dithering != Dithering::None {
-            for encoder in self.encoders_for_color(color) {
+            for encoder in candidates() {
                 if encoder.flags.get_dithering().intersect(options.dithering) != Dithering::None {
                     return encoder;
                 }
@@ -241,7 +263,7 @@ impl EncoderSet {
         }
 
         // Lastly, just pick any encoder that can do the job.
-        self.encoders_for_color(color)
+        candidates()
             .next()
             .expect("all color formats to be supported")
     }
@@ -253,10 +275,54 @@ impl EncoderSet {
         progress: Option<&mut Progress>,
         options: &EncodeOptions,
     ) -> Result<(), EncodingError> {
-        let encoder = self.pick_encoder(image.color(), options);
-        let args = Args::from(image, writer, progress, options.clone())?;
+        let options = self.input_options(image.color(), options);
+        let encoder = self.pick_encoder(image.color(), &options);
+        let args = Args::from(image, writer, progress, options)?;
         encoder.encode(args)
     }
+
+    /// Returns the options with all input transformations that don't apply
+    /// to this format and color disabled.
+    pub fn input_options(&self, color: ColorFormat, options: &EncodeOptions) -> EncodeOptions {
+        let mut options = options.clone();
+        // signed input is only supported for SNORM formats and F32 images
+        options.signed_snorm &=
+            self.flags.contains(EncodeFormatFlags::SNORM) && color.precision == Precision::F32;
+        options
+    }
+}
+
+/// Whether the input has to be transformed before encoding.
+fn transforms_input(options: &EncodeOptions) -> bool {
+    options.signed_snorm || !options.channel_mapping.is_identity()
+}
+/// Applies all input transformations of the options in place.
+///
+/// The channel mapping is applied before signed values are mapped to the
+/// range 0 to 1, so the constants of the mapping are signed values too.
+pub(crate) fn transform_input(options: &EncodeOptions, pixels: &mut [[f32; 4]]) {
+    options.channel_mapping.apply_rgba_f32(pixels);
+    if options.signed_snorm {
+        for pixel in pixels.iter_mut() {
+            *pixel = pixel.map(|x| x * 0.5 + 0.5);
+        }
+    }
+}
+/// Like [`as_rgba_f32`], but with all input transformations of the options
+/// applied.
+pub(crate) fn input_as_rgba_f32<'a>(
+    color: ColorFormat,
+    data: &'a [u8],
+    buffer: &'a mut [[f32; 4]],
+    options: &EncodeOptions,
+) -> &'a [[f32; 4]] {
+    if !transforms_input(options) {
+        return as_rgba_f32(color, data, buffer);
+    }
+
+    convert_to_rgba_f32(color, data, buffer);
+    transform_input(options, buffer);
+    buffer
 }
 
 fn copy_directly(args: Args) -> Result<(), EncodingError> {
@@ -268,7 +334,7 @@ fn copy_directly(args: Args) -> Result<(), EncodingError> {
         ..
     } = args;
 
-    progress.report(0.0);
+    progress.checked_report(0.0)?;
 
     // We can always just write everything directly on LE systems
     // and when the precision is U8
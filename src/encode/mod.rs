@@ -1,22 +1,28 @@
 use std::{
+    hash::{Hash, Hasher},
     io::Write,
     num::{NonZeroU32, NonZeroU8},
 };
 
-use crate::{EncodingError, Format, ImageView, Progress, Size};
+use crate::{ChannelMapping, EncodingError, Format, ImageView, Progress, Size};
 
 mod bc;
 mod bc1;
 mod bc4;
 mod bcn_util;
 mod bi_planar;
+mod dither;
 mod encoder;
+mod rdo;
 mod sub_sampled;
 mod uncompressed;
 
 use bc::*;
+pub(crate) use bc1::{compress_bc1_block, Bc1Options};
+pub(crate) use bc4::{compress_bc4_block, Bc4Options};
 use bi_planar::*;
 pub(crate) use encoder::EncoderSet;
+pub use rdo::RdoOptions;
 use sub_sampled::*;
 use uncompressed::*;
 
@@ -125,6 +131,22 @@ pub fn encode(
     encoders.encode(writer, image, progress, options)
 }
 
+/// Converts the image to RGBA F32 with all input transformations applied,
+/// i.e. the image as seen by the encoder of the given format.
+pub(crate) fn encoder_input_rgba_f32(
+    image: ImageView,
+    format: Format,
+    options: &EncodeOptions,
+) -> Vec<[f32; 4]> {
+    let mut rgba = vec![[0_f32; 4]; image.size().pixels() as usize];
+    crate::convert_to_rgba_f32(image.color(), image.data(), &mut rgba);
+    if let Some(encoders) = get_encoders(format) {
+        let options = encoders.input_options(image.color(), options);
+        encoder::transform_input(&options, &mut rgba);
+    }
+    rgba
+}
+
 #[cfg(feature = "rayon")]
 fn encode_parallel(
     writer: &mut dyn Write,
@@ -141,7 +163,15 @@ fn encode_parallel(
     // don't cause an infinite loop
     options.parallel = false;
 
-    let split = crate::SplitSurface::new(image, format, &options);
+    let pixel_info = crate::PixelInfo::from(format);
+    let split = match pixel_info {
+        // bi-planar formats store all of plane 1 before plane 2, so their
+        // fragments have to be reassembled plane by plane
+        crate::PixelInfo::BiPlanar(_) => {
+            crate::SplitSurface::new_bi_planar(image, format, &options)
+        }
+        _ => crate::SplitSurface::new(image, format, &options),
+    };
 
     // optimization for single fragment
     if let Some(single) = split.single() {
@@ -154,11 +184,13 @@ fn encode_parallel(
     // reporters. They will do nothing.
     let parallel_progress = crate::ParallelProgress::new(&mut progress, image.height() as u64 + 1);
 
-    let pixel_info = crate::PixelInfo::from(format);
     let result: Result<Vec<Vec<u8>>, EncodingError> = split
         .fragments()
         .par_iter()
         .map(|fragment| -> Result<Vec<u8>, EncodingError> {
+            // don't start new fragments after cancellation
+            parallel_progress.check_cancelled()?;
+
             // allocate exactly the right amount of memory
             let bytes: usize = pixel_info
                 .surface_bytes(fragment.size)
@@ -171,7 +203,7 @@ fn encode_parallel(
             // progress will be reported per fragment
             encode(&mut buffer, *fragment, format, None, &options)?;
 
-            parallel_progress.submit(fragment.height() as u64);
+            parallel_progress.submit(fragment.height() as u64)?;
 
             debug_assert_eq!(buffer.len(), bytes);
             Ok(buffer)
@@ -179,8 +211,12 @@ fn encode_parallel(
         .collect();
 
     let encoded_fragments = result?;
-    for fragment in encoded_fragments {
-        writer.write_all(&fragment)?;
+    if let crate::PixelInfo::BiPlanar(info) = pixel_info {
+        write_bi_planar_fragments(writer, info, split.fragments(), &encoded_fragments)?;
+    } else {
+        for fragment in encoded_fragments {
+            writer.write_all(&fragment)?;
+        }
     }
 
     // Report 100%
@@ -194,21 +230,38 @@ fn encode_parallel(
 pub struct EncodeOptions {
     /// Whether to enable dithering for specific channels.
     ///
-    /// The dithering algorithm depends on the format. Uncompressed formats use
-    /// Floyd-Steinberg dithering, while block-compressed formats use a modified
-    /// version of the algorithm to dithering within a block.
+    /// Uncompressed formats use the algorithm selected by
+    /// [`EncodeOptions::dithering_algorithm`], while block-compressed formats
+    /// always use a modified version of Floyd-Steinberg dithering within a
+    /// block.
     ///
     /// Notes:
-    /// 1. Dithering is not supported for high-precision uncompressed formats
-    ///    (>= 16 bits per pixel). This option will be ignored for those formats.
-    /// 2. YUV formats are not supported.
+    /// 1. Formats that can represent the input losslessly (e.g.
+    ///    `R32G32B32A32_FLOAT`) are never dithered.
+    /// 2. Bi-planar YUV formats (`NV12`, `P010`, `P016`) are not supported.
     ///
     /// Default: [`Dithering::None`]
     pub dithering: Dithering,
+    /// The dithering algorithm for uncompressed formats.
+    ///
+    /// This option is ignored if dithering is disabled and for
+    /// block-compressed formats.
+    ///
+    /// Default: [`DitheringAlgorithm::FloydSteinberg`]
+    pub dithering_algorithm: DitheringAlgorithm,
     /// The error metric for block compression formats.
     ///
     /// Default: [`ErrorMetric::Uniform`]
     pub error_metric: ErrorMetric,
+    /// The relative importance of the channels for block compression
+    /// formats. See [`ChannelWeights`] for details.
+    ///
+    /// The weights of the color channels are ignored for BC1-BC3 color
+    /// blocks if [`EncodeOptions::error_metric`] is
+    /// [`ErrorMetric::Perceptual`].
+    ///
+    /// Default: [`ChannelWeights::UNIFORM`]
+    pub channel_weights: ChannelWeights,
     /// The compression quality.
     ///
     /// This option is naturally ignored for uncompressed formats.
@@ -231,21 +284,78 @@ pub struct EncodeOptions {
     ///
     /// Default: `true`
     pub parallel: bool,
+    /// Rate-distortion optimization for block-compressed formats.
+    ///
+    /// If enabled, the encoder will reuse endpoints and indexes of recently
+    /// encoded blocks when doing so only slightly increases the error. This
+    /// doesn't change the size of the DDS file, but it makes the data
+    /// significantly more compressible with general-purpose compressors like
+    /// zstd, LZ4, or Deflate.
+    ///
+    /// This option is supported by BC1-BC5 and ignored for all other formats.
+    /// Note that blocks are only reused within the same chunk of the image,
+    /// so parallel encoding may reduce the effectiveness of RDO slightly.
+    ///
+    /// Default: `None`
+    pub rdo: Option<RdoOptions>,
+    /// Options specific to block-compressed formats.
+    ///
+    /// Default: [`BcOptions::default()`]
+    pub bc: BcOptions,
+    /// How the channels of the encoded image are built from the channels of
+    /// the input image.
+    ///
+    /// The mapping is applied on the fly while encoding, so the input image
+    /// doesn't have to be copied. This makes it easy to e.g. pack a grayscale
+    /// mask into the alpha channel or to invert the green channel of a normal
+    /// map. See [`ChannelMapping`] for details.
+    ///
+    /// When generating mipmaps with [`crate::Encoder`], the mapping is applied
+    /// before the mipmaps are generated, so straight alpha is resized with
+    /// the mapped alpha channel. This requires a copy of the mapped image in
+    /// RGBA F32.
+    ///
+    /// Default: [`ChannelMapping::IDENTITY`]
+    pub channel_mapping: ChannelMapping,
+    /// Whether F32 images are in the range -1 to 1 when encoding SNORM
+    /// formats.
+    ///
+    /// By default, SNORM formats expect all inputs in the range 0 to 1, i.e.
+    /// 0 is encoded as -1, 0.5 as 0, and 1 as 1. If this option is enabled,
+    /// images with [`crate::Precision::F32`] are encoded as is instead, so
+    /// -1 is encoded as -1, 0 as 0, and 1 as 1. Values are rounded to the
+    /// nearest SNORM value, so images decoded with
+    /// [`crate::DecodeOptions::signed_snorm`] round-trip exactly.
+    ///
+    /// This option is ignored for non-SNORM formats and U8/U16 images. It is
+    /// applied after [`EncodeOptions::channel_mapping`], so the mapping
+    /// operates on signed values. E.g. [`crate::ChannelSource::Zero`] is
+    /// encoded as 0 and [`crate::ChannelSource::One`] as 1.
+    ///
+    /// Default: `false`
+    pub signed_snorm: bool,
 }
 impl Default for EncodeOptions {
     fn default() -> Self {
         Self {
             dithering: Dithering::None,
+            dithering_algorithm: DitheringAlgorithm::FloydSteinberg,
             error_metric: ErrorMetric::Uniform,
+            channel_weights: ChannelWeights::UNIFORM,
             quality: CompressionQuality::Normal,
             parallel: true,
+            rdo: None,
+            bc: BcOptions::default(),
+            channel_mapping: ChannelMapping::IDENTITY,
+            signed_snorm: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Dithering {
     /// Dithering is disabled for all channels.
+    #[default]
     None = 0b00,
     /// Dithering is enabled for all channels (RGBA).
     ColorAndAlpha = 0b11,
@@ -283,20 +393,198 @@ impl Dithering {
         }
     }
 }
-impl Default for Dithering {
-    fn default() -> Self {
-        Self::None
+
+/// The algorithm used for dithering uncompressed formats.
+///
+/// See [`EncodeOptions::dithering_algorithm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum DitheringAlgorithm {
+    /// Floyd-Steinberg error diffusion.
+    ///
+    /// This generally gives the best quality, but may show directional
+    /// "worm" artifacts in smooth gradients.
+    #[default]
+    FloydSteinberg,
+    /// Atkinson error diffusion.
+    ///
+    /// Only 3/4 of the error is diffused, which preserves contrast and gives
+    /// a cleaner look at the cost of detail in very light and dark areas.
+    Atkinson,
+    /// Sierra (3-row) error diffusion.
+    ///
+    /// The error is diffused to more neighbors than with Floyd-Steinberg,
+    /// which gives fewer directional artifacts.
+    Sierra,
+    /// Ordered dithering with an 8x8 Bayer matrix.
+    ///
+    /// This produces a regular cross-hatch pattern. Since the pattern only
+    /// depends on the position of a pixel, it doesn't prevent parallel
+    /// encoding.
+    Ordered,
+    /// Ordered dithering with a 64x64 blue noise texture.
+    ///
+    /// This produces an unstructured, high-frequency noise pattern that is
+    /// less visible than the pattern of [`DitheringAlgorithm::Ordered`]. Like
+    /// ordered dithering, it doesn't prevent parallel encoding.
+    BlueNoise,
+}
+impl DitheringAlgorithm {
+    /// Whether the dither pattern only depends on the position of a pixel.
+    ///
+    /// Position-based algorithms allow the image to be split and encoded in
+    /// parallel without visible seams. Error diffusion algorithms do not.
+    pub const fn is_position_based(self) -> bool {
+        dither::ThresholdMap::new(self).is_some()
+    }
+
+    /// The width and height of the repeating pattern of position-based
+    /// algorithms.
+    pub(crate) const fn pattern_size(self) -> Option<usize> {
+        match dither::ThresholdMap::new(self) {
+            Some(map) => Some(map.size()),
+            None => None,
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum ErrorMetric {
+    #[default]
     Uniform,
     Perceptual,
 }
-impl Default for ErrorMetric {
+
+/// The relative importance of the R, G, B, and A channels when encoding
+/// block-compressed formats.
+///
+/// This is useful for channel-packed textures (e.g. roughness, metalness, and
+/// ambient occlusion in R, G, and B) where some channels are more important
+/// than others. The error of each channel is multiplied by its weight.
+///
+/// Only the ratios between the weights matter. A weight of 0 means that the
+/// error of the channel is ignored. Negative weights are treated as 0. If all
+/// weights are 0, all channels are weighted equally.
+///
+/// Since BC5 channels and the alpha channel of BC3 are encoded
+/// independently, their weights can't shift error between channels. Instead,
+/// the weight determines how much effort is spent on the channel. Relative to
+/// the largest weight of all 4 channels, channels with a weight of at least
+/// 0.5 are encoded with the selected [`CompressionQuality`], channels with a
+/// weight of at least 0.1 with one level lower, channels with a smaller weight
+/// with two levels lower, and channels with a weight of 0 with
+/// [`CompressionQuality::Fast`]. BC4 only has a single channel, so weights
+/// have no effect on it.
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelWeights(pub [f32; 4]);
+impl ChannelWeights {
+    /// All channels are equally important.
+    pub const UNIFORM: Self = Self([1.0; 4]);
+
+    /// The weights of the R, G, and B channels, normalized such that the
+    /// largest of them is 1.
+    pub(crate) fn rgb(self) -> [f32; 3] {
+        let [r, g, b, _] = self.0;
+        normalize_weights([r, g, b])
+    }
+    /// The weight of the given channel (R, G, B, A), normalized such that the
+    /// largest of all 4 weights is 1.
+    pub(crate) fn normalized(self, channel: usize) -> f32 {
+        normalize_weights(self.0)[channel]
+    }
+}
+impl Default for ChannelWeights {
+    fn default() -> Self {
+        Self::UNIFORM
+    }
+}
+impl PartialEq for ChannelWeights {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.map(f32::to_bits) == other.0.map(f32::to_bits)
+    }
+}
+impl Eq for ChannelWeights {}
+impl Hash for ChannelWeights {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.map(f32::to_bits).hash(state);
+    }
+}
+
+/// Replaces negative and invalid weights with 0 and scales the weights such
+/// that the largest weight is 1. If all weights are 0, all weights are 1.
+pub(crate) fn normalize_weights<const N: usize>(weights: [f32; N]) -> [f32; N] {
+    let weights = weights.map(|w| if w > 0.0 { w } else { 0.0 });
+    let max = weights.iter().copied().fold(0.0, f32::max);
+    if max > 0.0 && max.is_finite() {
+        weights.map(|w| w / max)
+    } else {
+        [1.0; N]
+    }
+}
+
+/// Options for block-compressed formats.
+///
+/// See [`EncodeOptions::bc`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct BcOptions {
+    /// The alpha threshold for BC1.
+    ///
+    /// BC1 only supports fully opaque and fully transparent pixels. Pixels
+    /// with an alpha value greater than or equal to the threshold are encoded
+    /// as opaque, all other pixels as transparent. This is typically used to
+    /// tune the edges of cut-out textures (e.g. foliage).
+    ///
+    /// Default: `0.5`
+    pub alpha_threshold: f32,
+    /// Whether BC1 may use 3-color mode for fully opaque blocks.
+    ///
+    /// Blocks with transparent pixels always use 3-color mode. For opaque
+    /// blocks, the encoder will use 3-color mode if it gives a better result
+    /// than 4-color mode, but only for [`CompressionQuality::High`] and
+    /// above. Some older hardware and decoders mishandle 3-color mode, so it
+    /// can be disabled for opaque blocks here.
+    ///
+    /// Default: `true`
+    pub opaque_three_color: bool,
+    /// Whether BC1 may use the black of 3-color mode for near-black pixels in
+    /// opaque blocks.
+    ///
+    /// This frees up the other colors of the block for the remaining pixels,
+    /// which noticeably improves the quality of dark areas. However, this
+    /// black is transparent black, so these pixels will decode with an alpha
+    /// of 0. Only enable this option if the alpha channel of the texture is
+    /// ignored.
+    ///
+    /// This option is ignored if [`BcOptions::opaque_three_color`] is `false`.
+    /// Unlike regular 3-color blocks, it is used for all compression
+    /// qualities.
+    ///
+    /// Default: `false`
+    pub three_color_black: bool,
+}
+impl Default for BcOptions {
     fn default() -> Self {
-        Self::Uniform
+        Self {
+            alpha_threshold: 0.5,
+            opaque_three_color: true,
+            three_color_black: false,
+        }
+    }
+}
+impl PartialEq for BcOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.alpha_threshold.to_bits() == other.alpha_threshold.to_bits()
+            && self.opaque_three_color == other.opaque_three_color
+            && self.three_color_black == other.three_color_black
+    }
+}
+impl Eq for BcOptions {}
+impl Hash for BcOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.alpha_threshold.to_bits().hash(state);
+        self.opaque_three_color.hash(state);
+        self.three_color_black.hash(state);
     }
 }
 
@@ -324,18 +612,14 @@ impl Default for ErrorMetric {
 ///
 /// Encoding DDS images is embarrassingly parallel, so using multiple cores
 /// should make encoding roughly 4-10x faster on normal consumer hardware.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub enum CompressionQuality {
     Fast,
+    #[default]
     Normal,
     High,
     Unreasonable,
 }
-impl Default for CompressionQuality {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
 
 /// The preferred group size when splitting an image into chunks for parallel
 /// encoding.
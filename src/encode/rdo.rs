@@ -0,0 +1,315 @@
+//! Rate-distortion optimization (RDO) for BCn block formats.
+//!
+//! BCn data itself is not compressed any further, but it is often stored
+//! compressed with a general-purpose compressor (zstd, LZ4, Deflate, ...).
+//! These compressors work best when the data contains long repeated byte
+//! sequences. RDO makes the encoded blocks more repetitive by reusing
+//! endpoints and index patterns of recently emitted blocks, as long as the
+//! additional error is worth the reduction in size.
+//!
+//! This is done as a post-process on every encoded block. Each 8-byte part of
+//! a block (e.g. the color part and the alpha part of BC3) is optimized
+//! independently. The following candidates are tried for each part of each
+//! block in the lookback window:
+//!
+//! 1. Copy the entire part.
+//! 2. Copy the endpoints and pick the best indexes for them (BC1 only).
+//! 3. Copy the indexes and keep the current endpoints (BC1 and BC4).
+//!
+//! The candidate with the lowest cost `D + lambda * R` is chosen, where `D` is
+//! the squared error relative to the source pixels of the block and `R` is a
+//! rough estimate of how many bits a LZ-style compressor needs to store the
+//! part.
+
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use crate::decode::bc_blocks;
+use crate::n8;
+
+/// Options for rate-distortion optimized encoding.
+///
+/// See [`EncodeOptions::rdo`](crate::EncodeOptions::rdo).
+#[derive(Debug, Clone, Copy)]
+pub struct RdoOptions {
+    /// How much quality to trade for smaller compressed sizes.
+    ///
+    /// The encoder will accept an additional squared error of up to `lambda`
+    /// (in 8-bit units, summed over all pixels and channels of a block part)
+    /// for every bit it expects to save. Higher values produce smaller files
+    /// with more artifacts. Values up to around `16.0` are hardly noticeable,
+    /// while values of `100.0` and above produce visible artifacts for most
+    /// images.
+    ///
+    /// A value of `0.0` will only reuse parts that are closer to the source
+    /// pixels than the originally encoded part.
+    ///
+    /// Default: `16.0`
+    pub lambda: f32,
+    /// The number of previously encoded blocks that are considered for reuse.
+    ///
+    /// Larger windows find more matches, but encoding time grows linearly with
+    /// the window size. It's not useful to make the window larger than the
+    /// window of the compressor that is used afterward.
+    ///
+    /// Default: `64`
+    pub window: u16,
+}
+impl Default for RdoOptions {
+    fn default() -> Self {
+        Self {
+            lambda: 16.0,
+            window: 64,
+        }
+    }
+}
+impl PartialEq for RdoOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.lambda.to_bits() == other.lambda.to_bits() && self.window == other.window
+    }
+}
+impl Eq for RdoOptions {}
+impl Hash for RdoOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.lambda.to_bits().hash(state);
+        self.window.hash(state);
+    }
+}
+
+/// The kind of an 8-byte part of a BCn block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockPart {
+    /// A BC1 block, including the transparency of 3-color mode blocks.
+    Bc1,
+    /// The color block of BC2 and BC3, which always uses 4 colors.
+    Bc1FourColor,
+    /// The explicit 4-bit alpha of BC2.
+    Bc2Alpha,
+    /// A BC4 block.
+    Bc4 { snorm: bool },
+}
+
+/// The estimated cost of a literal byte in bits.
+const LITERAL_BITS: u32 = 8;
+/// The estimated cost of a match in bits.
+///
+/// Most LZ compressors can't encode matches shorter than 3 or 4 bytes, so
+/// only runs of at least 4 bytes are counted as matches.
+const MATCH_BITS: u32 = 24;
+
+/// The source pixels of each part of a block.
+///
+/// The pixels use the same representation as decoded parts: color parts use
+/// RGB (and A for [`BlockPart::Bc1`]) and single-channel parts only use the
+/// first channel. Unused channels are 0.
+pub(crate) struct BlockSource {
+    enabled: bool,
+    parts: [Decoded; 2],
+}
+impl BlockSource {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            parts: [[[0; 4]; 16]; 2],
+        }
+    }
+
+    /// Sets the source of a color part.
+    pub fn set_color(&mut self, part: usize, block: &[[f32; 4]; 16], alpha: bool) {
+        if self.enabled {
+            self.parts[part] = block.map(|[r, g, b, a]| {
+                let a = if alpha { n8::from_f32(a) } else { 0 };
+                [n8::from_f32(r), n8::from_f32(g), n8::from_f32(b), a]
+            });
+        }
+    }
+    /// Sets the source of a single-channel part.
+    pub fn set_single(&mut self, part: usize, block: &[f32; 16]) {
+        if self.enabled {
+            self.parts[part] = block.map(|v| [n8::from_f32(v), 0, 0, 0]);
+        }
+    }
+}
+
+/// The state of the RDO post-process for a sequence of blocks.
+pub(crate) struct Rdo<const BLOCK_BYTES: usize> {
+    lambda: f32,
+    window: usize,
+    parts: &'static [BlockPart],
+    history: VecDeque<[u8; BLOCK_BYTES]>,
+}
+impl<const BLOCK_BYTES: usize> Rdo<BLOCK_BYTES> {
+    pub fn new(options: RdoOptions, parts: &'static [BlockPart]) -> Self {
+        debug_assert_eq!(parts.len() * 8, BLOCK_BYTES);
+
+        let window = options.window as usize;
+        Self {
+            lambda: options.lambda.max(0.0),
+            window,
+            parts,
+            history: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Optimizes the given block and adds it to the history.
+    pub fn optimize(&mut self, block: &mut [u8; BLOCK_BYTES], source: &BlockSource) {
+        if self.window == 0 {
+            return;
+        }
+
+        for (part_index, &kind) in self.parts.iter().enumerate() {
+            let range = part_index * 8..(part_index + 1) * 8;
+            let mut part = [0; 8];
+            part.copy_from_slice(&block[range.clone()]);
+
+            let best = self.optimize_part(part, &source.parts[part_index], part_index, kind);
+            block[range].copy_from_slice(&best);
+        }
+
+        if self.history.len() == self.window {
+            self.history.pop_back();
+        }
+        self.history.push_front(*block);
+    }
+
+    fn optimize_part(
+        &self,
+        original: [u8; 8],
+        reference: &Decoded,
+        part_index: usize,
+        kind: BlockPart,
+    ) -> [u8; 8] {
+        let cost = |candidate: [u8; 8], bits: u32| {
+            distortion(reference, &decode(kind, candidate)) + self.lambda * bits as f32
+        };
+
+        let mut best = original;
+        let mut best_cost = cost(original, 8 * LITERAL_BITS);
+        let mut consider = |candidate: [u8; 8], bits: u32| {
+            let cost = cost(candidate, bits);
+            if cost < best_cost {
+                best = candidate;
+                best_cost = cost;
+            }
+        };
+
+        for previous in &self.history {
+            let mut previous_part = [0; 8];
+            previous_part.copy_from_slice(&previous[part_index * 8..(part_index + 1) * 8]);
+
+            // 1. reuse the entire part
+            consider(previous_part, MATCH_BITS);
+
+            match kind {
+                BlockPart::Bc1 | BlockPart::Bc1FourColor => {
+                    // 2. reuse the endpoints
+                    let endpoints = [
+                        previous_part[0],
+                        previous_part[1],
+                        previous_part[2],
+                        previous_part[3],
+                    ];
+                    consider(
+                        bc1_best_indexes(endpoints, reference, kind),
+                        MATCH_BITS + 4 * LITERAL_BITS,
+                    );
+
+                    // 3. reuse the indexes
+                    let mut candidate = original;
+                    candidate[4..].copy_from_slice(&previous_part[4..]);
+                    consider(candidate, MATCH_BITS + 4 * LITERAL_BITS);
+                }
+                BlockPart::Bc4 { .. } => {
+                    // 3. reuse the indexes
+                    // The endpoints are only 2 bytes, so reusing them doesn't
+                    // produce a match.
+                    let mut candidate = original;
+                    candidate[2..].copy_from_slice(&previous_part[2..]);
+                    consider(candidate, MATCH_BITS + 2 * LITERAL_BITS);
+                }
+                BlockPart::Bc2Alpha => {}
+            }
+        }
+
+        best
+    }
+}
+
+/// The decoded pixels of a block part. Unused channels are 0.
+type Decoded = [[u8; 4]; 16];
+
+fn decode(kind: BlockPart, part: [u8; 8]) -> Decoded {
+    match kind {
+        BlockPart::Bc1 => bc_blocks::bc1_u8_rgba(part),
+        BlockPart::Bc1FourColor => {
+            let mut pixels = bc_blocks::bc1_no_default_u8_rgba(part);
+            pixels.iter_mut().for_each(|p| p[3] = 0);
+            pixels
+        }
+        BlockPart::Bc2Alpha => {
+            let bits = u64::from_le_bytes(part);
+            let mut pixels = [[0; 4]; 16];
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                pixel[0] = ((bits >> (i * 4)) & 0xF) as u8 * 17;
+            }
+            pixels
+        }
+        BlockPart::Bc4 { snorm: false } => bc_blocks::bc4u_gray::<u8>(part).map(|[v]| [v, 0, 0, 0]),
+        BlockPart::Bc4 { snorm: true } => bc_blocks::bc4s_gray::<u8>(part).map(|[v]| [v, 0, 0, 0]),
+    }
+}
+
+fn distortion(a: &Decoded, b: &Decoded) -> f32 {
+    let mut sum: u32 = 0;
+    for (a, b) in a.iter().zip(b) {
+        for c in 0..4 {
+            let diff = a[c] as i32 - b[c] as i32;
+            sum += (diff * diff) as u32;
+        }
+    }
+    sum as f32
+}
+
+/// Returns a BC1 block with the given endpoints and the indexes that best
+/// approximate the reference pixels.
+fn bc1_best_indexes(endpoints: [u8; 4], reference: &Decoded, kind: BlockPart) -> [u8; 8] {
+    let mut palette = [[0_u8; 4]; 4];
+    for (index, color) in palette.iter_mut().enumerate() {
+        let pattern = index as u8 * 0x55;
+        let block = [
+            endpoints[0],
+            endpoints[1],
+            endpoints[2],
+            endpoints[3],
+            pattern,
+            pattern,
+            pattern,
+            pattern,
+        ];
+        *color = decode(kind, block)[0];
+    }
+
+    let mut indexes: u32 = 0;
+    for (i, pixel) in reference.iter().enumerate() {
+        let mut best_index = 0;
+        let mut best_error = u32::MAX;
+        for (index, color) in palette.iter().enumerate() {
+            let error: u32 = (0..4)
+                .map(|c| {
+                    let diff = pixel[c] as i32 - color[c] as i32;
+                    (diff * diff) as u32
+                })
+                .sum();
+            if error < best_error {
+                best_index = index as u32;
+                best_error = error;
+            }
+        }
+        indexes |= best_index << (i * 2);
+    }
+
+    let mut block = [0; 8];
+    block[..4].copy_from_slice(&endpoints);
+    block[4..].copy_from_slice(&indexes.to_le_bytes());
+    block
+}
@@ -1,6 +1,11 @@
-use crate::{as_rgba_f32, cast, ch, n1, n8, util, yuv16, yuv8, EncodingError, Report};
+use glam::Vec4;
 
-use super::encoder::{Args, Encoder, EncoderSet, Flags};
+use crate::{cast, ch, n1, n8, util, yuv16, yuv8, EncodingError, Report};
+
+use super::{
+    dither::{channel_mask, unorm_step, yuv_step, ErrorDiffusion, ThresholdMap, DITHER_GROUP_SIZE},
+    encoder::{input_as_rgba_f32, Args, Encoder, EncoderSet, Flags},
+};
 
 // helpers
 
@@ -43,6 +48,7 @@ where
         writer,
         width,
         height,
+        options,
         mut progress,
         ..
     } = args;
@@ -66,7 +72,7 @@ where
         for chunk in y_line.chunks(chunk_size) {
             if chunk_index % 4096 == 0 {
                 // occasionally report progress
-                progress.report(chunk_index as f32 / chunk_count as f32);
+                progress.checked_report(chunk_index as f32 / chunk_count as f32)?;
             }
             chunk_index += 1;
 
@@ -75,7 +81,10 @@ where
             let intermediate = &mut intermediate_buffer[..pixels];
             let encoded = &mut encoded_buffer[..util::div_ceil(pixels, block_width)];
 
-            process(as_rgba_f32(color, chunk, intermediate), encoded);
+            process(
+                input_as_rgba_f32(color, chunk, intermediate, &options),
+                encoded,
+            );
 
             cast::ToLe::to_le(encoded);
 
@@ -97,6 +106,152 @@ macro_rules! universal_subsample {
     }};
 }
 
+/// Functions for dithering a sub-sampled format.
+///
+/// Each block of pixels is encoded in 3 steps:
+///
+/// 1. `shared` computes the values shared by all pixels of the block (e.g.
+///    the chroma of YUY2).
+/// 2. `quantize` computes the value of each pixel (e.g. the luma of YUY2)
+///    given the shared values. It returns the pixel value and the decoded
+///    RGBA color of the pixel.
+/// 3. `pack` puts the shared and pixel values together into the encoded
+///    block.
+///
+/// Pixels are quantized one after the other, so the error of a pixel can be
+/// diffused to the next pixel within the same block.
+struct SubsampleDither<const BLOCK_WIDTH: usize, Shared, Own, EncodedBlock> {
+    /// The quantization step of each channel of a pixel for position-based
+    /// dithering.
+    step: fn(Vec4) -> Vec4,
+    shared: fn(&[Vec4; BLOCK_WIDTH]) -> Shared,
+    quantize: fn(&Shared, Vec4) -> (Own, Vec4),
+    pack: fn(&Shared, [Own; BLOCK_WIDTH]) -> EncodedBlock,
+}
+
+fn uncompressed_universal_subsample_dither<const BLOCK_WIDTH: usize, Shared, Own, EncodedBlock>(
+    args: Args,
+    dither: SubsampleDither<BLOCK_WIDTH, Shared, Own, EncodedBlock>,
+) -> Result<(), EncodingError>
+where
+    Own: Default + Copy,
+    EncodedBlock: Default + Copy + cast::ToLe + cast::Castable,
+{
+    let Args {
+        data,
+        color,
+        writer,
+        width,
+        height,
+        options,
+        mut progress,
+        ..
+    } = args;
+    let bytes_per_pixel = color.bytes_per_pixel() as usize;
+
+    let mut diffusion = ErrorDiffusion::new(options.dithering_algorithm, width);
+    let threshold_map = ThresholdMap::new(options.dithering_algorithm);
+    let error_mask = channel_mask(options.dithering);
+
+    const BUFFER_PIXELS: usize = 512;
+    let mut intermediate_buffer = [[0_f32; 4]; BUFFER_PIXELS];
+    let mut encoded_buffer = [EncodedBlock::default(); BUFFER_PIXELS / 2];
+
+    let chunk_pixels = BUFFER_PIXELS / BLOCK_WIDTH * BLOCK_WIDTH;
+    let chunk_size = chunk_pixels * bytes_per_pixel;
+
+    let chunk_count = height * util::div_ceil(width * bytes_per_pixel, chunk_size);
+    let mut chunk_index: usize = 0;
+
+    for (y, y_line) in data.chunks(width * bytes_per_pixel).enumerate() {
+        debug_assert!(y_line.len() == width * bytes_per_pixel);
+
+        if let Some(diffusion) = &mut diffusion {
+            if y > 0 {
+                diffusion.next_row();
+            }
+        }
+        let mut x: usize = 0;
+
+        for chunk in y_line.chunks(chunk_size) {
+            if chunk_index % 4096 == 0 {
+                // occasionally report progress
+                progress.checked_report(chunk_index as f32 / chunk_count as f32)?;
+            }
+            chunk_index += 1;
+
+            let pixels = chunk.len() / bytes_per_pixel;
+
+            let intermediate = &mut intermediate_buffer[..pixels];
+            let encoded = &mut encoded_buffer[..util::div_ceil(pixels, BLOCK_WIDTH)];
+            let intermediate = input_as_rgba_f32(color, chunk, intermediate, &options);
+
+            for (block, out) in intermediate.chunks(BLOCK_WIDTH).zip(encoded.iter_mut()) {
+                // the last partial block is filled with the last pixel
+                let pixel_at = |i: usize| Vec4::from(block[i.min(block.len() - 1)]);
+
+                // Position-based dithering uses the threshold of the first
+                // pixel for the shared values of the block. Averaging the
+                // thresholds of all pixels would cancel them out.
+                let mut inputs = [Vec4::ZERO; BLOCK_WIDTH];
+                for (i, input) in inputs.iter_mut().enumerate() {
+                    let pixel = pixel_at(i);
+                    *input = if let Some(diffusion) = &diffusion {
+                        pixel + diffusion.error_at((x + i).min(width - 1))
+                    } else if let Some(map) = &threshold_map {
+                        pixel + map.threshold(x, y) * (dither.step)(pixel) * error_mask
+                    } else {
+                        pixel
+                    };
+                }
+
+                let shared = (dither.shared)(&inputs);
+
+                let mut own = [Own::default(); BLOCK_WIDTH];
+                for (i, own) in own.iter_mut().enumerate() {
+                    let px = (x + i).min(width - 1);
+                    let pixel = pixel_at(i);
+                    // the error of the previous pixels in this block is
+                    // included here
+                    let input = if let Some(diffusion) = &diffusion {
+                        pixel + diffusion.error_at(px)
+                    } else if let Some(map) = &threshold_map {
+                        pixel + map.threshold(px, y) * (dither.step)(pixel) * error_mask
+                    } else {
+                        pixel
+                    };
+
+                    let (value, decoded) = (dither.quantize)(&shared, input);
+                    *own = value;
+
+                    if let Some(diffusion) = &mut diffusion {
+                        if i < block.len() {
+                            diffusion.diffuse(px, (input - decoded) * error_mask);
+                        }
+                    }
+                }
+
+                *out = (dither.pack)(&shared, own);
+                x += block.len();
+            }
+
+            cast::ToLe::to_le(encoded);
+            writer.write_all(cast::as_bytes(encoded))?;
+        }
+    }
+
+    Ok(())
+}
+
+macro_rules! universal_subsample_dither {
+    ($block_width:literal, $out:ty, $dither:expr) => {
+        Encoder::new_universal(|args| {
+            uncompressed_universal_subsample_dither::<$block_width, _, _, $out>(args, $dither)
+        })
+        .with_group_size(DITHER_GROUP_SIZE)
+    };
+}
+
 // encoders
 
 fn to_rgbg([p0, p1]: &[[f32; 4]; 2]) -> [u8; 4] {
@@ -107,15 +262,50 @@ fn to_rgbg([p0, p1]: &[[f32; 4]; 2]) -> [u8; 4] {
     [r, g0, b, g1]
 }
 
-pub(crate) const R8G8_B8G8_UNORM: EncoderSet =
-    EncoderSet::new(&[universal_subsample!(2, [u8; 4], to_rgbg).add_flags(Flags::EXACT_U8)]);
+fn rgbg_shared([p0, p1]: &[Vec4; 2]) -> [u8; 2] {
+    let r = n8::from_f32((p0.x + p1.x) * 0.5);
+    let b = n8::from_f32((p0.z + p1.z) * 0.5);
+    [r, b]
+}
+fn rgbg_quantize(&[r, b]: &[u8; 2], pixel: Vec4) -> (u8, Vec4) {
+    let g = n8::from_f32(pixel.y);
+    let decoded = Vec4::new(n8::f32(r), n8::f32(g), n8::f32(b), pixel.w);
+    (g, decoded)
+}
+
+pub(crate) const R8G8_B8G8_UNORM: EncoderSet = EncoderSet::new(&[
+    universal_subsample!(2, [u8; 4], to_rgbg).add_flags(Flags::EXACT_U8),
+    universal_subsample_dither!(
+        2,
+        [u8; 4],
+        SubsampleDither {
+            step: |_| unorm_step([8; 4]),
+            shared: rgbg_shared,
+            quantize: rgbg_quantize,
+            pack: |&[r, b], [g0, g1]| [r, g0, b, g1],
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
-pub(crate) const G8R8_G8B8_UNORM: EncoderSet =
-    EncoderSet::new(&[universal_subsample!(2, [u8; 4], |pair| {
+pub(crate) const G8R8_G8B8_UNORM: EncoderSet = EncoderSet::new(&[
+    universal_subsample!(2, [u8; 4], |pair| {
         let [r, g0, b, g1] = to_rgbg(pair);
         [g0, r, g1, b]
     })
-    .add_flags(Flags::EXACT_U8)]);
+    .add_flags(Flags::EXACT_U8),
+    universal_subsample_dither!(
+        2,
+        [u8; 4],
+        SubsampleDither {
+            step: |_| unorm_step([8; 4]),
+            shared: rgbg_shared,
+            quantize: rgbg_quantize,
+            pack: |&[r, b], [g0, g1]| [g0, r, g1, b],
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
 fn to_yuy2([p0, p1]: &[[f32; 4]; 2]) -> [u8; 4] {
     let yuv1 = yuv8::from_rgb_f32([p0[0], p0[1], p0[2]]);
@@ -132,12 +322,49 @@ fn to_yuy2([p0, p1]: &[[f32; 4]; 2]) -> [u8; 4] {
     [y0, u, y1, v]
 }
 
-pub(crate) const YUY2: EncoderSet = EncoderSet::new(&[universal_subsample!(2, [u8; 4], to_yuy2)]);
+/// Returns the U and V values shared by a pair of pixels.
+fn yuy2_shared([p0, p1]: &[Vec4; 2]) -> [u8; 2] {
+    let [_, u, _, v] = to_yuy2(&[p0.to_array(), p1.to_array()]);
+    [u, v]
+}
+fn yuy2_quantize(&[u, v]: &[u8; 2], pixel: Vec4) -> (u8, Vec4) {
+    let [y, _, _] = yuv8::from_rgb_f32([pixel.x, pixel.y, pixel.z]);
+    let [r, g, b] = yuv8::f32([y, u, v]);
+    (y, Vec4::new(r, g, b, pixel.w))
+}
+
+pub(crate) const YUY2: EncoderSet = EncoderSet::new(&[
+    universal_subsample!(2, [u8; 4], to_yuy2),
+    universal_subsample_dither!(
+        2,
+        [u8; 4],
+        SubsampleDither {
+            step: |_| yuv_step(255.0, 0.0),
+            shared: yuy2_shared,
+            quantize: yuy2_quantize,
+            pack: |&[u, v], [y0, y1]| [y0, u, y1, v],
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
-pub(crate) const UYVY: EncoderSet = EncoderSet::new(&[universal_subsample!(2, [u8; 4], |pair| {
-    let [y0, u, y1, v] = to_yuy2(pair);
-    [u, y0, v, y1]
-})]);
+pub(crate) const UYVY: EncoderSet = EncoderSet::new(&[
+    universal_subsample!(2, [u8; 4], |pair| {
+        let [y0, u, y1, v] = to_yuy2(pair);
+        [u, y0, v, y1]
+    }),
+    universal_subsample_dither!(
+        2,
+        [u8; 4],
+        SubsampleDither {
+            step: |_| yuv_step(255.0, 0.0),
+            shared: yuy2_shared,
+            quantize: yuy2_quantize,
+            pack: |&[u, v], [y0, y1]| [u, y0, v, y1],
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
 fn to_y216([p0, p1]: &[[f32; 4]; 2]) -> [u16; 4] {
     let yuv1 = yuv16::from_rgb_f32([p0[0], p0[1], p0[2]]);
@@ -154,19 +381,78 @@ fn to_y216([p0, p1]: &[[f32; 4]; 2]) -> [u16; 4] {
     [y0, u, y1, v]
 }
 
-pub(crate) const Y210: EncoderSet =
-    EncoderSet::new(&[
-        universal_subsample!(2, [u16; 4], |pair| to_y216(pair).map(|c| c & 0xFFC0))
-            .add_flags(Flags::EXACT_U8),
-    ]);
+/// Returns the U and V values shared by a pair of pixels. `mask` is applied
+/// to all values to reduce their precision.
+fn y216_shared([p0, p1]: &[Vec4; 2], mask: u16) -> [u16; 2] {
+    let [_, u, _, v] = to_y216(&[p0.to_array(), p1.to_array()]);
+    [u & mask, v & mask]
+}
+fn y216_quantize(&[u, v]: &[u16; 2], pixel: Vec4, mask: u16) -> (u16, Vec4) {
+    let [y, _, _] = yuv16::from_rgb_f32([pixel.x, pixel.y, pixel.z]);
+    let y = y & mask;
+    let [r, g, b] = yuv16::f32([y, u, v]);
+    (y, Vec4::new(r, g, b, pixel.w))
+}
+
+pub(crate) const Y210: EncoderSet = EncoderSet::new(&[
+    universal_subsample!(2, [u16; 4], |pair| to_y216(pair).map(|c| c & 0xFFC0))
+        .add_flags(Flags::EXACT_U8),
+    universal_subsample_dither!(
+        2,
+        [u16; 4],
+        SubsampleDither {
+            step: |_| yuv_step(1023.0, 0.0),
+            shared: |pair| y216_shared(pair, 0xFFC0),
+            quantize: |shared, pixel| y216_quantize(shared, pixel, 0xFFC0),
+            pack: |&[u, v], [y0, y1]| [y0, u, y1, v],
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
-pub(crate) const Y216: EncoderSet =
-    EncoderSet::new(&[universal_subsample!(2, [u16; 4], to_y216).add_flags(Flags::EXACT_U8)]);
+pub(crate) const Y216: EncoderSet = EncoderSet::new(&[
+    universal_subsample!(2, [u16; 4], to_y216).add_flags(Flags::EXACT_U8),
+    universal_subsample_dither!(
+        2,
+        [u16; 4],
+        SubsampleDither {
+            step: |_| yuv_step(65535.0, 0.0),
+            shared: |pair| y216_shared(pair, 0xFFFF),
+            quantize: |shared, pixel| y216_quantize(shared, pixel, 0xFFFF),
+            pack: |&[u, v], [y0, y1]| [y0, u, y1, v],
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
-pub(crate) const R1_UNORM: EncoderSet = EncoderSet::new(&[universal_subsample!(8, u8, |block| {
-    let mut out = 0_u8;
-    for (i, &p) in block.iter().enumerate() {
-        out |= n1::from_f32(ch::rgba_to_grayscale(p)[0]) << (7 - i);
-    }
-    out
-})]);
+pub(crate) const R1_UNORM: EncoderSet = EncoderSet::new(&[
+    universal_subsample!(8, u8, |block| {
+        let mut out = 0_u8;
+        for (i, &p) in block.iter().enumerate() {
+            out |= n1::from_f32(ch::rgba_to_grayscale(p)[0]) << (7 - i);
+        }
+        out
+    }),
+    // error diffusion or halftoning instead of a hard threshold
+    universal_subsample_dither!(
+        8,
+        u8,
+        SubsampleDither {
+            step: |_| unorm_step([1, 0, 0, 0]),
+            shared: |_| (),
+            quantize: |_, pixel| {
+                let [gray] = ch::rgba_to_grayscale(pixel.to_array());
+                let bit = n1::from_f32(gray);
+                (bit, Vec4::new(n1::f32(bit), pixel.y, pixel.z, pixel.w))
+            },
+            pack: |_, bits| {
+                let mut out = 0_u8;
+                for (i, bit) in bits.into_iter().enumerate() {
+                    out |= bit << (7 - i);
+                }
+                out
+            },
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
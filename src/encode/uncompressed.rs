@@ -1,14 +1,17 @@
 use glam::Vec4;
 
 use crate::{
-    as_rgba_f32, cast, ch, convert_channels, convert_channels_for, fp10, fp11, fp16, n1, n10, n16,
-    n2, n4, n5, n6, n8, rgb9995f, s16, s8, util, xr10, yuv10, yuv16, yuv8, Channels, ColorFormat,
+    cast, ch, convert_channels, convert_channels_for, fp10, fp11, fp16, n1, n10, n16, n2, n4, n5,
+    n6, n8, rgb9995f, s16, s8, util, xr10, yuv10, yuv16, yuv8, Channels, ColorFormat,
     ColorFormatSet, EncodingError, Precision, Report,
 };
 
 use super::{
-    encoder::{Args, Encoder, EncoderSet, Flags},
-    Dithering,
+    dither::{
+        channel_mask, small_float_step, unorm_step, yuv_step, ErrorDiffusion, ThresholdMap,
+        DITHER_GROUP_SIZE,
+    },
+    encoder::{input_as_rgba_f32, Args, Encoder, EncoderSet, Flags},
 };
 
 // helpers
@@ -26,6 +29,7 @@ where
         data,
         color,
         writer,
+        options,
         mut progress,
         ..
     } = args;
@@ -40,7 +44,7 @@ where
     for (chunk_index, line) in data.chunks(chunk_size).enumerate() {
         // occasionally report progress
         if chunk_index % REPORT_FREQUENCY == 0 {
-            progress.report(chunk_index as f32 / chunk_count as f32);
+            progress.checked_report(chunk_index as f32 / chunk_count as f32)?;
         }
 
         debug_assert!(line.len() % bytes_per_pixel == 0);
@@ -49,7 +53,10 @@ where
         let intermediate = &mut intermediate_buffer[..pixels];
         let encoded = &mut encoded_buffer[..pixels];
 
-        process(as_rgba_f32(color, line, intermediate), encoded);
+        process(
+            input_as_rgba_f32(color, line, intermediate, &options),
+            encoded,
+        );
 
         cast::ToLe::to_le(encoded);
 
@@ -59,7 +66,11 @@ where
     Ok(())
 }
 
-fn uncompressed_universal_dither<EncodedPixel, F>(args: Args, f: F) -> Result<(), EncodingError>
+fn uncompressed_universal_dither<EncodedPixel, F>(
+    args: Args,
+    step: fn(Vec4) -> Vec4,
+    f: F,
+) -> Result<(), EncodingError>
 where
     EncodedPixel: Default + Copy + cast::ToLe + cast::Castable,
     F: Fn(Vec4) -> (EncodedPixel, Vec4),
@@ -76,17 +87,10 @@ where
     } = args;
     let bytes_per_pixel = color.bytes_per_pixel() as usize;
 
-    let error_padding = 2;
-    let mut error_buffer = vec![Vec4::ZERO; 2 * (width + error_padding * 2)];
-    let (mut current_line_error, mut next_line_error) =
-        error_buffer.split_at_mut(width + error_padding * 2);
+    let mut diffusion = ErrorDiffusion::new(options.dithering_algorithm, width);
+    let threshold_map = ThresholdMap::new(options.dithering_algorithm);
 
-    let error_mask = match options.dithering {
-        Dithering::None => Vec4::ZERO,
-        Dithering::ColorAndAlpha => Vec4::ONE,
-        Dithering::Color => Vec4::new(1.0, 1.0, 1.0, 0.0),
-        Dithering::Alpha => Vec4::new(0.0, 0.0, 0.0, 1.0),
-    };
+    let error_mask = channel_mask(options.dithering);
 
     const BUFFER_PIXELS: usize = 512;
     let mut intermediate_buffer = [[0_f32; 4]; BUFFER_PIXELS];
@@ -95,19 +99,21 @@ where
     let chunk_size = BUFFER_PIXELS * bytes_per_pixel;
     let chunk_count = height * util::div_ceil(width * bytes_per_pixel, chunk_size);
     let mut chunk_index: usize = 0;
-    for row in data.chunks(width * bytes_per_pixel) {
+    for (y, row) in data.chunks(width * bytes_per_pixel).enumerate() {
         debug_assert!(row.len() == width * bytes_per_pixel);
 
         // prepare error buffers
-        std::mem::swap(&mut current_line_error, &mut next_line_error);
-        next_line_error.fill(Vec4::ZERO);
-        let mut error_offset = error_padding;
-        let mut next_error_add = Vec4::ZERO;
+        if let Some(diffusion) = &mut diffusion {
+            if y > 0 {
+                diffusion.next_row();
+            }
+        }
+        let mut x: usize = 0;
 
         for line in row.chunks(chunk_size) {
             // occasionally report progress
             if chunk_index % REPORT_FREQUENCY == 0 {
-                progress.report(chunk_index as f32 / chunk_count as f32);
+                progress.checked_report(chunk_index as f32 / chunk_count as f32)?;
             }
             chunk_index += 1;
 
@@ -116,21 +122,22 @@ where
 
             let intermediate = &mut intermediate_buffer[..pixels];
             let encoded = &mut encoded_buffer[..pixels];
-            let intermediate = as_rgba_f32(color, line, intermediate);
+            let intermediate = input_as_rgba_f32(color, line, intermediate, &options);
 
             for (i, out) in intermediate.iter().zip(encoded.iter_mut()) {
-                let error = current_line_error[error_offset] + next_error_add;
-                let (encoded_pixel, mut error) = f(Vec4::from(*i) + error);
-
-                // diffuse error with Floyd-Steinberg weights
-                error *= error_mask;
-                next_error_add = error * (7.0 / 16.0);
-                next_line_error[error_offset - 1] += error * (3.0 / 16.0);
-                next_line_error[error_offset] += error * (5.0 / 16.0);
-                next_line_error[error_offset + 1] += error * (1.0 / 16.0);
-
-                *out = encoded_pixel;
-                error_offset += 1;
+                let pixel = Vec4::from(*i);
+
+                *out = if let Some(diffusion) = &mut diffusion {
+                    let (encoded_pixel, error) = f(pixel + diffusion.error_at(x));
+                    diffusion.diffuse(x, error * error_mask);
+                    encoded_pixel
+                } else if let Some(map) = &threshold_map {
+                    let offset = map.threshold(x, y) * step(pixel) * error_mask;
+                    f(pixel + offset).0
+                } else {
+                    f(pixel).0
+                };
+                x += 1;
             }
 
             cast::ToLe::to_le(encoded);
@@ -163,7 +170,7 @@ fn uncompressed_untyped(
     for (chunk_index, line) in data.chunks(chuck_size).enumerate() {
         // occasionally report progress
         if chunk_index % REPORT_FREQUENCY == 0 {
-            progress.report(chunk_index as f32 / chunk_count as f32);
+            progress.checked_report(chunk_index as f32 / chunk_count as f32)?;
         }
 
         debug_assert!(line.len() % bytes_per_pixel == 0);
@@ -244,17 +251,46 @@ macro_rules! universal_grayscale {
     };
 }
 macro_rules! universal_dither {
-    ($out:ty, $f:expr) => {
-        Encoder::new_universal(|args| uncompressed_universal_dither::<$out, _>(args, $f))
+    ($out:ty, step = $step:expr, $f:expr) => {
+        Encoder::new_universal(|args| uncompressed_universal_dither::<$out, _>(args, $step, $f))
+            .with_group_size(DITHER_GROUP_SIZE)
     };
 }
 
+/// Quantizes all channels of the pixel and returns the quantized values and
+/// the quantization error.
+fn quantize<T: Copy>(pixel: Vec4, to: fn(f32) -> T, back: fn(T) -> f32) -> ([T; 4], Vec4) {
+    let encoded = pixel.to_array().map(to);
+    let error = pixel - Vec4::from(encoded.map(back));
+    (encoded, error)
+}
+/// Quantizes the grayscale value (R) of the pixel and returns the quantized
+/// value and the quantization error.
+fn quantize_grayscale<T: Copy>(pixel: Vec4, to: fn(f32) -> T, back: fn(T) -> f32) -> (T, Vec4) {
+    let [gray] = ch::rgba_to_grayscale(pixel.to_array());
+    let encoded = to(gray);
+    (encoded, Vec4::new(gray - back(encoded), 0.0, 0.0, 0.0))
+}
+
+/// The quantization steps of SNORM8 and SNORM16 in the unsigned range 0 to 1.
+const S8_STEP: f32 = 1.0 / 254.0;
+const S16_STEP: f32 = 1.0 / 65534.0;
+
+fn fp16_step(pixel: Vec4) -> Vec4 {
+    Vec4::from(pixel.to_array().map(|c| small_float_step(c, 10)))
+}
+
 // encoders
 
 pub(crate) const R8G8B8_UNORM: EncoderSet = EncoderSet::new(&[
     Encoder::copy(ColorFormat::RGB_U8),
     color_convert!(ColorFormat::RGB_U8),
     universal!([u8; 3], |[r, g, b, _]| [r, g, b].map(n8::from_f32)),
+    universal_dither!([u8; 3], step = |_| unorm_step([8; 4]), |pixel| {
+        let ([r, g, b, _], error) = quantize(pixel, n8::from_f32, n8::f32);
+        ([r, g, b], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
 ]);
 
 pub(crate) const B8G8R8_UNORM: EncoderSet = EncoderSet::new(&[
@@ -272,18 +308,32 @@ pub(crate) const B8G8R8_UNORM: EncoderSet = EncoderSet::new(&[
         uncompressed_untyped(args, 3, process_line)
     }),
     universal!([u8; 3], |[r, g, b, _]| [b, g, r].map(n8::from_f32)),
+    universal_dither!([u8; 3], step = |_| unorm_step([8; 4]), |pixel| {
+        let ([r, g, b, _], error) = quantize(pixel, n8::from_f32, n8::f32);
+        ([b, g, r], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
 ]);
 
 pub(crate) const R8G8B8A8_UNORM: EncoderSet = EncoderSet::new(&[
     Encoder::copy(ColorFormat::RGBA_U8),
     color_convert!(ColorFormat::RGBA_U8),
     universal!([u8; 4], |rgba| rgba.map(n8::from_f32)),
+    universal_dither!([u8; 4], step = |_| unorm_step([8; 4]), |pixel| {
+        quantize(pixel, n8::from_f32, n8::f32)
+    })
+    .add_flags(Flags::DITHER_ALL),
 ]);
 
 pub(crate) const R8G8B8A8_SNORM: EncoderSet = EncoderSet::new(&[
     color_convert!(ColorFormat::RGBA_U8, snorm = true),
     universal!([u8; 4], |rgba| rgba.map(s8::from_uf32)),
-]);
+    universal_dither!([u8; 4], step = |_| Vec4::splat(S8_STEP), |pixel| {
+        quantize(pixel, s8::from_uf32, s8::uf32)
+    })
+    .add_flags(Flags::DITHER_ALL),
+])
+.snorm();
 
 pub(crate) const B8G8R8A8_UNORM: EncoderSet = EncoderSet::new(&[
     Encoder::new(ColorFormatSet::U8, Flags::EXACT_U8, |args| {
@@ -300,6 +350,11 @@ pub(crate) const B8G8R8A8_UNORM: EncoderSet = EncoderSet::new(&[
         uncompressed_untyped(args, 4, process_line)
     }),
     universal!([u8; 4], |[r, g, b, a]| [b, g, r, a].map(n8::from_f32)),
+    universal_dither!([u8; 4], step = |_| unorm_step([8; 4]), |pixel| {
+        let ([r, g, b, a], error) = quantize(pixel, n8::from_f32, n8::f32);
+        ([b, g, r, a], error)
+    })
+    .add_flags(Flags::DITHER_ALL),
 ]);
 
 pub(crate) const B8G8R8X8_UNORM: EncoderSet = EncoderSet::new(&[
@@ -325,6 +380,11 @@ pub(crate) const B8G8R8X8_UNORM: EncoderSet = EncoderSet::new(&[
         n8::from_f32(r),
         0xFF
     ]),
+    universal_dither!([u8; 4], step = |_| unorm_step([8; 4]), |pixel| {
+        let ([r, g, b, _], error) = quantize(pixel, n8::from_f32, n8::f32);
+        ([b, g, r, 0xFF], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
 ]);
 
 pub(crate) const B5G6R5_UNORM: EncoderSet = EncoderSet::new(&[
@@ -334,7 +394,7 @@ pub(crate) const B5G6R5_UNORM: EncoderSet = EncoderSet::new(&[
         let b = n5::from_f32(b) as u16;
         b | (g << 5) | (r << 11)
     }),
-    universal_dither!(u16, |pixel| {
+    universal_dither!(u16, step = |_| unorm_step([5, 6, 5, 0]), |pixel| {
         let r = n5::from_f32(pixel[0]) as u16;
         let g = n6::from_f32(pixel[1]) as u16;
         let b = n5::from_f32(pixel[2]) as u16;
@@ -355,7 +415,7 @@ pub(crate) const B5G5R5A1_UNORM: EncoderSet = EncoderSet::new(&[
         let a = n1::from_f32(a) as u16;
         b | (g << 5) | (r << 10) | (a << 15)
     }),
-    universal_dither!(u16, |pixel| {
+    universal_dither!(u16, step = |_| unorm_step([5, 5, 5, 1]), |pixel| {
         let r = n5::from_f32(pixel[0]) as u16;
         let g = n5::from_f32(pixel[1]) as u16;
         let b = n5::from_f32(pixel[2]) as u16;
@@ -375,10 +435,7 @@ pub(crate) const B5G5R5A1_UNORM: EncoderSet = EncoderSet::new(&[
 ]);
 
 fn rgba4_encode_with_error(pixel: Vec4) -> ([u8; 4], Vec4) {
-    let encoded = pixel.to_array().map(n4::from_f32);
-    let back = Vec4::from(encoded.map(n4::f32));
-    let error = pixel - back;
-    (encoded, error)
+    quantize(pixel, n4::from_f32, n4::f32)
 }
 
 pub(crate) const B4G4R4A4_UNORM: EncoderSet = EncoderSet::new(&[
@@ -389,7 +446,7 @@ pub(crate) const B4G4R4A4_UNORM: EncoderSet = EncoderSet::new(&[
         let a = n4::from_f32(a) as u16;
         b | (g << 4) | (r << 8) | (a << 12)
     }),
-    universal_dither!(u16, |pixel| {
+    universal_dither!(u16, step = |_| unorm_step([4; 4]), |pixel| {
         let (encoded, error) = rgba4_encode_with_error(pixel);
         let [r, g, b, a] = encoded.map(|c| c as u16);
         (b | (g << 4) | (r << 8) | (a << 12), error)
@@ -405,7 +462,7 @@ pub(crate) const A4B4G4R4_UNORM: EncoderSet = EncoderSet::new(&[
         let a = n4::from_f32(a) as u16;
         a | (b << 4) | (g << 8) | (r << 12)
     }),
-    universal_dither!(u16, |pixel| {
+    universal_dither!(u16, step = |_| unorm_step([4; 4]), |pixel| {
         let (encoded, error) = rgba4_encode_with_error(pixel);
         let [r, g, b, a] = encoded.map(|c| c as u16);
         (a | (b << 4) | (g << 8) | (r << 12), error)
@@ -417,60 +474,118 @@ pub(crate) const R8_UNORM: EncoderSet = EncoderSet::new(&[
     Encoder::copy(ColorFormat::GRAYSCALE_U8),
     color_convert!(ColorFormat::GRAYSCALE_U8),
     universal_grayscale!(u8, n8::from_f32),
+    universal_dither!(u8, step = |_| unorm_step([8; 4]), |pixel| {
+        quantize_grayscale(pixel, n8::from_f32, n8::f32)
+    })
+    .add_flags(Flags::DITHER_COLOR),
 ]);
 
 pub(crate) const R8_SNORM: EncoderSet = EncoderSet::new(&[
     color_convert!(ColorFormat::GRAYSCALE_U8, snorm = true),
     universal_grayscale!(u8, s8::from_uf32),
+    universal_dither!(u8, step = |_| Vec4::splat(S8_STEP), |pixel| {
+        quantize_grayscale(pixel, s8::from_uf32, s8::uf32)
+    })
+    .add_flags(Flags::DITHER_COLOR),
+])
+.snorm();
+
+pub(crate) const R8G8_UNORM: EncoderSet = EncoderSet::new(&[
+    universal!([u8; 2], |[r, g, _, _]| [r, g].map(n8::from_f32)).add_flags(Flags::EXACT_U8),
+    universal_dither!([u8; 2], step = |_| unorm_step([8; 4]), |pixel| {
+        let (encoded, mut error) = quantize(pixel, n8::from_f32, n8::f32);
+        error.z = 0.0;
+        error.w = 0.0;
+        ([encoded[0], encoded[1]], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
 ]);
 
-pub(crate) const R8G8_UNORM: EncoderSet =
-    EncoderSet::new(&[
-        universal!([u8; 2], |[r, g, _, _]| [r, g].map(n8::from_f32)).add_flags(Flags::EXACT_U8)
-    ]);
-
-pub(crate) const R8G8_SNORM: EncoderSet =
-    EncoderSet::new(&[
-        universal!([u8; 2], |[r, g, _, _]| [r, g].map(s8::from_uf32)).add_flags(Flags::EXACT_U8),
-    ]);
+pub(crate) const R8G8_SNORM: EncoderSet = EncoderSet::new(&[
+    universal!([u8; 2], |[r, g, _, _]| [r, g].map(s8::from_uf32)).add_flags(Flags::EXACT_U8),
+    universal_dither!([u8; 2], step = |_| Vec4::splat(S8_STEP), |pixel| {
+        let (encoded, mut error) = quantize(pixel, s8::from_uf32, s8::uf32);
+        error.z = 0.0;
+        error.w = 0.0;
+        ([encoded[0], encoded[1]], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
+])
+.snorm();
 
 pub(crate) const A8_UNORM: EncoderSet = EncoderSet::new(&[
     Encoder::copy(ColorFormat::ALPHA_U8),
     color_convert!(ColorFormat::ALPHA_U8),
     universal!(u8, |[_, _, _, a]| n8::from_f32(a)),
+    universal_dither!(u8, step = |_| unorm_step([8; 4]), |pixel| {
+        let a = n8::from_f32(pixel.w);
+        (a, Vec4::new(0.0, 0.0, 0.0, pixel.w - n8::f32(a)))
+    })
+    .add_flags(Flags::DITHER_ALPHA),
 ]);
 
 pub(crate) const R16_UNORM: EncoderSet = EncoderSet::new(&[
     Encoder::copy(ColorFormat::GRAYSCALE_U16),
     color_convert!(ColorFormat::GRAYSCALE_U16),
     universal_grayscale!(u16, n16::from_f32),
+    universal_dither!(u16, step = |_| unorm_step([16; 4]), |pixel| {
+        quantize_grayscale(pixel, n16::from_f32, n16::f32)
+    })
+    .add_flags(Flags::DITHER_COLOR),
 ]);
 
 pub(crate) const R16_SNORM: EncoderSet = EncoderSet::new(&[
     color_convert!(ColorFormat::GRAYSCALE_U16, snorm = true),
     universal_grayscale!(u16, s16::from_uf32),
+    universal_dither!(u16, step = |_| Vec4::splat(S16_STEP), |pixel| {
+        quantize_grayscale(pixel, s16::from_uf32, s16::uf32)
+    })
+    .add_flags(Flags::DITHER_COLOR),
+])
+.snorm();
+
+pub(crate) const R16G16_UNORM: EncoderSet = EncoderSet::new(&[
+    universal!([u16; 2], |[r, g, _, _]| [r, g].map(n16::from_f32)).add_flags(Flags::EXACT_U16),
+    universal_dither!([u16; 2], step = |_| unorm_step([16; 4]), |pixel| {
+        let (encoded, mut error) = quantize(pixel, n16::from_f32, n16::f32);
+        error.z = 0.0;
+        error.w = 0.0;
+        ([encoded[0], encoded[1]], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
 ]);
 
-pub(crate) const R16G16_UNORM: EncoderSet =
-    EncoderSet::new(&[
-        universal!([u16; 2], |[r, g, _, _]| [r, g].map(n16::from_f32)).add_flags(Flags::EXACT_U16),
-    ]);
-
-pub(crate) const R16G16_SNORM: EncoderSet =
-    EncoderSet::new(&[
-        universal!([u16; 2], |[r, g, _, _]| [r, g].map(s16::from_uf32)).add_flags(Flags::EXACT_U16),
-    ]);
+pub(crate) const R16G16_SNORM: EncoderSet = EncoderSet::new(&[
+    universal!([u16; 2], |[r, g, _, _]| [r, g].map(s16::from_uf32)).add_flags(Flags::EXACT_U16),
+    universal_dither!([u16; 2], step = |_| Vec4::splat(S16_STEP), |pixel| {
+        let (encoded, mut error) = quantize(pixel, s16::from_uf32, s16::uf32);
+        error.z = 0.0;
+        error.w = 0.0;
+        ([encoded[0], encoded[1]], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
+])
+.snorm();
 
 pub(crate) const R16G16B16A16_UNORM: EncoderSet = EncoderSet::new(&[
     Encoder::copy(ColorFormat::RGBA_U16),
     color_convert!(ColorFormat::RGBA_U16),
     universal!([u16; 4], |rgba| rgba.map(n16::from_f32)),
+    universal_dither!([u16; 4], step = |_| unorm_step([16; 4]), |pixel| {
+        quantize(pixel, n16::from_f32, n16::f32)
+    })
+    .add_flags(Flags::DITHER_ALL),
 ]);
 
 pub(crate) const R16G16B16A16_SNORM: EncoderSet = EncoderSet::new(&[
     color_convert!(ColorFormat::RGBA_U16, snorm = true),
     universal!([u16; 4], |rgba| rgba.map(s16::from_uf32)),
-]);
+    universal_dither!([u16; 4], step = |_| Vec4::splat(S16_STEP), |pixel| {
+        quantize(pixel, s16::from_uf32, s16::uf32)
+    })
+    .add_flags(Flags::DITHER_ALL),
+])
+.snorm();
 
 pub(crate) const R10G10B10A2_UNORM: EncoderSet = EncoderSet::new(&[
     universal!(u32, |[r, g, b, a]| {
@@ -480,7 +595,7 @@ pub(crate) const R10G10B10A2_UNORM: EncoderSet = EncoderSet::new(&[
         let a = n2::from_f32(a) as u32;
         (a << 30) | (b << 20) | (g << 10) | r
     }),
-    universal_dither!(u32, |pixel| {
+    universal_dither!(u32, step = |_| unorm_step([10, 10, 10, 2]), |pixel| {
         let [r, g, b, a] = pixel.to_array();
         let r = n10::from_f32(r) as u32;
         let g = n10::from_f32(g) as u32;
@@ -507,42 +622,81 @@ pub(crate) const R11G11B10_FLOAT: EncoderSet = EncoderSet::new(&[
         let b10 = fp10::from_f32(b) as u32;
         (b10 << 22) | (g11 << 11) | r11
     }),
-    universal_dither!(u32, |pixel| {
-        let r11 = fp11::from_f32(pixel[0]) as u32;
-        let g11 = fp11::from_f32(pixel[1]) as u32;
-        let b10 = fp10::from_f32(pixel[2]) as u32;
+    universal_dither!(
+        u32,
+        step = |pixel| {
+            Vec4::new(
+                small_float_step(pixel.x, 6),
+                small_float_step(pixel.y, 6),
+                small_float_step(pixel.z, 5),
+                0.0,
+            )
+        },
+        |pixel| {
+            let r11 = fp11::from_f32(pixel[0]) as u32;
+            let g11 = fp11::from_f32(pixel[1]) as u32;
+            let b10 = fp10::from_f32(pixel[2]) as u32;
+
+            let back = Vec4::new(
+                fp11::f32(r11 as u16),
+                fp11::f32(g11 as u16),
+                fp10::f32(b10 as u16),
+                1.0,
+            );
+            let error = pixel - back;
+
+            ((b10 << 22) | (g11 << 11) | r11, error)
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
-        let back = Vec4::new(
-            fp11::f32(r11 as u16),
-            fp11::f32(g11 as u16),
-            fp10::f32(b10 as u16),
-            1.0,
-        );
-        let error = pixel - back;
+pub(crate) const R9G9B9E5_SHAREDEXP: EncoderSet = EncoderSet::new(&[
+    universal!(u32, |[r, g, b, _]| { rgb9995f::from_f32([r, g, b]) }).add_flags(Flags::EXACT_U8),
+    universal_dither!(
+        u32,
+        step = |pixel| {
+            // all channels share the exponent of the largest channel
+            let exp = rgb9995f::from_f32([pixel.x, pixel.y, pixel.z]) >> 27;
+            let step = util::two_powi(exp as i8 - 24);
+            Vec4::new(step, step, step, 0.0)
+        },
+        |pixel| {
+            let encoded = rgb9995f::from_f32([pixel.x, pixel.y, pixel.z]);
+            let [r, g, b] = rgb9995f::f32(encoded);
+            let error = pixel - Vec4::new(r, g, b, pixel.w);
+            (encoded, error)
+        }
+    )
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
-        ((b10 << 22) | (g11 << 11) | r11, error)
+pub(crate) const R16_FLOAT: EncoderSet = EncoderSet::new(&[
+    universal_grayscale!(u16, fp16::from_f32).add_flags(Flags::EXACT_U8),
+    universal_dither!(u16, step = fp16_step, |pixel| {
+        quantize_grayscale(pixel, fp16::from_f32, fp16::f32)
     })
     .add_flags(Flags::DITHER_COLOR),
 ]);
 
-pub(crate) const R9G9B9E5_SHAREDEXP: EncoderSet =
-    EncoderSet::new(&[
-        universal!(u32, |[r, g, b, _]| { rgb9995f::from_f32([r, g, b]) })
-            .add_flags(Flags::EXACT_U8),
-    ]);
-
-pub(crate) const R16_FLOAT: EncoderSet =
-    EncoderSet::new(&[universal_grayscale!(u16, fp16::from_f32).add_flags(Flags::EXACT_U8)]);
-
-pub(crate) const R16G16_FLOAT: EncoderSet =
-    EncoderSet::new(&[
-        universal!([u16; 2], |[r, g, _, _]| [r, g].map(fp16::from_f32)).add_flags(Flags::EXACT_U8),
-    ]);
+pub(crate) const R16G16_FLOAT: EncoderSet = EncoderSet::new(&[
+    universal!([u16; 2], |[r, g, _, _]| [r, g].map(fp16::from_f32)).add_flags(Flags::EXACT_U8),
+    universal_dither!([u16; 2], step = fp16_step, |pixel| {
+        let (encoded, mut error) = quantize(pixel, fp16::from_f32, fp16::f32);
+        error.z = 0.0;
+        error.w = 0.0;
+        ([encoded[0], encoded[1]], error)
+    })
+    .add_flags(Flags::DITHER_COLOR),
+]);
 
-pub(crate) const R16G16B16A16_FLOAT: EncoderSet =
-    EncoderSet::new(&[
-        universal!([u16; 4], |rgba| rgba.map(fp16::from_f32)).add_flags(Flags::EXACT_U8)
-    ]);
+pub(crate) const R16G16B16A16_FLOAT: EncoderSet = EncoderSet::new(&[
+    universal!([u16; 4], |rgba| rgba.map(fp16::from_f32)).add_flags(Flags::EXACT_U8),
+    universal_dither!([u16; 4], step = fp16_step, |pixel| {
+        quantize(pixel, fp16::from_f32, fp16::f32)
+    })
+    .add_flags(Flags::DITHER_ALL),
+]);
 
 pub(crate) const R32_FLOAT: EncoderSet = EncoderSet::new(&[
     Encoder::copy(ColorFormat::GRAYSCALE_F32),
@@ -573,57 +727,91 @@ pub(crate) const R10G10B10_XR_BIAS_A2_UNORM: EncoderSet = EncoderSet::new(&[
         let a = n2::from_f32(a) as u32;
         (a << 30) | (b << 20) | (g << 10) | r
     }),
-    universal_dither!(u32, |pixel| {
+    universal_dither!(
+        u32,
+        step = |_| Vec4::new(1.0 / 510.0, 1.0 / 510.0, 1.0 / 510.0, 1.0 / 3.0),
+        |pixel| {
+            let [r, g, b, a] = pixel.to_array();
+            let r = xr10::from_f32(r) as u32;
+            let g = xr10::from_f32(g) as u32;
+            let b = xr10::from_f32(b) as u32;
+            let a = n2::from_f32(a) as u32;
+
+            let back = Vec4::new(
+                xr10::f32(r as u16),
+                xr10::f32(g as u16),
+                xr10::f32(b as u16),
+                n2::f32(a as u8),
+            );
+            let error = pixel - back;
+
+            ((a << 30) | (b << 20) | (g << 10) | r, error)
+        }
+    )
+    .add_flags(Flags::DITHER_ALL),
+]);
+
+pub(crate) const AYUV: EncoderSet = EncoderSet::new(&[
+    universal!([u8; 4], |[r, g, b, a]| {
+        let [y, u, v] = yuv8::from_rgb_f32([r, g, b]);
+        let a = n8::from_f32(a);
+        [v, u, y, a]
+    }),
+    universal_dither!([u8; 4], step = |_| yuv_step(255.0, 1.0 / 255.0), |pixel| {
         let [r, g, b, a] = pixel.to_array();
-        let r = xr10::from_f32(r) as u32;
-        let g = xr10::from_f32(g) as u32;
-        let b = xr10::from_f32(b) as u32;
-        let a = n2::from_f32(a) as u32;
+        let [y, u, v] = yuv8::from_rgb_f32([r, g, b]);
+        let a = n8::from_f32(a);
 
-        let back = Vec4::new(
-            xr10::f32(r as u16),
-            xr10::f32(g as u16),
-            xr10::f32(b as u16),
-            n2::f32(a as u8),
-        );
-        let error = pixel - back;
+        let [r, g, b] = yuv8::f32([y, u, v]);
+        let error = pixel - Vec4::new(r, g, b, n8::f32(a));
 
-        ((a << 30) | (b << 20) | (g << 10) | r, error)
+        ([v, u, y, a], error)
     })
     .add_flags(Flags::DITHER_ALL),
 ]);
 
-pub(crate) const AYUV: EncoderSet = EncoderSet::new(&[universal!([u8; 4], |[r, g, b, a]| {
-    let [y, u, v] = yuv8::from_rgb_f32([r, g, b]);
-    let a = n8::from_f32(a);
-    [v, u, y, a]
-})]);
-
 pub(crate) const Y410: EncoderSet = EncoderSet::new(&[
     universal!(u32, |[r, g, b, a]| {
         let [y, u, v] = yuv10::from_rgb_f32([r, g, b]);
         let a = n2::from_f32(a) as u32;
         (a << 30) | ((v as u32) << 20) | ((y as u32) << 10) | (u as u32)
     }),
-    universal_dither!(u32, |pixel| {
-        let [r, g, b, a_f32] = pixel.to_array();
+    universal_dither!(u32, step = |_| yuv_step(1023.0, 1.0 / 3.0), |pixel| {
+        let [r, g, b, a] = pixel.to_array();
         let [y, u, v] = yuv10::from_rgb_f32([r, g, b]);
-        let a = n2::from_f32(a_f32) as u32;
+        let a = n2::from_f32(a);
 
-        let a_back = n2::f32(a as u8);
-        let error = Vec4::new(0.0, 0.0, 0.0, a_f32 - a_back);
+        let [r, g, b] = yuv10::f32([y, u, v]);
+        let error = pixel - Vec4::new(r, g, b, n2::f32(a));
 
         (
-            (a << 30) | ((v as u32) << 20) | ((y as u32) << 10) | (u as u32),
+            ((a as u32) << 30) | ((v as u32) << 20) | ((y as u32) << 10) | (u as u32),
             error,
         )
     })
-    .add_flags(Flags::DITHER_ALPHA),
+    .add_flags(Flags::DITHER_ALL),
 ]);
 
-pub(crate) const Y416: EncoderSet = EncoderSet::new(&[universal!([u16; 4], |[r, g, b, a]| {
-    let [y, u, v] = yuv16::from_rgb_f32([r, g, b]);
-    let a = n16::from_f32(a);
-    [u, y, v, a]
-})
-.add_flags(Flags::EXACT_U8)]);
+pub(crate) const Y416: EncoderSet = EncoderSet::new(&[
+    universal!([u16; 4], |[r, g, b, a]| {
+        let [y, u, v] = yuv16::from_rgb_f32([r, g, b]);
+        let a = n16::from_f32(a);
+        [u, y, v, a]
+    })
+    .add_flags(Flags::EXACT_U8),
+    universal_dither!(
+        [u16; 4],
+        step = |_| yuv_step(65535.0, 1.0 / 65535.0),
+        |pixel| {
+            let [r, g, b, a] = pixel.to_array();
+            let [y, u, v] = yuv16::from_rgb_f32([r, g, b]);
+            let a = n16::from_f32(a);
+
+            let [r, g, b] = yuv16::f32([y, u, v]);
+            let error = pixel - Vec4::new(r, g, b, n16::f32(a));
+
+            ([u, y, v, a], error)
+        }
+    )
+    .add_flags(Flags::DITHER_ALL),
+]);
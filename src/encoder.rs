@@ -1,12 +1,19 @@
 use std::io::Write;
+use std::time::Instant;
+#[cfg(feature = "rayon")]
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    sync::mpsc,
+};
 
 use crate::{
-    encode,
+    cast, encode,
     header::Header,
     iter::{SurfaceInfo, SurfaceIterator},
     resize::{Aligner, ResizeState},
-    sub_progress, ColorFormat, DataLayout, EncodeOptions, EncodingError, Format, ImageView,
-    Progress, ProgressRange, Report, Size,
+    sub_progress, ChannelMapping, ColorFormat, DataLayout, EncodeOptions, EncodingError, Format,
+    ImageView, PixelInfo, Progress, ProgressEvent, ProgressRange, Report, Size, SurfaceState,
 };
 
 /// An encoder for DDS files.
@@ -22,6 +29,10 @@ pub struct Encoder<W> {
     /// Defaults: `EncodeOptions::default()`
     pub options: EncodeOptions,
     resize: Option<Box<(Aligner, ResizeState)>>,
+    /// Whether a surface was only partially written by a [`SurfaceWriter`].
+    poisoned: bool,
+
+    stats: EncoderStats,
 }
 impl<W> Encoder<W> {
     /// Creates a new encoder and immediately writes the header to the writer.
@@ -46,13 +57,27 @@ impl<W> Encoder<W> {
 
         header.write(&mut writer)?;
 
+        let iter = SurfaceIterator::new(layout);
+        let mut total_pixels = 0;
+        let mut counter = iter.clone();
+        while let Some(surface) = counter.current() {
+            total_pixels += surface.size().pixels();
+            counter.advance();
+        }
+
         Ok(Self {
             writer,
             format,
             layout,
-            iter: SurfaceIterator::new(layout),
+            iter,
             options: EncodeOptions::default(),
             resize: None,
+            poisoned: false,
+            stats: EncoderStats {
+                started: None,
+                processed_pixels: 0,
+                total_pixels,
+            },
         })
     }
 
@@ -113,6 +138,347 @@ impl<W> Encoder<W> {
         self.write_surface_impl(image, progress, options)
     }
 
+    /// Writes multiple surfaces at once.
+    ///
+    /// This is equivalent to calling [`Self::write_surface_with`] for each
+    /// image in order, with the same write options. E.g. to write a texture
+    /// array with mipmaps, pass all array elements and set
+    /// [`WriteOptions::generate_mipmaps`].
+    ///
+    /// If the `rayon` feature is enabled and [`EncodeOptions::parallel`] is
+    /// `true`, all surfaces are encoded in parallel. Mipmaps of different
+    /// images are generated concurrently as well. This is significantly
+    /// faster for many small surfaces (e.g. large arrays of small textures or
+    /// small mipmaps), because parallel encoding within a single surface only
+    /// pays off for large surfaces. Each encoded surface is written as soon as
+    /// all surfaces before it have been written, so only surfaces that
+    /// finished encoding out of order are buffered in memory.
+    ///
+    /// Progress is reported for all surfaces together. Surfaces are weighted
+    /// by their number of pixels. When encoding in parallel, only
+    /// [`SurfaceState::Finished`] events are emitted, in order, as the
+    /// surfaces are written.
+    ///
+    /// If any image has an unexpected size or there are more images than
+    /// surfaces left, an error is returned before anything is encoded.
+    pub fn write_surfaces(
+        &mut self,
+        images: &[ImageView],
+        mut progress: Option<&mut Progress>,
+        options: &WriteOptions,
+    ) -> Result<(), EncodingError>
+    where
+        W: Write,
+    {
+        self.check_poisoned()?;
+
+        // Figure out which surfaces each image will write.
+        let mut iter = self.iter.clone();
+        let mut plans = Vec::with_capacity(images.len());
+        for &image in images {
+            let current = iter.current().ok_or(EncodingError::TooManySurfaces)?;
+            if current.size() != image.size() {
+                return Err(EncodingError::UnexpectedSurfaceSize);
+            }
+            iter.advance();
+
+            let mut mipmaps = Vec::new();
+            let is_volume = matches!(self.layout, DataLayout::Volume(_));
+            if options.generate_mipmaps && !is_volume {
+                while let Some(current) = iter.current().filter(|c| c.is_mipmap()) {
+                    mipmaps.push(current.size());
+                    iter.advance();
+                }
+            }
+            plans.push((image, mipmaps));
+        }
+
+        #[cfg(feature = "rayon")]
+        if self.options.parallel {
+            return self.write_surfaces_parallel(&plans, progress, options);
+        }
+
+        let total_pixels: u64 = plans
+            .iter()
+            .map(|(image, mips)| plan_pixels(*image, mips))
+            .sum();
+        let mut done_pixels = 0;
+        for (image, mipmaps) in &plans {
+            let pixels = plan_pixels(*image, mipmaps);
+            let range = ProgressRange::from_to(
+                done_pixels as f32 / total_pixels.max(1) as f32,
+                (done_pixels + pixels) as f32 / total_pixels.max(1) as f32,
+            );
+            done_pixels += pixels;
+
+            self.write_surface_impl(*image, sub_progress(&mut progress, range).as_mut(), options)?;
+        }
+
+        progress.report(1.0);
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    fn write_surfaces_parallel(
+        &mut self,
+        plans: &[(ImageView, Vec<Size>)],
+        mut progress: Option<&mut Progress>,
+        options: &WriteOptions,
+    ) -> Result<(), EncodingError>
+    where
+        W: Write,
+    {
+        self.stats.started.get_or_insert_with(Instant::now);
+
+        let total_pixels: u64 = plans
+            .iter()
+            .map(|(image, mips)| plan_pixels(*image, mips))
+            .sum();
+        let format = self.format;
+        let encode_options = &self.options;
+
+        let encode_surface =
+            |image: ImageView, options: &EncodeOptions| -> Result<Vec<u8>, EncodingError> {
+                let mut buffer = Vec::new();
+                encode(&mut buffer, image, format, None, options)?;
+                Ok(buffer)
+            };
+
+        // The +1 ensures that 100% is reported only after everything was
+        // written.
+        let (parallel_progress, mut events) =
+            crate::ParallelProgress::new_with_events(&mut progress, total_pixels + 1);
+        let parallel_progress = &parallel_progress;
+
+        let encode_plan = |(image, mipmaps): &(ImageView, Vec<Size>)| {
+            parallel_progress.check_cancelled()?;
+
+            let transformed = if mipmaps.is_empty() {
+                None
+            } else {
+                transform_for_mipmaps(*image, format, encode_options)
+            };
+            let (image, encode_options) = match &transformed {
+                Some((data, options)) => (
+                    &ImageView::new(cast::as_bytes(data), image.size(), ColorFormat::RGBA_F32)
+                        .expect("invalid buffer"),
+                    options,
+                ),
+                None => (image, encode_options),
+            };
+
+            let mut surfaces = Vec::with_capacity(1 + mipmaps.len());
+            surfaces.push(encode_surface(*image, encode_options)?);
+            parallel_progress.submit(image.size().pixels())?;
+
+            if !mipmaps.is_empty() {
+                let mut aligner = Aligner::new();
+                let mut resize = ResizeState::new();
+                let src = aligner.align(*image);
+
+                for &mipmap_size in mipmaps {
+                    parallel_progress.check_cancelled()?;
+
+                    let mip_data = resize.resize(
+                        &src,
+                        mipmap_size,
+                        options.resize_straight_alpha,
+                        options.resize_filter,
+                    );
+                    let mip =
+                        ImageView::new(mip_data, mipmap_size, image.color).expect("invalid mipmap");
+                    surfaces.push(encode_surface(mip, encode_options)?);
+                    parallel_progress.submit(mipmap_size.pixels())?;
+                }
+            }
+
+            Ok(surfaces)
+        };
+        let encode_plan = &encode_plan;
+
+        // Plans are claimed in order by the workers and the calling thread.
+        // The calling thread writes the encoded surfaces in order and encodes
+        // plans itself while waiting, so it never blocks on workers that
+        // haven't started yet.
+        let next_plan = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let claim = || -> Option<usize> {
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            let index = next_plan.fetch_add(1, Ordering::Relaxed);
+            if index < plans.len() {
+                Some(index)
+            } else {
+                None
+            }
+        };
+        let claim = &claim;
+
+        type Encoded = Result<Vec<Vec<u8>>, EncodingError>;
+        let result = rayon::in_place_scope(|scope| -> Result<(), EncodingError> {
+            let (sender, receiver) = mpsc::channel::<(usize, Encoded)>();
+            let workers = rayon::current_num_threads().min(plans.len().saturating_sub(1));
+            for _ in 0..workers {
+                let sender = sender.clone();
+                scope.spawn(move |_| {
+                    while let Some(index) = claim() {
+                        let encoded = encode_plan(&plans[index]);
+                        let failed = encoded.is_err();
+                        if sender.send((index, encoded)).is_err() || failed {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // Encoded surfaces that finished before the surfaces before them.
+            let mut pending: BTreeMap<usize, Encoded> = BTreeMap::new();
+            let mut write_in_order = || -> Result<(), EncodingError> {
+                for index in 0..plans.len() {
+                    let surfaces = loop {
+                        pending.extend(receiver.try_iter());
+                        if let Some(encoded) = pending.remove(&index) {
+                            break encoded?;
+                        }
+                        if let Some(claimed) = claim() {
+                            pending.insert(claimed, encode_plan(&plans[claimed]));
+                        } else if let Ok((done, encoded)) = receiver.recv() {
+                            pending.insert(done, encoded);
+                        } else {
+                            // All workers are gone, so one of them panicked.
+                            // The scope will propagate the panic.
+                            return Ok(());
+                        }
+                    };
+
+                    for surface in surfaces {
+                        let pixels = self.iter.current().map_or(0, |c| c.size().pixels());
+                        self.writer.write_all(&surface)?;
+                        self.stats.processed_pixels += pixels;
+                        if let Some(events) = &mut events {
+                            if let Some(event) =
+                                self.stats
+                                    .event(&self.iter, self.layout, SurfaceState::Finished)
+                            {
+                                events(&event);
+                            }
+                        }
+                        self.iter.advance();
+                    }
+                }
+                Ok(())
+            };
+            let result = write_in_order();
+            if result.is_err() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            result
+        });
+        result?;
+
+        progress.report(1.0);
+        Ok(())
+    }
+
+    /// Begins writing the next surface row by row.
+    ///
+    /// This is useful for surfaces that are too large to be held in memory at
+    /// once. Rows are pushed to the returned [`SurfaceWriter`] in the given
+    /// color format and are encoded and written as soon as a complete strip
+    /// of rows is available. The strip height is determined by
+    /// [`EncodingSupport::split_height`](crate::EncodingSupport::split_height)
+    /// (e.g. 4 rows for BC1), so at most one strip of rows is buffered. With
+    /// position-based dithering (see
+    /// [`DitheringAlgorithm`](crate::DitheringAlgorithm)), strips are as tall
+    /// as the dither pattern, so the pattern lines up across strips.
+    ///
+    /// Bi-planar formats (e.g. `NV12`) are not supported, since their second
+    /// plane is stored after the entire first plane and would have to be kept
+    /// in memory. [`EncodingError::UnsupportedFormat`] is returned for them.
+    ///
+    /// Mipmaps are not generated in this mode. They have to be written
+    /// separately like any other surface.
+    ///
+    /// Since strips are encoded independently, formats that use error
+    /// diffusion dithering (see
+    /// [`EncodingSupport::local_dithering`](crate::EncodingSupport::local_dithering))
+    /// may produce slightly different results than [`Self::write_surface`].
+    ///
+    /// The surface is only complete after [`SurfaceWriter::finish`] has been
+    /// called. If the writer is dropped before that or writing rows fails,
+    /// the encoder is poisoned and all further calls will return
+    /// [`EncodingError::Poisoned`].
+    pub fn begin_surface(
+        &mut self,
+        color: ColorFormat,
+    ) -> Result<SurfaceWriter<'_, '_, W>, EncodingError>
+    where
+        W: Write,
+    {
+        self.begin_surface_with(color, None)
+    }
+
+    /// Begins writing the next surface row by row.
+    ///
+    /// Same as [`Self::begin_surface`], but progress is reported to the given
+    /// reporter. Progress is reported after each encoded strip of rows, and
+    /// [`SurfaceState::Started`] and [`SurfaceState::Finished`] events are
+    /// emitted when the writer is created and finished respectively. If the
+    /// reporter cancels the operation, [`SurfaceWriter::write_rows`] returns
+    /// [`EncodingError::Cancelled`] and the encoder is poisoned.
+    pub fn begin_surface_with<'a, 'p>(
+        &'a mut self,
+        color: ColorFormat,
+        mut progress: Option<&'a mut Progress<'p>>,
+    ) -> Result<SurfaceWriter<'a, 'p, W>, EncodingError>
+    where
+        W: Write,
+    {
+        self.check_poisoned()?;
+        let current = self.iter.current().ok_or(EncodingError::TooManySurfaces)?;
+        let size = current.size();
+
+        let support = self
+            .format
+            .encoding_support()
+            .ok_or(EncodingError::UnsupportedFormat(self.format))?;
+        if let PixelInfo::BiPlanar(_) = PixelInfo::from(self.format) {
+            return Err(EncodingError::UnsupportedFormat(self.format));
+        }
+        let strip_height =
+            crate::split::aligned_split_height(&support, &self.options).unwrap_or(size.height);
+
+        self.stats.started.get_or_insert_with(Instant::now);
+        self.stats.emit(
+            &mut progress,
+            &self.iter,
+            self.layout,
+            SurfaceState::Started,
+        );
+        // Only a finished surface writer clears this flag.
+        self.poisoned = true;
+
+        Ok(SurfaceWriter {
+            encoder: self,
+            progress,
+            color,
+            size,
+            strip_height,
+            buffer: Vec::new(),
+            rows: 0,
+        })
+    }
+
+    fn check_poisoned(&self) -> Result<(), EncodingError> {
+        if self.poisoned {
+            Err(EncodingError::Poisoned)
+        } else {
+            Ok(())
+        }
+    }
+
     fn write_surface_impl(
         &mut self,
         image: ImageView,
@@ -122,6 +488,8 @@ impl<W> Encoder<W> {
     where
         W: Write,
     {
+        self.check_poisoned()?;
+
         // Get information about the current surface.
         let current = self.iter.current().ok_or(EncodingError::TooManySurfaces)?;
         if current.size() != image.size() {
@@ -154,14 +522,43 @@ impl<W> Encoder<W> {
             }
         };
 
+        let transformed = if generated_mipmaps > 0 {
+            transform_for_mipmaps(image, self.format, &self.options)
+        } else {
+            None
+        };
+        let (image, encode_options) = match &transformed {
+            Some((data, options)) => (
+                ImageView::new(cast::as_bytes(data), image.size(), ColorFormat::RGBA_F32)
+                    .expect("invalid buffer"),
+                options,
+            ),
+            None => (image, &self.options),
+        };
+
+        self.stats.started.get_or_insert_with(Instant::now);
+
         // write the main surface
+        self.stats.emit(
+            &mut progress,
+            &self.iter,
+            self.layout,
+            SurfaceState::Started,
+        );
         encode(
             &mut self.writer,
             image,
             self.format,
             sub_progress(&mut progress, get_level_progress_range(0)).as_mut(),
-            &self.options,
+            encode_options,
         )?;
+        self.stats.processed_pixels += image.size().pixels();
+        self.stats.emit(
+            &mut progress,
+            &self.iter,
+            self.layout,
+            SurfaceState::Finished,
+        );
         self.iter.advance();
 
         if generated_mipmaps > 0 {
@@ -187,13 +584,26 @@ impl<W> Encoder<W> {
                 let mip =
                     ImageView::new(mip_data, mipmap_size, image.color).expect("invalid mipmap");
 
+                self.stats.emit(
+                    &mut progress,
+                    &self.iter,
+                    self.layout,
+                    SurfaceState::Started,
+                );
                 encode(
                     &mut self.writer,
                     mip,
                     self.format,
                     sub_progress(&mut progress, get_level_progress_range(count)).as_mut(),
-                    &self.options,
+                    encode_options,
                 )?;
+                self.stats.processed_pixels += mipmap_size.pixels();
+                self.stats.emit(
+                    &mut progress,
+                    &self.iter,
+                    self.layout,
+                    SurfaceState::Finished,
+                );
                 self.iter.advance();
             }
         }
@@ -245,11 +655,13 @@ impl<W> Encoder<W> {
     /// If you need the writer after this call, use [`Self::into_writer`].
     ///
     /// This will return [`EncodingError::MissingSurfaces`] if some surfaces are
-    /// yet to be written. See [`Self::is_done`].
+    /// yet to be written. See [`Self::is_done`]. If the encoder is poisoned
+    /// (see [`Self::begin_surface`]), [`EncodingError::Poisoned`] is returned.
     pub fn finish(mut self) -> Result<(), EncodingError>
     where
         W: Write,
     {
+        self.check_poisoned()?;
         if !self.is_done() {
             return Err(EncodingError::MissingSurfaces);
         }
@@ -267,19 +679,240 @@ impl<W> Encoder<W> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Applies the channel mapping of the options to an image that mipmaps will
+/// be generated from.
+///
+/// The mapping has to be applied before mipmaps are generated, so that e.g.
+/// straight alpha is resized with the mapped alpha channel. If the mapping
+/// isn't the identity, the image as seen by the encoder (see
+/// [`crate::encode::encoder_input_rgba_f32`]) is returned along with options
+/// that don't transform the input again.
+fn transform_for_mipmaps(
+    image: ImageView,
+    format: Format,
+    options: &EncodeOptions,
+) -> Option<(Vec<[f32; 4]>, EncodeOptions)> {
+    if options.channel_mapping.is_identity() {
+        return None;
+    }
+
+    let data = crate::encode::encoder_input_rgba_f32(image, format, options);
+    let options = EncodeOptions {
+        channel_mapping: ChannelMapping::IDENTITY,
+        signed_snorm: false,
+        ..options.clone()
+    };
+    Some((data, options))
+}
+
+/// The number of pixels of an image and all mipmaps generated from it.
+fn plan_pixels(image: ImageView, mipmaps: &[Size]) -> u64 {
+    image.size().pixels() + mipmaps.iter().map(|size| size.pixels()).sum::<u64>()
+}
+
+/// Keeps track of the pixels encoded by an [`Encoder`] for progress events.
+struct EncoderStats {
+    /// When the first surface started encoding.
+    started: Option<Instant>,
+    processed_pixels: u64,
+    total_pixels: u64,
+}
+impl EncoderStats {
+    /// Emits a progress event for the current surface of the iterator.
+    fn emit(
+        &self,
+        progress: &mut Option<&mut Progress>,
+        iter: &SurfaceIterator,
+        layout: DataLayout,
+        state: SurfaceState,
+    ) {
+        let progress = match progress {
+            Some(progress) if progress.has_events() => progress,
+            _ => return,
+        };
+        if let Some(event) = self.event(iter, layout, state) {
+            progress.event(&event);
+        }
+    }
+    /// Creates a progress event for the current surface of the iterator.
+    fn event(
+        &self,
+        iter: &SurfaceIterator,
+        layout: DataLayout,
+        state: SurfaceState,
+    ) -> Option<ProgressEvent> {
+        let (surface, size) = match (iter.index(layout), iter.current()) {
+            (Some(index), Some(current)) => (index, current.size()),
+            _ => return None,
+        };
+
+        let elapsed = self.started.map(|s| s.elapsed()).unwrap_or_default();
+        let remaining = if self.processed_pixels > 0 {
+            let remaining_pixels = self.total_pixels.saturating_sub(self.processed_pixels);
+            Some(elapsed.mul_f64(remaining_pixels as f64 / self.processed_pixels as f64))
+        } else {
+            None
+        };
+
+        Some(ProgressEvent {
+            surface,
+            size,
+            state,
+            processed_pixels: self.processed_pixels,
+            total_pixels: self.total_pixels,
+            elapsed,
+            remaining,
+        })
+    }
+}
+
+/// A writer for the rows of a single surface.
+///
+/// See [`Encoder::begin_surface`].
+pub struct SurfaceWriter<'a, 'p, W> {
+    encoder: &'a mut Encoder<W>,
+    progress: Option<&'a mut Progress<'p>>,
+    color: ColorFormat,
+    size: Size,
+    strip_height: u32,
+    /// The rows of the current incomplete strip.
+    buffer: Vec<u8>,
+    /// The number of rows written so far (including buffered rows).
+    rows: u32,
+}
+impl<W: Write> SurfaceWriter<'_, '_, W> {
+    /// The size of the surface.
+    pub fn size(&self) -> Size {
+        self.size
+    }
+    /// The color format of the rows.
+    pub fn color(&self) -> ColorFormat {
+        self.color
+    }
+    /// The number of rows that have been written so far.
+    pub fn rows_written(&self) -> u32 {
+        self.rows
+    }
+
+    fn row_pitch(&self) -> usize {
+        self.size.width as usize * self.color.bytes_per_pixel() as usize
+    }
+
+    /// Writes the next rows of the surface.
+    ///
+    /// `data` must contain one or more complete rows in the color format of
+    /// the writer. Rows can be written in any number of calls, and the number
+    /// of rows per call does not have to be a multiple of the strip height.
+    ///
+    /// Returns [`EncodingError::UnexpectedSurfaceSize`] if `data` does not
+    /// contain complete rows or if more rows are written than the surface has.
+    /// In this case, nothing is written and the writer can still be used.
+    /// Any other error poisons the encoder.
+    pub fn write_rows(&mut self, data: &[u8]) -> Result<(), EncodingError> {
+        let row_pitch = self.row_pitch();
+        if row_pitch == 0 || data.len() % row_pitch != 0 {
+            return Err(EncodingError::UnexpectedSurfaceSize);
+        }
+        let rows = data.len() / row_pitch;
+        if rows as u64 > (self.size.height - self.rows) as u64 {
+            return Err(EncodingError::UnexpectedSurfaceSize);
+        }
+
+        let mut data = data;
+
+        // complete the buffered strip first
+        if !self.buffer.is_empty() {
+            let buffered = (self.buffer.len() / row_pitch) as u32;
+            let take = (self.strip_height - buffered).min(rows as u32);
+            let (head, tail) = data.split_at(take as usize * row_pitch);
+            self.buffer.extend_from_slice(head);
+            self.rows += take;
+            data = tail;
+
+            if buffered + take == self.strip_height || self.rows == self.size.height {
+                let buffer = std::mem::take(&mut self.buffer);
+                let result = self.encode_rows(&buffer);
+                self.buffer = buffer;
+                self.buffer.clear();
+                result?;
+            }
+        }
+
+        // encode all complete strips without copying them
+        let rows = (data.len() / row_pitch) as u32;
+        let complete = if self.rows + rows == self.size.height {
+            rows
+        } else {
+            rows / self.strip_height * self.strip_height
+        };
+        let (head, tail) = data.split_at(complete as usize * row_pitch);
+        if complete > 0 {
+            self.rows += complete;
+            self.encode_rows(head)?;
+        }
+
+        // buffer the rest
+        self.buffer.extend_from_slice(tail);
+        self.rows += (tail.len() / row_pitch) as u32;
+
+        Ok(())
+    }
+
+    /// Encodes the given rows and reports the progress of all rows written
+    /// so far.
+    fn encode_rows(&mut self, data: &[u8]) -> Result<(), EncodingError> {
+        let rows = (data.len() / self.row_pitch()) as u32;
+        let image = ImageView::new(data, Size::new(self.size.width, rows), self.color)
+            .expect("rows have the correct size");
+        let encoder = &mut *self.encoder;
+
+        encode(
+            &mut encoder.writer,
+            image,
+            encoder.format,
+            None,
+            &encoder.options,
+        )?;
+
+        self.progress
+            .checked_report(self.rows as f32 / self.size.height as f32)
+    }
+
+    /// Finishes the surface and advances the encoder to the next surface.
+    ///
+    /// Returns [`EncodingError::MissingRows`] if not all rows of the surface
+    /// have been written.
+    pub fn finish(mut self) -> Result<(), EncodingError> {
+        if self.rows != self.size.height {
+            return Err(EncodingError::MissingRows);
+        }
+        debug_assert!(self.buffer.is_empty());
+
+        let encoder = &mut *self.encoder;
+        encoder.stats.processed_pixels += self.size.pixels();
+        encoder.stats.emit(
+            &mut self.progress,
+            &encoder.iter,
+            encoder.layout,
+            SurfaceState::Finished,
+        );
+        encoder.iter.advance();
+        encoder.poisoned = false;
+
+        self.progress.report(1.0);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
 pub enum ResizeFilter {
     Nearest,
+    #[default]
     Box,
     Triangle,
     Mitchell,
     Lanczos3,
 }
-impl Default for ResizeFilter {
-    fn default() -> Self {
-        Self::Box
-    }
-}
 
 #[derive(Debug, Clone, Copy)]
 pub struct WriteOptions {
@@ -2,7 +2,7 @@ use std::num::NonZeroU32;
 
 use crate::{
     header::{DxgiFormat, FourCC, Header},
-    Format,
+    Format, Size,
 };
 
 #[derive(Debug)]
@@ -95,12 +95,21 @@ pub enum DecodingError {
     RowPitchTooSmall {
         required_minimum: usize,
     },
-    /// When decoding a rectangle, the buffer is too small.
+    /// When decoding a rectangle or box, the buffer is too small.
     ///
     /// A buffer much have at least `row_pitch * rect.height` bytes.
     RectBufferTooSmall {
         required_minimum: usize,
     },
+    /// When decoding a box, the box is out of bounds of the size of the
+    /// volume.
+    BoxOutOfBounds,
+    /// When decoding a box, the slice pitch is too small.
+    ///
+    /// A slice pitch must be at least `row_pitch * box.height` bytes.
+    SlicePitchTooSmall {
+        required_minimum: usize,
+    },
 
     /// Returned by [`crate::Decoder::read_surface`] when the user tries to
     /// decode a surface into an image that is not the same size as the
@@ -116,6 +125,11 @@ pub enum DecodingError {
     /// This error is returned by [`crate::Decoder::read_cube_map`] when the
     /// user tries to read a DDS file that isn't a cube map.
     NotACubeMap,
+    /// This error is returned by [`crate::Decoder::read_volume_box`] when the
+    /// user tries to read a DDS file that isn't a volume.
+    NotAVolume,
+    /// The requested mipmap level does not exist.
+    MipmapLevelOutOfBounds(u8),
 
     /// The decoder has exceeded its memory limit.
     MemoryLimitExceeded,
@@ -146,6 +160,16 @@ impl std::fmt::Display for DecodingError {
                     required_minimum
                 )
             }
+            DecodingError::BoxOutOfBounds => {
+                write!(f, "Box is out of bounds of the volume size")
+            }
+            DecodingError::SlicePitchTooSmall { required_minimum } => {
+                write!(
+                    f,
+                    "Slice pitch too small: Must be at least `row_pitch * box.height` == {} bytes",
+                    required_minimum
+                )
+            }
             DecodingError::UnexpectedSurfaceSize => {
                 write!(f, "Unexpected surface size")
             }
@@ -158,6 +182,12 @@ impl std::fmt::Display for DecodingError {
             DecodingError::NotACubeMap => {
                 write!(f, "The DDS file is not a cube map")
             }
+            DecodingError::NotAVolume => {
+                write!(f, "The DDS file is not a volume")
+            }
+            DecodingError::MipmapLevelOutOfBounds(level) => {
+                write!(f, "Mipmap level {} does not exist", level)
+            }
 
             DecodingError::MemoryLimitExceeded => {
                 write!(f, "Memory limit exceeded")
@@ -313,6 +343,20 @@ pub enum EncodingError {
     /// Returned by [`crate::Encoder::finish()`] when the encoder has not
     /// written all surfaces declared in the header.
     MissingSurfaces,
+    /// Returned by [`crate::SurfaceWriter::finish()`] when not all rows of
+    /// the surface have been written.
+    MissingRows,
+    /// Returned when the operation was cancelled by the progress reporter.
+    /// See [`crate::Progress`].
+    Cancelled,
+    /// Returned by [`crate::encode_with_target()`] when no candidate formats
+    /// are given.
+    NoCandidateFormats,
+    /// Returned by [`crate::Encoder`] when a surface was only partially
+    /// written by a [`crate::SurfaceWriter`]. This happens if the writer was
+    /// dropped before it was finished or if writing rows failed. The encoder
+    /// cannot write any more surfaces.
+    Poisoned,
 
     Layout(LayoutError),
     Io(std::io::Error),
@@ -335,6 +379,12 @@ impl std::fmt::Display for EncodingError {
                 write!(f, "Too many surfaces are attempted to written")
             }
             EncodingError::MissingSurfaces => write!(f, "Not enough surfaces have been written"),
+            EncodingError::MissingRows => write!(f, "Not enough rows have been written"),
+            EncodingError::Cancelled => write!(f, "The operation was cancelled"),
+            EncodingError::NoCandidateFormats => write!(f, "No candidate formats were given"),
+            EncodingError::Poisoned => {
+                write!(f, "The encoder is poisoned by a partially written surface")
+            }
 
             EncodingError::Layout(err) => write!(f, "Layout error: {}", err),
             EncodingError::Io(err) => write!(f, "IO error: {}", err),
@@ -361,3 +411,232 @@ impl From<std::io::Error> for EncodingError {
         EncodingError::Io(err)
     }
 }
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DdsFileError {
+    /// The surface, array element, or cube map face does not exist (or
+    /// already exists when inserting).
+    IndexOutOfBounds,
+    /// The given data does not have the length required by the data layout.
+    UnexpectedDataLength {
+        expected: u64,
+        actual: u64,
+    },
+    /// The operation is not supported for the data layout of the file.
+    ///
+    /// E.g. volumes cannot have array elements and only DX9 cube maps can
+    /// have individual faces inserted or removed.
+    UnsupportedLayout,
+    /// Returned by [`crate::DdsFile::merge_array`] when no inputs are given.
+    EmptyInput,
+    /// The inputs of a merge operation do not have the same format, size,
+    /// number of mipmaps, or kind.
+    MismatchedInputs,
+
+    Layout(LayoutError),
+    Decoding(DecodingError),
+}
+
+impl std::fmt::Display for DdsFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DdsFileError::IndexOutOfBounds => write!(f, "Index out of bounds"),
+            DdsFileError::UnexpectedDataLength { expected, actual } => {
+                write!(
+                    f,
+                    "Unexpected data length: expected {} bytes, but got {} bytes",
+                    expected, actual
+                )
+            }
+            DdsFileError::UnsupportedLayout => {
+                write!(f, "Operation is not supported for this data layout")
+            }
+            DdsFileError::EmptyInput => write!(f, "No inputs were given"),
+            DdsFileError::MismatchedInputs => {
+                write!(
+                    f,
+                    "Inputs must have the same format, size, number of mipmaps, and kind"
+                )
+            }
+
+            DdsFileError::Layout(err) => write!(f, "Layout error: {}", err),
+            DdsFileError::Decoding(err) => write!(f, "Decoding error: {}", err),
+        }
+    }
+}
+impl std::error::Error for DdsFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DdsFileError::Layout(err) => Some(err),
+            DdsFileError::Decoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<LayoutError> for DdsFileError {
+    fn from(err: LayoutError) -> Self {
+        DdsFileError::Layout(err)
+    }
+}
+impl From<DecodingError> for DdsFileError {
+    fn from(err: DecodingError) -> Self {
+        DdsFileError::Decoding(err)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TransformError {
+    /// The format cannot be transformed losslessly.
+    UnsupportedFormat(Format),
+    /// The transform would split blocks of a block-compressed format.
+    ///
+    /// Mirroring an axis requires the size along that axis to be a multiple
+    /// of the block size or at most the block size.
+    NotBlockAligned,
+    /// The given data does not have the length required by the size and
+    /// format of the surface.
+    UnexpectedDataLength {
+        expected: u64,
+        actual: u64,
+    },
+
+    Layout(LayoutError),
+}
+
+impl std::fmt::Display for TransformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransformError::UnsupportedFormat(format) => {
+                write!(f, "Format {:?} cannot be transformed losslessly", format)
+            }
+            TransformError::NotBlockAligned => {
+                write!(f, "The transform would split blocks of the surface")
+            }
+            TransformError::UnexpectedDataLength { expected, actual } => {
+                write!(
+                    f,
+                    "Unexpected data length: expected {} bytes, but got {} bytes",
+                    expected, actual
+                )
+            }
+
+            TransformError::Layout(err) => write!(f, "Layout error: {}", err),
+        }
+    }
+}
+impl std::error::Error for TransformError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TransformError::Layout(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<LayoutError> for TransformError {
+    fn from(err: LayoutError) -> Self {
+        TransformError::Layout(err)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TranscodeError {
+    Decoding(DecodingError),
+    Encoding(EncodingError),
+}
+
+impl std::fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranscodeError::Decoding(err) => write!(f, "Decoding error: {}", err),
+            TranscodeError::Encoding(err) => write!(f, "Encoding error: {}", err),
+        }
+    }
+}
+impl std::error::Error for TranscodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TranscodeError::Decoding(err) => Some(err),
+            TranscodeError::Encoding(err) => Some(err),
+        }
+    }
+}
+
+impl From<DecodingError> for TranscodeError {
+    fn from(err: DecodingError) -> Self {
+        TranscodeError::Decoding(err)
+    }
+}
+impl From<EncodingError> for TranscodeError {
+    fn from(err: EncodingError) -> Self {
+        TranscodeError::Encoding(err)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConvertError {
+    /// The target format cannot be represented with the requested kind of
+    /// header.
+    UnsupportedHeader(Format),
+    Decoding(DecodingError),
+    Encoding(EncodingError),
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConvertError::UnsupportedHeader(format) => {
+                write!(f, "Format {:?} cannot be represented by the header", format)
+            }
+            ConvertError::Decoding(err) => write!(f, "Decoding error: {}", err),
+            ConvertError::Encoding(err) => write!(f, "Encoding error: {}", err),
+        }
+    }
+}
+impl std::error::Error for ConvertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConvertError::UnsupportedHeader(_) => None,
+            ConvertError::Decoding(err) => Some(err),
+            ConvertError::Encoding(err) => Some(err),
+        }
+    }
+}
+
+impl From<DecodingError> for ConvertError {
+    fn from(err: DecodingError) -> Self {
+        ConvertError::Decoding(err)
+    }
+}
+impl From<EncodingError> for ConvertError {
+    fn from(err: EncodingError) -> Self {
+        ConvertError::Encoding(err)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MetricsError {
+    /// The two images do not have the same size.
+    SizeMismatch { reference: Size, image: Size },
+}
+
+impl std::fmt::Display for MetricsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetricsError::SizeMismatch { reference, image } => {
+                write!(
+                    f,
+                    "Image size mismatch: reference is {}x{}, but image is {}x{}",
+                    reference.width, reference.height, image.width, image.height
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for MetricsError {}
@@ -0,0 +1,785 @@
+use std::io::{Read, Seek, Write};
+
+use crate::header::{Caps2, Header, MiscFlags, ParseOptions};
+use crate::{
+    CubeMapFaces, DataLayout, DataRegion, DdsFileError, Decoder, DecodingError, Format,
+    LayoutError, Size, Texture, TextureArrayKind, Transform, TransformError, Volume,
+};
+
+/// The index of a single surface within a [`DdsFile`].
+///
+/// Textures and texture arrays are indexed by array index and mipmap level,
+/// cube maps additionally by face, and volumes by mipmap level and depth
+/// slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SurfaceIndex {
+    /// The index of the texture or cube map in the array.
+    ///
+    /// Always 0 for single textures and volumes.
+    pub array_index: usize,
+    /// The cube map face. Must be a single face for cube maps and `None`
+    /// otherwise.
+    pub face: Option<CubeMapFaces>,
+    /// The mipmap level.
+    pub mipmap: u8,
+    /// The depth slice. Always 0 for anything but volumes.
+    pub depth: u32,
+}
+impl SurfaceIndex {
+    /// The index of a mipmap of a texture (or texture array element).
+    pub const fn texture(array_index: usize, mipmap: u8) -> Self {
+        Self {
+            array_index,
+            face: None,
+            mipmap,
+            depth: 0,
+        }
+    }
+    /// The index of a mipmap of a cube map face.
+    pub const fn cube_face(array_index: usize, face: CubeMapFaces, mipmap: u8) -> Self {
+        Self {
+            array_index,
+            face: Some(face),
+            mipmap,
+            depth: 0,
+        }
+    }
+    /// The index of a depth slice of a volume mipmap.
+    pub const fn depth_slice(mipmap: u8, depth: u32) -> Self {
+        Self {
+            array_index: 0,
+            face: None,
+            mipmap,
+            depth,
+        }
+    }
+}
+
+/// An owned, in-memory DDS file.
+///
+/// A [`DdsFile`] consists of a header and the raw (encoded) data of all
+/// surfaces. Surface data is never decoded or re-encoded, so loading and
+/// saving a DDS file preserves its data bit-for-bit.
+///
+/// The header is kept in sync with the data. Inserting or removing array
+/// elements, cube map faces, or mipmaps will update the array size, cube map
+/// faces, and mipmap count of the header accordingly.
+///
+/// ```no_run
+/// # use dds::{*, header::*};
+/// # use std::fs::File;
+/// let reader = File::open("array.dds").unwrap();
+/// let mut file = DdsFile::read(reader, &ParseOptions::default(), usize::MAX).unwrap();
+/// // drop all mipmaps and the last array element
+/// file.set_mipmap_count(1).unwrap();
+/// file.remove_element(file.element_count() - 1).unwrap();
+/// file.write(&mut File::create("smaller-array.dds").unwrap()).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DdsFile {
+    header: Header,
+    format: Format,
+    layout: DataLayout,
+    /// The data of each level 0 object. This is the data of a single texture
+    /// (incl. mipmaps) for textures, texture arrays, and cube maps (one per
+    /// face), and the data of the whole volume (incl. mipmaps) for volumes.
+    objects: Vec<Vec<u8>>,
+}
+impl DdsFile {
+    /// Creates a new DDS file with the given header where all surfaces are
+    /// filled with zeros.
+    ///
+    /// The format is detected from the header.
+    pub fn new(header: Header) -> Result<Self, DecodingError> {
+        let format = Format::from_header(&header)?;
+        Self::new_with(header, format)
+    }
+    /// Creates a new DDS file with the given header and format where all
+    /// surfaces are filled with zeros.
+    pub fn new_with(header: Header, format: Format) -> Result<Self, DecodingError> {
+        Self::from_header_with(std::io::repeat(0), header, format, usize::MAX)
+    }
+
+    /// Reads the header and all surfaces of a DDS file from the given reader.
+    ///
+    /// See [`Self::from_header_with`] for details on `memory_limit`.
+    pub fn read<R: Read>(
+        mut reader: R,
+        options: &ParseOptions,
+        memory_limit: usize,
+    ) -> Result<Self, DecodingError> {
+        let header = Header::read(&mut reader, options)?;
+        Self::from_header(reader, header, memory_limit)
+    }
+    /// Reads all surfaces described by the given header from the given
+    /// reader.
+    ///
+    /// The format is detected from the header. See [`Self::from_header_with`]
+    /// for details on `memory_limit`.
+    pub fn from_header<R: Read>(
+        reader: R,
+        header: Header,
+        memory_limit: usize,
+    ) -> Result<Self, DecodingError> {
+        let format = Format::from_header(&header)?;
+        Self::from_header_with(reader, header, format, memory_limit)
+    }
+    /// Reads all surfaces described by the given header and format from the
+    /// given reader.
+    ///
+    /// The reader is expected to be positioned at the start of the data
+    /// section.
+    ///
+    /// `memory_limit` is the maximum number of bytes of surface data. If the
+    /// header describes more data than this, [`DecodingError::MemoryLimitExceeded`]
+    /// is returned before anything is read. Since the header may come from an
+    /// untrusted source, memory is allocated as the data is read, so a
+    /// truncated data section results in an IO error.
+    pub fn from_header_with<R: Read>(
+        mut reader: R,
+        header: Header,
+        format: Format,
+        memory_limit: usize,
+    ) -> Result<Self, DecodingError> {
+        let layout = DataLayout::from_header_with(&header, format.into())?;
+        if layout.data_len() > memory_limit as u64 {
+            return Err(DecodingError::MemoryLimitExceeded);
+        }
+        let objects = read_objects(&mut reader, layout)?;
+
+        Ok(Self {
+            header,
+            format,
+            layout,
+            objects,
+        })
+    }
+    /// Reads the header and all surfaces of the given decoder.
+    ///
+    /// The decoder is rewound to the start of the data section afterwards.
+    pub fn from_decoder<R: Read + Seek>(decoder: &mut Decoder<R>) -> Result<Self, DecodingError> {
+        let layout = decoder.layout();
+        let objects = decoder.read_raw_data(|reader| read_objects(reader, layout))?;
+
+        Ok(Self {
+            header: decoder.header().clone(),
+            format: decoder.format(),
+            layout,
+            objects,
+        })
+    }
+    fn from_objects(
+        header: Header,
+        format: Format,
+        objects: Vec<Vec<u8>>,
+    ) -> Result<Self, DdsFileError> {
+        let layout = DataLayout::from_header_with(&header, format.into())?;
+        let file = Self {
+            header,
+            format,
+            layout,
+            objects,
+        };
+        debug_assert_eq!(file.objects.len(), file.layout_object_count());
+        Ok(file)
+    }
+
+    /// Combines textures or cube maps into a texture array or cube map array.
+    ///
+    /// All decoders must have the same format, size, and number of mipmaps.
+    /// Each decoder may contain a single texture, a texture array, a cube map,
+    /// or a cube map array, but all decoders must be of the same kind. The
+    /// elements of all decoders are concatenated in order.
+    ///
+    /// The raw data of all surfaces is copied as is, so this is lossless.
+    /// Since DX9 does not support arrays, the returned file always has a DX10
+    /// header. Volumes and partial cube maps are not supported.
+    ///
+    /// All decoders are rewound to the start of their data section afterwards.
+    pub fn merge_array<R: Read + Seek>(decoders: &mut [Decoder<R>]) -> Result<Self, DdsFileError> {
+        let first = decoders.first().ok_or(DdsFileError::EmptyInput)?;
+        let format = first.format();
+        let template = merge_template(first.layout())?;
+        let mut header = first
+            .header()
+            .to_dx10()
+            .ok_or(DdsFileError::UnsupportedLayout)?;
+        for decoder in decoders.iter() {
+            if decoder.format() != format || merge_template(decoder.layout())? != template {
+                return Err(DdsFileError::MismatchedInputs);
+            }
+        }
+
+        let mut objects = Vec::new();
+        for decoder in decoders.iter_mut() {
+            let layout = decoder.layout();
+            objects.extend(decoder.read_raw_data(|reader| read_objects(reader, layout))?);
+        }
+
+        let faces = if header.is_cube_map() { 6 } else { 1 };
+        header.array_size = u32::try_from(objects.len() / faces)
+            .map_err(|_| LayoutError::ArraySizeTooBig(u32::MAX))?;
+
+        Self::from_objects(Header::Dx10(header), format, objects)
+    }
+    /// Combines 6 textures into a cube map.
+    ///
+    /// The faces must be given in the order they are stored in DDS files:
+    /// +X, -X, +Y, -Y, +Z, -Z. All decoders must contain a single texture and
+    /// have the same format, size, and number of mipmaps.
+    ///
+    /// The raw data of all surfaces is copied as is, so this is lossless. The
+    /// header of the returned file is based on the header of the first face.
+    ///
+    /// All decoders are rewound to the start of their data section afterwards.
+    pub fn merge_cube_map<R: Read + Seek>(
+        faces: &mut [Decoder<R>; 6],
+    ) -> Result<Self, DdsFileError> {
+        let format = faces[0].format();
+        let template = match faces[0].layout() {
+            DataLayout::Texture(texture) => texture,
+            _ => return Err(DdsFileError::UnsupportedLayout),
+        };
+        for decoder in faces.iter() {
+            if decoder.format() != format || decoder.layout() != DataLayout::Texture(template) {
+                return Err(DdsFileError::MismatchedInputs);
+            }
+        }
+
+        let mut objects = Vec::with_capacity(6);
+        for decoder in faces.iter_mut() {
+            let layout = decoder.layout();
+            objects.extend(decoder.read_raw_data(|reader| read_objects(reader, layout))?);
+        }
+
+        let header = match faces[0].header() {
+            Header::Dx9(dx9) => Header::Dx9(dx9.clone().with_cube_map_faces(CubeMapFaces::ALL)),
+            Header::Dx10(dx10) => Header::Dx10(
+                dx10.clone()
+                    .with_misc_flags(dx10.misc_flag | MiscFlags::TEXTURE_CUBE)
+                    .with_array_size(1),
+            ),
+        };
+
+        Self::from_objects(header, format, objects)
+    }
+
+    /// Returns a standalone DDS file containing only the array element at the
+    /// given index.
+    ///
+    /// For cube map arrays, the returned file will be a single cube map. For
+    /// files without arrays, the only valid index is 0 and a copy of the file
+    /// is returned.
+    pub fn extract_element(&self, index: usize) -> Result<Self, DdsFileError> {
+        let faces = self.faces_per_element();
+        let objects = self
+            .objects
+            .get(index * faces..(index + 1) * faces)
+            .ok_or(DdsFileError::IndexOutOfBounds)?
+            .to_vec();
+
+        let mut header = self.header.clone();
+        if let Header::Dx10(dx10) = &mut header {
+            dx10.array_size = 1;
+        }
+
+        Self::from_objects(header, self.format, objects)
+    }
+    /// Returns a standalone DDS file containing only the given face of the
+    /// cube map at the given array index.
+    ///
+    /// The returned file will be a single 2D texture.
+    pub fn extract_cube_map_face(
+        &self,
+        array_index: usize,
+        face: CubeMapFaces,
+    ) -> Result<Self, DdsFileError> {
+        if self.cube_map_faces().is_none() {
+            return Err(DdsFileError::UnsupportedLayout);
+        }
+        let (object, _, _) = self
+            .locate(SurfaceIndex::cube_face(array_index, face, 0))
+            .ok_or(DdsFileError::IndexOutOfBounds)?;
+
+        let header = match &self.header {
+            Header::Dx9(dx9) => {
+                let mut dx9 = dx9.clone();
+                dx9.caps2 -= Caps2::CUBE_MAP | Caps2::CUBE_MAP_ALL_FACES;
+                Header::Dx9(dx9)
+            }
+            Header::Dx10(dx10) => Header::Dx10(
+                dx10.clone()
+                    .with_misc_flags(dx10.misc_flag - MiscFlags::TEXTURE_CUBE)
+                    .with_array_size(1),
+            ),
+        };
+
+        Self::from_objects(header, self.format, vec![self.objects[object].clone()])
+    }
+
+    /// The header of the DDS file.
+    ///
+    /// The header always describes the current data of the file.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    pub fn format(&self) -> Format {
+        self.format
+    }
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+
+    /// Returns the raw data of the surface at the given index.
+    ///
+    /// If the index is invalid, `None` is returned.
+    pub fn surface(&self, index: SurfaceIndex) -> Option<&[u8]> {
+        let (object, start, len) = self.locate(index)?;
+        Some(&self.objects[object][start..start + len])
+    }
+    /// Returns the raw data of the surface at the given index.
+    ///
+    /// If the index is invalid, `None` is returned.
+    pub fn surface_mut(&mut self, index: SurfaceIndex) -> Option<&mut [u8]> {
+        let (object, start, len) = self.locate(index)?;
+        Some(&mut self.objects[object][start..start + len])
+    }
+    /// Replaces the raw data of the surface at the given index.
+    ///
+    /// The new data must have exactly the same length as the old data.
+    pub fn replace_surface(
+        &mut self,
+        index: SurfaceIndex,
+        data: &[u8],
+    ) -> Result<(), DdsFileError> {
+        let surface = self
+            .surface_mut(index)
+            .ok_or(DdsFileError::IndexOutOfBounds)?;
+        if surface.len() != data.len() {
+            return Err(DdsFileError::UnexpectedDataLength {
+                expected: surface.len() as u64,
+                actual: data.len() as u64,
+            });
+        }
+        surface.copy_from_slice(data);
+        Ok(())
+    }
+
+    /// The number of array elements.
+    ///
+    /// For cube maps, this is the number of cube maps, not faces. Single
+    /// textures and volumes always have 1 element.
+    pub fn element_count(&self) -> usize {
+        self.objects.len() / self.faces_per_element()
+    }
+    /// The number of bytes of a single array element.
+    ///
+    /// This is the length of the data expected by [`Self::insert_element`].
+    pub fn element_len(&self) -> u64 {
+        self.object_len() * self.faces_per_element() as u64
+    }
+    /// Returns the raw data of the array element at the given index.
+    ///
+    /// The data of an element is exactly what is stored in the data section
+    /// of a DDS file: all mipmaps of a texture, or all mipmaps of all faces of
+    /// a cube map.
+    pub fn element(&self, index: usize) -> Option<Vec<u8>> {
+        let faces = self.faces_per_element();
+        let objects = self.objects.get(index * faces..(index + 1) * faces)?;
+        Some(objects.concat())
+    }
+    /// Inserts a new array element at the given index.
+    ///
+    /// `data` must be the raw data of one element (see [`Self::element`]).
+    /// This is only supported for DX10 textures, texture arrays, and cube
+    /// maps.
+    pub fn insert_element(&mut self, index: usize, data: Vec<u8>) -> Result<(), DdsFileError> {
+        self.check_dx10_array()?;
+        if index > self.element_count() {
+            return Err(DdsFileError::IndexOutOfBounds);
+        }
+        if data.len() as u64 != self.element_len() {
+            return Err(DdsFileError::UnexpectedDataLength {
+                expected: self.element_len(),
+                actual: data.len() as u64,
+            });
+        }
+
+        self.set_array_size(self.element_count() + 1)?;
+
+        let object_len = self.object_len() as usize;
+        let at = index * self.faces_per_element();
+        self.objects.splice(
+            at..at,
+            data.chunks(object_len.max(1)).map(|chunk| chunk.to_vec()),
+        );
+        Ok(())
+    }
+    /// Appends a new array element.
+    ///
+    /// See [`Self::insert_element`].
+    pub fn push_element(&mut self, data: Vec<u8>) -> Result<(), DdsFileError> {
+        self.insert_element(self.element_count(), data)
+    }
+    /// Removes the array element at the given index and returns its raw data.
+    ///
+    /// This is only supported for DX10 textures, texture arrays, and cube
+    /// maps.
+    pub fn remove_element(&mut self, index: usize) -> Result<Vec<u8>, DdsFileError> {
+        self.check_dx10_array()?;
+        if index >= self.element_count() {
+            return Err(DdsFileError::IndexOutOfBounds);
+        }
+
+        let faces = self.faces_per_element();
+        self.set_array_size(self.element_count() - 1)?;
+
+        let removed: Vec<Vec<u8>> = self
+            .objects
+            .drain(index * faces..(index + 1) * faces)
+            .collect();
+        Ok(removed.concat())
+    }
+
+    /// Inserts a face into a partial DX9 cube map.
+    ///
+    /// `data` must be the raw data of one face including all mipmaps.
+    ///
+    /// DX10 only supports cube maps with all 6 faces, so this method will
+    /// return an error for DX10 headers.
+    pub fn insert_cube_map_face(
+        &mut self,
+        face: CubeMapFaces,
+        data: Vec<u8>,
+    ) -> Result<(), DdsFileError> {
+        let faces = self.dx9_cube_map_faces()?;
+        if face.count() != 1 || faces.contains(face) {
+            return Err(DdsFileError::IndexOutOfBounds);
+        }
+        if data.len() as u64 != self.object_len() {
+            return Err(DdsFileError::UnexpectedDataLength {
+                expected: self.object_len(),
+                actual: data.len() as u64,
+            });
+        }
+
+        self.set_dx9_cube_map_faces(faces | face)?;
+        self.objects.insert(face_position(faces | face, face), data);
+        Ok(())
+    }
+    /// Removes a face from a DX9 cube map and returns its raw data.
+    ///
+    /// DX10 only supports cube maps with all 6 faces, so this method will
+    /// return an error for DX10 headers.
+    pub fn remove_cube_map_face(&mut self, face: CubeMapFaces) -> Result<Vec<u8>, DdsFileError> {
+        let faces = self.dx9_cube_map_faces()?;
+        if face.count() != 1 || !faces.contains(face) {
+            return Err(DdsFileError::IndexOutOfBounds);
+        }
+
+        self.set_dx9_cube_map_faces(faces - face)?;
+        Ok(self.objects.remove(face_position(faces, face)))
+    }
+
+    /// Sets the number of mipmaps of all textures/volumes in this file.
+    ///
+    /// Existing mipmaps beyond the new count are removed. New mipmaps are
+    /// filled with zeros and can be set with [`Self::replace_surface`].
+    pub fn set_mipmap_count(&mut self, count: u8) -> Result<(), DdsFileError> {
+        if count == 0 {
+            return Err(DdsFileError::IndexOutOfBounds);
+        }
+
+        let header = self.header.clone().with_mipmap_count(count as u32);
+        let layout = DataLayout::from_header_with(&header, self.format.into())?;
+
+        let old_regions = mip_regions(self.layout);
+        let new_regions = mip_regions(layout);
+        let new_len = new_regions.last().map_or(0, |(start, len)| start + len);
+
+        for object in &mut self.objects {
+            let mut new_object = vec![0_u8; new_len];
+            for ((old_start, len), (new_start, _)) in old_regions.iter().zip(&new_regions) {
+                new_object[*new_start..new_start + len]
+                    .copy_from_slice(&object[*old_start..old_start + len]);
+            }
+            *object = new_object;
+        }
+
+        self.header = header;
+        self.layout = layout;
+        Ok(())
+    }
+
+    /// Losslessly flips or rotates all surfaces of the file.
+    ///
+    /// For 90° and 270° rotations, the width and height of the header are
+    /// swapped. See [`crate::transform()`] for which formats and sizes are
+    /// supported. If any surface cannot be transformed, an error is returned
+    /// and the file is left unchanged.
+    ///
+    /// Cube maps are transformed as a whole, so their faces still join up
+    /// afterward. [`Transform::FlipX`] and [`Transform::FlipY`] mirror the
+    /// cube along the X and Y axis respectively, and rotations rotate the cube
+    /// around the Z axis. This swaps and re-orients faces such that the +Z
+    /// face is transformed like a regular texture. Partial cube maps may end
+    /// up with a different set of faces.
+    pub fn transform(&mut self, transform: Transform) -> Result<(), TransformError> {
+        // the byte offset, byte length, and size of every surface in an object
+        let surfaces: Vec<(usize, usize, Size)> = match self.layout {
+            DataLayout::Volume(volume) => volume
+                .iter_mips()
+                .flat_map(|mip| mip.iter_depth_slices())
+                .map(|s| (s.data_offset() as usize, s.data_len() as usize, s.size()))
+                .collect(),
+            DataLayout::Texture(_) | DataLayout::TextureArray(_) => self
+                .template_texture()
+                .into_iter()
+                .flat_map(|texture| texture.iter_mips())
+                .map(|s| (s.data_offset() as usize, s.data_len() as usize, s.size()))
+                .collect(),
+        };
+        let transform_object = |object: &[u8], transform: Transform| {
+            let mut new_object = vec![0_u8; object.len()];
+            for &(offset, len, size) in &surfaces {
+                let transformed =
+                    crate::transform(&object[offset..offset + len], size, self.format, transform)?;
+                new_object[offset..offset + len].copy_from_slice(&transformed);
+            }
+            Ok::<_, TransformError>(new_object)
+        };
+
+        let mut objects = Vec::with_capacity(self.objects.len());
+        let mut header = self.header.clone();
+        if let Some(faces) = self.cube_map_faces() {
+            // the faces of the transformed cube map and where they come from
+            let new_faces: Vec<(CubeMapFaces, CubeMapFaces, Transform)> = CubeMapFaces::ALL
+                .iter()
+                .map(|face| {
+                    let (source, transform) = transform.cube_map_face_source(face);
+                    (face, source, transform)
+                })
+                .filter(|(_, source, _)| faces.contains(*source))
+                .collect();
+
+            for cube_map in self.objects.chunks(faces.count() as usize) {
+                for &(_, source, transform) in &new_faces {
+                    let object = &cube_map[face_position(faces, source)];
+                    objects.push(transform_object(object, transform)?);
+                }
+            }
+
+            let new_faces = new_faces
+                .iter()
+                .fold(CubeMapFaces::empty(), |acc, (face, _, _)| acc | *face);
+            if new_faces != faces {
+                // only DX9 headers can have partial cube maps
+                if let Header::Dx9(dx9) = &header {
+                    header = Header::Dx9(dx9.clone().with_cube_map_faces(new_faces));
+                }
+            }
+        } else {
+            for object in &self.objects {
+                objects.push(transform_object(object, transform)?);
+            }
+        }
+
+        let size = transform.transformed_size(header.size());
+        let depth = header.depth();
+        let header = header.with_dimensions(size.width, size.height, depth);
+        self.layout = DataLayout::from_header_with(&header, self.format.into())?;
+        self.header = header;
+        self.objects = objects;
+        Ok(())
+    }
+
+    /// Writes the header and all surfaces of the DDS file to the given writer.
+    pub fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.header.write(writer)?;
+        for object in &self.objects {
+            writer.write_all(object)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the object index, byte offset, and byte length of a surface.
+    fn locate(&self, index: SurfaceIndex) -> Option<(usize, usize, usize)> {
+        let (object, surface) = match self.layout {
+            DataLayout::Volume(volume) => {
+                if index.array_index != 0 || index.face.is_some() {
+                    return None;
+                }
+                let slice = volume.get(index.mipmap)?.get_depth_slice(index.depth)?;
+                (0, slice)
+            }
+            DataLayout::Texture(_) | DataLayout::TextureArray(_) => {
+                if index.depth != 0 {
+                    return None;
+                }
+                let object = match (self.cube_map_faces(), index.face) {
+                    (None, None) => index.array_index,
+                    (Some(faces), Some(face)) if face.count() == 1 && faces.contains(face) => index
+                        .array_index
+                        .checked_mul(faces.count() as usize)?
+                        .checked_add(face_position(faces, face))?,
+                    _ => return None,
+                };
+                if object >= self.objects.len() {
+                    return None;
+                }
+                (object, self.template_texture()?.get(index.mipmap)?)
+            }
+        };
+
+        Some((
+            object,
+            surface.data_offset() as usize,
+            surface.data_len() as usize,
+        ))
+    }
+
+    /// A texture at offset 0 that describes the layout of each object.
+    fn template_texture(&self) -> Option<Texture> {
+        match self.layout {
+            DataLayout::Texture(texture) => Some(texture),
+            DataLayout::Volume(_) => None,
+            DataLayout::TextureArray(array) => Some(array.first()),
+        }
+    }
+    fn layout_object_count(&self) -> usize {
+        match self.layout {
+            DataLayout::Texture(_) | DataLayout::Volume(_) => 1,
+            DataLayout::TextureArray(array) => array.len(),
+        }
+    }
+    fn object_len(&self) -> u64 {
+        match self.layout {
+            DataLayout::Texture(texture) => texture.data_len(),
+            DataLayout::Volume(volume) => volume.data_len(),
+            DataLayout::TextureArray(array) => array.first().data_len(),
+        }
+    }
+    fn cube_map_faces(&self) -> Option<CubeMapFaces> {
+        match self.layout.texture_array()?.kind() {
+            TextureArrayKind::Textures => None,
+            TextureArrayKind::CubeMaps => Some(CubeMapFaces::ALL),
+            TextureArrayKind::PartialCubeMap(faces) => Some(faces),
+        }
+    }
+    fn faces_per_element(&self) -> usize {
+        self.cube_map_faces()
+            .map_or(1, |faces| faces.count() as usize)
+    }
+
+    fn check_dx10_array(&self) -> Result<(), DdsFileError> {
+        match &self.header {
+            Header::Dx10(dx10) if !dx10.is_volume() => Ok(()),
+            _ => Err(DdsFileError::UnsupportedLayout),
+        }
+    }
+    /// Updates the header and layout for the given number of array elements.
+    ///
+    /// The objects have to be updated by the caller.
+    fn set_array_size(&mut self, array_size: usize) -> Result<(), DdsFileError> {
+        let mut header = self.header.clone();
+        if let Header::Dx10(dx10) = &mut header {
+            dx10.array_size = array_size
+                .try_into()
+                .map_err(|_| LayoutError::ArraySizeTooBig(u32::MAX))?;
+        }
+        self.set_header(header)
+    }
+    /// Replaces the header and layout iff the new header has a valid layout.
+    fn set_header(&mut self, header: Header) -> Result<(), DdsFileError> {
+        self.layout = DataLayout::from_header_with(&header, self.format.into())?;
+        self.header = header;
+        Ok(())
+    }
+
+    fn dx9_cube_map_faces(&self) -> Result<CubeMapFaces, DdsFileError> {
+        match &self.header {
+            Header::Dx9(dx9) => dx9.cube_map_faces().ok_or(DdsFileError::UnsupportedLayout),
+            Header::Dx10(_) => Err(DdsFileError::UnsupportedLayout),
+        }
+    }
+    fn set_dx9_cube_map_faces(&mut self, faces: CubeMapFaces) -> Result<(), DdsFileError> {
+        match &self.header {
+            Header::Dx9(dx9) => {
+                self.set_header(Header::Dx9(dx9.clone().with_cube_map_faces(faces)))
+            }
+            Header::Dx10(_) => Err(DdsFileError::UnsupportedLayout),
+        }
+    }
+}
+
+/// Reads the data of all level 0 objects of the given layout.
+fn read_objects<R: Read + ?Sized>(
+    reader: &mut R,
+    layout: DataLayout,
+) -> std::io::Result<Vec<Vec<u8>>> {
+    let (count, object_len) = match layout {
+        DataLayout::Texture(texture) => (1, texture.data_len()),
+        DataLayout::Volume(volume) => (1, volume.data_len()),
+        DataLayout::TextureArray(array) => (array.len(), array.first().data_len()),
+    };
+    let object_len = usize::try_from(object_len)
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::OutOfMemory))?;
+
+    // The header may claim much more data than the reader has, so memory is
+    // only allocated up front in bounded amounts.
+    let mut objects = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        let mut object = Vec::with_capacity(object_len.min(1 << 20));
+        reader.take(object_len as u64).read_to_end(&mut object)?;
+        if object.len() != object_len {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        objects.push(object);
+    }
+    Ok(objects)
+}
+
+/// Returns a single element of the given layout (texture or cube map face)
+/// and whether it's a cube map.
+///
+/// Two layouts can be merged iff their templates are equal.
+fn merge_template(layout: DataLayout) -> Result<(Texture, bool), DdsFileError> {
+    match layout {
+        DataLayout::Texture(texture) => Ok((texture, false)),
+        DataLayout::TextureArray(array) => match array.kind() {
+            TextureArrayKind::Textures => Ok((array.first(), false)),
+            TextureArrayKind::CubeMaps => Ok((array.first(), true)),
+            TextureArrayKind::PartialCubeMap(_) => Err(DdsFileError::UnsupportedLayout),
+        },
+        DataLayout::Volume(_) => Err(DdsFileError::UnsupportedLayout),
+    }
+}
+
+/// The position of the given face among all present faces.
+fn face_position(faces: CubeMapFaces, face: CubeMapFaces) -> usize {
+    // faces are stored in the order of their bits
+    (faces.bits() & (face.bits() - 1)).count_ones() as usize
+}
+
+/// The byte offset and length of each mipmap within a single object of the
+/// given layout.
+fn mip_regions(layout: DataLayout) -> Vec<(usize, usize)> {
+    fn volume_regions(volume: Volume) -> Vec<(usize, usize)> {
+        volume
+            .iter_mips()
+            .map(|mip| (mip.data_offset() as usize, mip.data_len() as usize))
+            .collect()
+    }
+    fn texture_regions(texture: Texture) -> Vec<(usize, usize)> {
+        texture
+            .iter_mips()
+            .map(|mip| (mip.data_offset() as usize, mip.data_len() as usize))
+            .collect()
+    }
+
+    match layout {
+        DataLayout::Texture(texture) => texture_regions(texture),
+        DataLayout::Volume(volume) => volume_regions(volume),
+        DataLayout::TextureArray(array) => texture_regions(array.first()),
+    }
+}
@@ -1233,6 +1233,11 @@ impl Dx9Header {
         self
     }
     /// A builder-pattern-style method to set the pixel format of the header.
+    ///
+    /// The pitch or linear size is not stored in the header. It is computed
+    /// from the pixel format and dimensions when the header is written (see
+    /// [`Header::to_raw`]), so it is always consistent with the new pixel
+    /// format.
     pub fn with_pixel_format(mut self, pixel_format: Dx9PixelFormat) -> Self {
         self.pixel_format = pixel_format;
         self
@@ -1248,10 +1253,8 @@ impl Dx9Header {
                     DxgiFormat::BC2_UNORM
                 } else if *four_cc == FourCC::DXT4 {
                     DxgiFormat::BC3_UNORM
-                } else if let Some(dxgi) = four_cc_to_dxgi(*four_cc) {
-                    dxgi
                 } else {
-                    return None;
+                    four_cc_to_dxgi(*four_cc)?
                 }
             }
             Dx9PixelFormat::Mask(mask_pixel_format) => masked_to_dxgi(mask_pixel_format)?,
@@ -1,4 +1,7 @@
-use crate::{DataLayout, DataRegion, Size, SurfaceDescriptor, Texture, Volume};
+use crate::{
+    CubeMapFaces, DataLayout, DataRegion, Size, SurfaceDescriptor, SurfaceIndex, Texture,
+    TextureArrayKind, Volume,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct SurfaceInfo<'a> {
@@ -57,7 +60,7 @@ impl SurfaceIterator {
         }
     }
 
-    pub fn current(&self) -> Option<SurfaceInfo> {
+    pub fn current(&self) -> Option<SurfaceInfo<'_>> {
         match self {
             Self::Texture(iter) => iter.current(),
             Self::Volume(iter) => iter.current(),
@@ -85,6 +88,54 @@ impl SurfaceIterator {
         }
     }
 
+    /// Moves the iterator to the first depth slice of the given mipmap level
+    /// of a volume.
+    ///
+    /// Levels past the last mipmap move the iterator to the end. This does
+    /// nothing for non-volume iterators.
+    pub fn set_volume_level(&mut self, level: u8) {
+        if let Self::Volume(iter) = self {
+            iter.current_level = level.min(iter.volume.mipmaps());
+            iter.current_depth = 0;
+        }
+    }
+
+    /// Returns the index of the current surface within the given layout.
+    ///
+    /// The layout must be the layout the iterator was created with.
+    pub fn index(&self, layout: DataLayout) -> Option<SurfaceIndex> {
+        match self {
+            Self::Texture(iter) => {
+                if iter.current_index >= iter.len {
+                    return None;
+                }
+
+                let index = iter.current_index as usize;
+                let mipmap = iter.current_level;
+                let faces = match layout.texture_array().map(|array| array.kind()) {
+                    Some(TextureArrayKind::CubeMaps) => CubeMapFaces::ALL,
+                    Some(TextureArrayKind::PartialCubeMap(faces)) => faces,
+                    _ => return Some(SurfaceIndex::texture(index, mipmap)),
+                };
+
+                // faces are stored in the order of their bits
+                let count = faces.bits().count_ones() as usize;
+                let face = (0..6)
+                    .map(|bit| CubeMapFaces::from_bits_truncate(1 << bit))
+                    .filter(|face| faces.contains(*face))
+                    .nth(index % count)?;
+                Some(SurfaceIndex::cube_face(index / count, face, mipmap))
+            }
+            Self::Volume(iter) => {
+                iter.current()?;
+                Some(SurfaceIndex::depth_slice(
+                    iter.current_level,
+                    iter.current_depth,
+                ))
+            }
+        }
+    }
+
     /// How many bytes have been read so far to reach the current surface.
     pub fn elapsed_bytes(&self) -> u64 {
         match self {
@@ -111,7 +162,7 @@ impl TextureSurfaceIterator {
         }
     }
 
-    fn current(&self) -> Option<SurfaceInfo> {
+    fn current(&self) -> Option<SurfaceInfo<'_>> {
         if self.current_index < self.len {
             let desc = self.first.get(self.current_level);
             debug_assert!(desc.is_some());
@@ -186,7 +237,7 @@ impl VolumeSurfaceIterator {
         }
     }
 
-    fn current(&self) -> Option<SurfaceInfo> {
+    fn current(&self) -> Option<SurfaceInfo<'_>> {
         let v = self.volume.get(self.current_level)?;
         debug_assert!(self.current_depth < v.depth());
         let desc = v.get_depth_slice(self.current_depth);
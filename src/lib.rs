@@ -50,10 +50,25 @@
 //! with [`Decoder::read_cube_map`].
 //!
 //! Volumes have to be read one depth slice at a time using [`Decoder::read_surface`].
+//! Alternatively, [`Decoder::read_volume_box`] can be used to read a 3D box
+//! of a volume in one call.
 //!
 //! It is also possible to decode a rectangle of a surface using
 //! [`Decoder::read_surface_rect`].
 //!
+//! ### Editing
+//!
+//! [`DdsFile`] holds the header and raw (encoded) data of an entire DDS file
+//! in memory. Surfaces, array elements, and mipmaps can be replaced, added, or
+//! removed without re-encoding. Surfaces of uncompressed and BC1-BC5 formats
+//! can also be flipped and rotated losslessly with [`DdsFile::transform`].
+//!
+//! [`transcode()`] converts a surface to a different format. Conversions
+//! between compatible block formats (e.g. BC1 to BC3) are done directly at the
+//! block level without decoding and re-encoding the whole surface.
+//! [`convert()`] converts an entire DDS file to a different format while
+//! preserving its layout (mipmaps, cube map faces, array elements, etc.).
+//!
 //! ### Encoding
 //!
 //! Since the data of a DDS file is determined by the header, the first step to
@@ -120,6 +135,11 @@
 //!
 //! Volumes have to be encoded one depth slice at a time using [`Encoder::write_surface`].
 //!
+//! Surfaces that are too large to be held in memory can be written row by row
+//! using [`Encoder::begin_surface`]. Conversely, many small surfaces (e.g. the
+//! elements of a texture array) can be encoded in parallel with
+//! [`Encoder::write_surfaces`].
+//!
 //! ### Progress reporting
 //!
 //! The decoder is generally so fast that progress reporting is not needed.
@@ -127,7 +147,22 @@
 //! The encoder, however, can take a long time to encode large images. Use the
 //! `progress` parameter of the [`Encoder::write_surface_with`] method to get
 //! periodic updates on the encoding progress. See the [`Progress`] type for
-//! more details.
+//! more details. [`Progress::with_events`] additionally provides structured
+//! events about which surface is being encoded.
+//!
+//! ### Measuring quality
+//!
+//! The [`compare()`] function measures the error between two images (e.g. an
+//! original image and the result of encoding and decoding it). It returns the
+//! MSE, PSNR, maximum error, and SSIM of each channel, optionally measured in
+//! the perceptually uniform Oklab color space. [`encode_with_stats()`] encodes
+//! a surface and returns such metrics along with the blocks that have the
+//! largest error. [`encode_with_target()`] picks the cheapest format and
+//! quality level that meets a given quality target.
+//!
+//! If you don't know which format to use for an image, [`suggest_format()`]
+//! analyzes the image (alpha usage, grayscale, normal maps, dynamic range) and
+//! recommends a format.
 //!
 //! ### Low-level API
 //!
@@ -143,35 +178,52 @@
 
 mod cast;
 mod color;
+mod convert;
 mod decode;
 mod decoder;
 mod detect;
 mod encode;
 mod encoder;
 mod error;
+mod file;
 mod format;
 pub mod header;
 mod iter;
 mod layout;
+mod metrics;
 mod pixel;
 mod progress;
 mod resize;
 mod split;
+mod stats;
+mod suggest;
+mod target;
+mod transcode;
+mod transform;
 mod util;
 
 pub use color::*;
-pub use decode::{decode, decode_rect, DecodeOptions};
+pub use convert::*;
+pub use decode::{decode, decode_rect, DecodeOptions, ToneMapOperator, ToneMapping};
 pub use decoder::*;
 pub use encode::{
-    encode, CompressionQuality, Dithering, EncodeOptions, EncodingSupport, ErrorMetric,
+    encode, BcOptions, ChannelWeights, CompressionQuality, Dithering, DitheringAlgorithm,
+    EncodeOptions, EncodingSupport, ErrorMetric, RdoOptions,
 };
 pub use encoder::*;
 pub use error::*;
+pub use file::*;
 pub use format::*;
 pub use layout::*;
+pub use metrics::*;
 pub use pixel::*;
 pub use progress::*;
 pub use split::*;
+pub use stats::*;
+pub use suggest::*;
+pub use target::*;
+pub use transcode::*;
+pub use transform::*;
 
 /// A borrowed slice of image data.
 #[derive(Clone, Copy)]
@@ -335,3 +387,47 @@ impl Rect {
         end_x <= size.width as u64 && end_y <= size.height as u64
     }
 }
+
+/// A 3D box within a volume.
+///
+/// `x`, `y`, and `width`, `height` describe a rectangle within each depth
+/// slice, while `z` and `depth` select the range of depth slices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Box3 {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
+impl Box3 {
+    pub const fn new(x: u32, y: u32, z: u32, width: u32, height: u32, depth: u32) -> Self {
+        Self {
+            x,
+            y,
+            z,
+            width,
+            height,
+            depth,
+        }
+    }
+
+    /// The rectangle of this box within a single depth slice.
+    pub const fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+
+    /// Whether the volume of this box is zero.
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0 || self.depth == 0
+    }
+
+    /// Returns `true` if this box is completely within the bounds of a volume
+    /// with the given slice size and depth.
+    pub(crate) fn is_within_bounds(&self, size: Size, depth: u32) -> bool {
+        // use u64 to prevent overflow
+        let end_z = self.z as u64 + self.depth as u64;
+        self.rect().is_within_bounds(size) && end_z <= depth as u64
+    }
+}
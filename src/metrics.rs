@@ -0,0 +1,244 @@
+use glam::Vec3A;
+
+use crate::{convert_to_rgba_f32, srgb_to_oklab, ImageView, MetricsError};
+
+/// The color space in which [`compare`] measures errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MetricSpace {
+    /// Errors are measured on the RGBA values as stored in the image.
+    ///
+    /// Channels: R, G, B, A.
+    #[default]
+    Rgb,
+    /// The color channels are converted from sRGB to Oklab before measuring
+    /// errors. Since Oklab is perceptually uniform, this gives errors that
+    /// better correspond to how visible the differences are.
+    ///
+    /// Channels: L, a, b, A.
+    ///
+    /// Note that the a and b channels of Oklab are roughly in the range
+    /// -0.4 to 0.4, so their errors are naturally smaller than those of L.
+    Oklab,
+}
+
+/// Error metrics for a single channel.
+///
+/// All values are relative to a nominal range of 0 to 1, so an error of
+/// `1/255` corresponds to 1 step of an 8-bit channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ChannelMetrics {
+    /// The mean squared error.
+    pub mse: f64,
+    /// The maximum absolute error of any pixel.
+    pub max_error: f32,
+    /// The structural similarity index (SSIM).
+    ///
+    /// This is 1 for identical images and decreases the more different the
+    /// images are structurally.
+    pub ssim: f64,
+}
+impl ChannelMetrics {
+    /// The root mean squared error.
+    pub fn rmse(&self) -> f64 {
+        self.mse.sqrt()
+    }
+    /// The peak signal-to-noise ratio in dB.
+    ///
+    /// This is infinite for identical images.
+    pub fn psnr(&self) -> f64 {
+        psnr(self.mse)
+    }
+}
+
+/// The result of [`compare`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// The color space the metrics were measured in.
+    pub space: MetricSpace,
+    /// The metrics for each channel. See [`MetricSpace`] for the channel
+    /// order.
+    pub channels: [ChannelMetrics; 4],
+}
+impl Metrics {
+    /// Metrics for the color channels (excluding alpha) combined.
+    ///
+    /// The MSE and SSIM are averaged, the maximum error is the maximum of all
+    /// color channels.
+    pub fn color(&self) -> ChannelMetrics {
+        combine(&self.channels[..3])
+    }
+    /// Metrics for all channels (including alpha) combined.
+    ///
+    /// The MSE and SSIM are averaged, the maximum error is the maximum of all
+    /// channels.
+    pub fn all(&self) -> ChannelMetrics {
+        combine(&self.channels)
+    }
+}
+
+fn combine(channels: &[ChannelMetrics]) -> ChannelMetrics {
+    let n = channels.len() as f64;
+    ChannelMetrics {
+        mse: channels.iter().map(|c| c.mse).sum::<f64>() / n,
+        max_error: channels.iter().map(|c| c.max_error).fold(0.0, f32::max),
+        ssim: channels.iter().map(|c| c.ssim).sum::<f64>() / n,
+    }
+}
+fn psnr(mse: f64) -> f64 {
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        -10.0 * mse.log10()
+    }
+}
+
+/// Compares two images and returns error metrics for each channel.
+///
+/// The images must have the same size, but may have different color formats.
+/// Both images are converted to RGBA before comparison, so e.g. a grayscale
+/// image can be compared to an RGB image. Missing alpha channels are treated
+/// as fully opaque.
+///
+/// SSIM is computed with an 11x11 Gaussian window (sigma 1.5) as described
+/// in the original paper.
+pub fn compare(
+    reference: ImageView,
+    image: ImageView,
+    space: MetricSpace,
+) -> Result<Metrics, MetricsError> {
+    if reference.size() != image.size() {
+        return Err(MetricsError::SizeMismatch {
+            reference: reference.size(),
+            image: image.size(),
+        });
+    }
+
+    let width = reference.width() as usize;
+    let height = reference.height() as usize;
+
+    let a = to_channels(reference, space);
+    let b = to_channels(image, space);
+
+    let mut channels = [ChannelMetrics {
+        mse: 0.0,
+        max_error: 0.0,
+        ssim: 1.0,
+    }; 4];
+    if width * height == 0 {
+        return Ok(Metrics { space, channels });
+    }
+
+    for (c, metrics) in channels.iter_mut().enumerate() {
+        let (a, b) = (&a[c], &b[c]);
+
+        let mut sum = 0.0;
+        let mut max_error: f32 = 0.0;
+        for (a, b) in a.iter().zip(b) {
+            let diff = (a - b).abs();
+            sum += diff as f64 * diff as f64;
+            max_error = max_error.max(diff);
+        }
+
+        metrics.mse = sum / a.len() as f64;
+        metrics.max_error = max_error;
+        metrics.ssim = ssim(a, b, width, height);
+    }
+
+    Ok(Metrics { space, channels })
+}
+
+/// Converts the image to 4 separate channels in the given color space.
+fn to_channels(image: ImageView, space: MetricSpace) -> [Vec<f32>; 4] {
+    let pixels = image.size().pixels() as usize;
+    let mut rgba = vec![[0.0; 4]; pixels];
+    convert_to_rgba_f32(image.color(), image.data(), &mut rgba);
+
+    if space == MetricSpace::Oklab {
+        for pixel in &mut rgba {
+            let lab = srgb_to_oklab(Vec3A::new(pixel[0], pixel[1], pixel[2]));
+            pixel[0] = lab.x;
+            pixel[1] = lab.y;
+            pixel[2] = lab.z;
+        }
+    }
+
+    let mut channels: [Vec<f32>; 4] = Default::default();
+    for (c, channel) in channels.iter_mut().enumerate() {
+        *channel = rgba.iter().map(|p| p[c]).collect();
+    }
+    channels
+}
+
+fn ssim(a: &[f32], b: &[f32], width: usize, height: usize) -> f64 {
+    const C1: f32 = 0.01 * 0.01;
+    const C2: f32 = 0.03 * 0.03;
+
+    let kernel = gaussian_kernel();
+    let blur = |data: Vec<f32>| blur(data, width, height, &kernel);
+
+    let mu_a = blur(a.to_vec());
+    let mu_b = blur(b.to_vec());
+    let aa = blur(a.iter().map(|x| x * x).collect());
+    let bb = blur(b.iter().map(|x| x * x).collect());
+    let ab = blur(a.iter().zip(b).map(|(x, y)| x * y).collect());
+
+    let mut sum = 0.0;
+    for i in 0..a.len() {
+        let (mu_a, mu_b) = (mu_a[i], mu_b[i]);
+        let var_a = aa[i] - mu_a * mu_a;
+        let var_b = bb[i] - mu_b * mu_b;
+        let cov = ab[i] - mu_a * mu_b;
+
+        let numerator = (2.0 * mu_a * mu_b + C1) * (2.0 * cov + C2);
+        let denominator = (mu_a * mu_a + mu_b * mu_b + C1) * (var_a + var_b + C2);
+        sum += (numerator / denominator) as f64;
+    }
+    sum / a.len() as f64
+}
+
+const KERNEL_RADIUS: usize = 5;
+fn gaussian_kernel() -> [f32; KERNEL_RADIUS * 2 + 1] {
+    const SIGMA: f32 = 1.5;
+
+    let mut kernel = [0.0; KERNEL_RADIUS * 2 + 1];
+    for (i, k) in kernel.iter_mut().enumerate() {
+        let x = i as f32 - KERNEL_RADIUS as f32;
+        *k = (-x * x / (2.0 * SIGMA * SIGMA)).exp();
+    }
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+    kernel
+}
+/// A separable blur. Pixels outside the image are clamped to the edge.
+fn blur(data: Vec<f32>, width: usize, height: usize, kernel: &[f32]) -> Vec<f32> {
+    let r = KERNEL_RADIUS as isize;
+    let clamp = |i: isize, len: usize| i.clamp(0, len as isize - 1) as usize;
+
+    let mut horizontal = vec![0.0; data.len()];
+    for y in 0..height {
+        let row = &data[y * width..(y + 1) * width];
+        for x in 0..width {
+            horizontal[y * width + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, w)| w * row[clamp(x as isize + k as isize - r, width)])
+                .sum();
+        }
+    }
+
+    let mut out = data;
+    for y in 0..height {
+        for x in 0..width {
+            out[y * width + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, w)| {
+                    w * horizontal[clamp(y as isize + k as isize - r, height) * width + x]
+                })
+                .sum();
+        }
+    }
+    out
+}
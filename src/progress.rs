@@ -1,21 +1,63 @@
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::{EncodingError, Size, SurfaceIndex};
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for () {}
+    impl Sealed for std::ops::ControlFlow<()> {}
+}
+
+/// The return type of progress report functions.
+///
+/// This trait is implemented for `()` and `ControlFlow<()>`. Report functions
+/// that return `ControlFlow::Break(())` cancel the operation. See
+/// [`Progress`] for more details.
+pub trait ReportResult: sealed::Sealed {
+    fn into_control_flow(self) -> ControlFlow<()>;
+}
+impl ReportResult for () {
+    fn into_control_flow(self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+impl ReportResult for ControlFlow<()> {
+    fn into_control_flow(self) -> ControlFlow<()> {
+        self
+    }
+}
 
 trait ThreadSafeReporter: Send {
-    fn report(&mut self, progress: f32);
+    fn report(&mut self, progress: f32) -> ControlFlow<()>;
 }
-impl<F: FnMut(f32) + Send> ThreadSafeReporter for F {
-    fn report(&mut self, progress: f32) {
-        self(progress);
+impl<F: FnMut(f32) -> R + Send, R: ReportResult> ThreadSafeReporter for F {
+    fn report(&mut self, progress: f32) -> ControlFlow<()> {
+        self(progress).into_control_flow()
+    }
+}
+trait BoundReporter {
+    fn report(&mut self, progress: f32) -> ControlFlow<()>;
+}
+impl<F: FnMut(f32) -> R, R: ReportResult> BoundReporter for F {
+    fn report(&mut self, progress: f32) -> ControlFlow<()> {
+        self(progress).into_control_flow()
     }
 }
 
 pub(crate) trait Report {
     fn report(&mut self, progress: f32);
+
+    /// Reports the given progress and returns [`EncodingError::Cancelled`] if
+    /// the operation was cancelled by the report function.
+    fn checked_report(&mut self, progress: f32) -> Result<(), EncodingError>;
 }
 
 enum ProgressInner<'a> {
     Send(&'a mut dyn ThreadSafeReporter),
-    Bound(&'a mut dyn FnMut(f32)),
+    Bound(&'a mut dyn BoundReporter),
 }
 
 #[derive(Clone, Copy)]
@@ -86,8 +128,34 @@ impl Default for ProgressRange {
 /// Note that single-threaded progress reporters will **not** prevent or
 /// interfere with parallel encoding in any way. There's no performance
 /// penalty.
+///
+/// ### Cancellation
+///
+/// The report function may return either `()` or `ControlFlow<()>`. If it
+/// returns `ControlFlow::Break(())`, the operation is cancelled and
+/// [`EncodingError::Cancelled`] is returned as soon as possible. The data
+/// written so far will be incomplete.
+///
+/// ```
+/// # use dds::*;
+/// # use std::ops::ControlFlow;
+/// let mut report = |progress: f32| {
+///     if progress > 0.5 {
+///         ControlFlow::Break(())
+///     } else {
+///         ControlFlow::Continue(())
+///     }
+/// };
+/// let progress = Progress::new(&mut report);
+/// ```
+///
+/// Encoders check for cancellation every time they report progress, so
+/// cancellation takes effect within a few milliseconds. Since
+/// single-threaded reporters are not called during parallel encoding, they
+/// can only cancel before and after parallel sections.
 pub struct Progress<'a> {
     reporter: ProgressInner<'a>,
+    events: Option<&'a mut dyn FnMut(&ProgressEvent)>,
     range: ProgressRange,
 }
 impl<'a> Progress<'a> {
@@ -95,9 +163,10 @@ impl<'a> Progress<'a> {
     ///
     /// This reporter supports multi-threaded progress reporting. See the
     /// documentation of [`Progress`] for more details.
-    pub fn new<F: FnMut(f32) + Send>(reporter: &'a mut F) -> Self {
+    pub fn new<F: FnMut(f32) -> R + Send, R: ReportResult>(reporter: &'a mut F) -> Self {
         Self {
             reporter: ProgressInner::Send(reporter),
+            events: None,
             range: ProgressRange::FULL,
         }
     }
@@ -105,13 +174,45 @@ impl<'a> Progress<'a> {
     ///
     /// This reporter does **not** support multi-threaded progress reporting.
     /// See the documentation of [`Progress`] for more details.
-    pub fn new_single_threaded<F: FnMut(f32)>(reporter: &'a mut F) -> Self {
+    pub fn new_single_threaded<F: FnMut(f32) -> R, R: ReportResult>(reporter: &'a mut F) -> Self {
         Self {
             reporter: ProgressInner::Bound(reporter),
+            events: None,
             range: ProgressRange::FULL,
         }
     }
 
+    /// Sets a function that receives structured [`ProgressEvent`]s.
+    ///
+    /// Events are emitted by [`crate::Encoder::write_surface_with`] when a
+    /// surface (including generated mipmaps) starts and finishes encoding.
+    /// They are always emitted on the thread that called the encoder.
+    ///
+    /// ```
+    /// # use dds::*;
+    /// let mut report = |_progress: f32| {};
+    /// let mut log = |event: &ProgressEvent| {
+    ///     if event.state == SurfaceState::Started {
+    ///         println!("encoding {:?} ({:?})", event.surface, event.size);
+    ///     }
+    /// };
+    /// let progress = Progress::new(&mut report).with_events(&mut log);
+    /// ```
+    pub fn with_events<F: FnMut(&ProgressEvent)>(mut self, events: &'a mut F) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Whether events will be received by anyone.
+    pub(crate) fn has_events(&self) -> bool {
+        self.events.is_some()
+    }
+    pub(crate) fn event(&mut self, event: &ProgressEvent) {
+        if let Some(events) = &mut self.events {
+            events(event);
+        }
+    }
+
     /// Calls the underlying reporter function with the given progress.
     ///
     /// ### Panics
@@ -121,10 +222,14 @@ impl<'a> Progress<'a> {
     ///
     /// The underlying reporter may also panic.
     pub fn report(&mut self, progress: f32) {
+        _ = self.report_impl(progress);
+    }
+
+    fn report_impl(&mut self, progress: f32) -> ControlFlow<()> {
         let progress = self.range.project(progress);
         match &mut self.reporter {
             ProgressInner::Send(report) => report.report(progress),
-            ProgressInner::Bound(report) => report(progress),
+            ProgressInner::Bound(report) => report.report(progress),
         }
     }
 
@@ -135,6 +240,10 @@ impl<'a> Progress<'a> {
                 ProgressInner::Send(f) => ProgressInner::Send(*f),
                 ProgressInner::Bound(f) => ProgressInner::Bound(*f),
             },
+            events: match &mut self.events {
+                Some(f) => Some(*f),
+                None => None,
+            },
             range,
         }
     }
@@ -143,6 +252,12 @@ impl Report for Progress<'_> {
     fn report(&mut self, progress: f32) {
         self.report(progress);
     }
+    fn checked_report(&mut self, progress: f32) -> Result<(), EncodingError> {
+        match self.report_impl(progress) {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(EncodingError::Cancelled),
+        }
+    }
 }
 impl Report for Option<&mut Progress<'_>> {
     fn report(&mut self, progress: f32) {
@@ -150,6 +265,57 @@ impl Report for Option<&mut Progress<'_>> {
             reporter.report(progress);
         }
     }
+    fn checked_report(&mut self, progress: f32) -> Result<(), EncodingError> {
+        if let Some(reporter) = self {
+            reporter.checked_report(progress)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Whether a surface is about to be encoded or has just been encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SurfaceState {
+    Started,
+    Finished,
+}
+
+/// A structured progress event with information about the surface being
+/// encoded.
+///
+/// See [`Progress::with_events`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ProgressEvent {
+    /// The surface this event is about.
+    pub surface: SurfaceIndex,
+    /// The size of the surface.
+    pub size: Size,
+    pub state: SurfaceState,
+    /// The number of pixels of all surfaces that have been encoded so far
+    /// (including the surface of this event if it is finished).
+    pub processed_pixels: u64,
+    /// The total number of pixels of all surfaces in the DDS file.
+    pub total_pixels: u64,
+    /// The time elapsed since the encoder started encoding the first surface.
+    pub elapsed: Duration,
+    /// The estimated time until all surfaces are encoded.
+    ///
+    /// This is extrapolated from the number of pixels processed so far, so it
+    /// is `None` until the first surface has been encoded.
+    pub remaining: Option<Duration>,
+}
+impl ProgressEvent {
+    /// The average number of pixels encoded per second so far.
+    pub fn pixels_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.processed_pixels as f64 / seconds
+        } else {
+            0.0
+        }
+    }
 }
 
 pub(crate) fn sub_progress<'a>(
@@ -161,34 +327,69 @@ pub(crate) fn sub_progress<'a>(
 
 /// The current progress and a function to report it.
 type InnerState<'a> = (u64, &'a mut dyn ThreadSafeReporter);
+/// A function that receives progress events.
+type EventFn<'a> = &'a mut dyn FnMut(&ProgressEvent);
 pub(crate) struct ParallelProgress<'a> {
     progress: Option<Mutex<InnerState<'a>>>,
+    cancelled: AtomicBool,
     total: u64,
     range: ProgressRange,
 }
 impl<'a> ParallelProgress<'a> {
     pub fn new(progress: &'a mut Option<&mut Progress>, total: u64) -> Self {
+        Self::new_with_events(progress, total).0
+    }
+    /// Same as [`Self::new`], but the event function of the progress is
+    /// returned separately, so events can still be emitted by the calling
+    /// thread.
+    pub fn new_with_events(
+        progress: &'a mut Option<&mut Progress>,
+        total: u64,
+    ) -> (Self, Option<EventFn<'a>>) {
         let range = progress.as_ref().map(|p| p.range).unwrap_or_default();
 
-        Self {
-            progress: match progress {
-                Some(Progress {
-                    reporter: ProgressInner::Send(f),
-                    ..
-                }) => Some(Mutex::new((0, *f))),
+        let (reporter, events) = match progress {
+            Some(Progress {
+                reporter, events, ..
+            }) => (Some(reporter), events.as_mut()),
+            None => (None, None),
+        };
+        let parallel = Self {
+            progress: match reporter {
+                Some(ProgressInner::Send(f)) => Some(Mutex::new((0, *f))),
                 _ => None,
             },
+            cancelled: AtomicBool::new(false),
             total,
             range,
-        }
+        };
+        (parallel, events.map(|f| &mut **f as EventFn))
     }
 
-    pub fn submit(&self, progress: u64) {
+    /// Submits the given amount of progress.
+    ///
+    /// Returns [`EncodingError::Cancelled`] if the operation was cancelled by
+    /// this or any previous report.
+    pub fn submit(&self, progress: u64) -> Result<(), EncodingError> {
         if let Some(mutex) = self.progress.as_ref() {
             let mut guard = mutex.lock().unwrap();
+            // don't report again after cancellation
+            self.check_cancelled()?;
             guard.0 += progress;
             let progress = self.range.project(guard.0 as f32 / self.total as f32);
-            guard.1.report(progress);
+            if guard.1.report(progress).is_break() {
+                self.cancelled.store(true, Ordering::Relaxed);
+            }
+        }
+        self.check_cancelled()
+    }
+
+    /// Returns [`EncodingError::Cancelled`] if the operation was cancelled.
+    pub fn check_cancelled(&self) -> Result<(), EncodingError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(EncodingError::Cancelled)
+        } else {
+            Ok(())
         }
     }
 }
@@ -104,6 +104,24 @@ impl ResizeState {
     }
 }
 
+/// An upper bound for the number of bytes allocated by [`Aligner`] and
+/// [`ResizeState`] to resize an image of the given size and color to the new
+/// size.
+pub(crate) fn resize_buffer_bytes(size: Size, new_size: Size, color: ColorFormat) -> u64 {
+    let bytes_per_pixel = color.bytes_per_pixel() as u64;
+    // single-channel images are accumulated as f32, all others as Vec4
+    let accumulator_bytes = if color.channels.count() == 1 { 4 } else { 16 };
+
+    // the source might have to be copied to align it
+    let aligned = size.pixels().saturating_mul(bytes_per_pixel);
+    let dest = new_size.pixels().saturating_mul(bytes_per_pixel);
+    // the resizer stores the horizontally resized image before resizing it
+    // vertically
+    let temp = (new_size.width as u64 * size.height as u64).saturating_mul(accumulator_bytes);
+
+    aligned.saturating_add(dest).saturating_add(temp)
+}
+
 struct Args<'a, 'b> {
     size: Size,
     src_bytes: &'a [u8],
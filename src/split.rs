@@ -1,6 +1,41 @@
 use std::ops::Range;
 
-use crate::{Dithering, EncodeOptions, Format, ImageView, Size};
+use crate::{Dithering, EncodeOptions, EncodingSupport, Format, ImageView, Size};
+
+/// Whether the format dithers the whole surface (as opposed to within a
+/// block) with the given options.
+fn uses_global_dithering(support: &EncodingSupport, options: &EncodeOptions) -> bool {
+    !support.local_dithering()
+        && options.dithering.intersect(support.dithering()) != Dithering::None
+}
+
+/// Returns the split height of the format, rounded up such that
+/// position-based dither patterns line up across fragments.
+///
+/// Returns `None` if the format can't be split.
+pub(crate) fn aligned_split_height(
+    support: &EncodingSupport,
+    options: &EncodeOptions,
+) -> Option<u32> {
+    let split_height = support.split_height()?.get() as u32;
+
+    match options.dithering_algorithm.pattern_size() {
+        Some(pattern) if uses_global_dithering(support, options) => {
+            Some(lcm(split_height, pattern as u32))
+        }
+        _ => Some(split_height),
+    }
+}
+fn lcm(a: u32, b: u32) -> u32 {
+    fn gcd(a: u32, b: u32) -> u32 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+    a / gcd(a, b) * b
+}
 
 /// This implements the main logic for splitting a surface into lines.
 fn split_surface_into_lines(
@@ -13,16 +48,52 @@ fn split_surface_into_lines(
     }
 
     let support = format.encoding_support()?;
-    let split_height = support.split_height()?;
+    let split_height = aligned_split_height(&support, options)?;
 
-    // dithering destroys our ability to split the surface into lines,
-    // because it would create visible seams
-    if !support.local_dithering()
-        && options.dithering.intersect(support.dithering()) != Dithering::None
+    // error diffusion dithering destroys our ability to split the surface
+    // into lines, because it would create visible seams
+    if uses_global_dithering(&support, options) && !options.dithering_algorithm.is_position_based()
     {
         return None;
     }
 
+    line_groups(size, split_height, &support, options)
+}
+
+/// This implements the logic for splitting a bi-planar surface into lines.
+///
+/// Line groups of bi-planar formats can be encoded independently, but they
+/// can't simply be concatenated, because all of plane 1 is stored before
+/// plane 2. This is why their [`EncodingSupport::split_height`] is `None`.
+#[cfg(feature = "rayon")]
+fn split_bi_planar_surface_into_lines(
+    size: Size,
+    format: Format,
+    options: &EncodeOptions,
+) -> Option<Vec<Range<u32>>> {
+    if size.is_empty() {
+        return None;
+    }
+
+    let split_height = match crate::PixelInfo::from(format) {
+        crate::PixelInfo::BiPlanar(info) => info.plane2_sub_sampling().1 as u32,
+        _ => return None,
+    };
+    let support = format.encoding_support()?;
+
+    line_groups(size, split_height, &support, options)
+}
+
+/// Splits the surface into groups of lines of roughly the preferred group
+/// size. All groups except the last have a multiple of `split_height` lines.
+///
+/// Returns `None` if the surface is too small to be worth splitting.
+fn line_groups(
+    size: Size,
+    split_height: u32,
+    support: &EncodingSupport,
+    options: &EncodeOptions,
+) -> Option<Vec<Range<u32>>> {
     let group_pixels = support
         .group_size()
         .get_group_pixels(options.quality)
@@ -33,8 +104,8 @@ fn split_surface_into_lines(
     }
 
     let group_height = u64::clamp(
-        (group_pixels / size.width as u64) / split_height.get() as u64 * split_height.get() as u64,
-        split_height.get() as u64,
+        (group_pixels / size.width as u64) / split_height as u64 * split_height as u64,
+        split_height as u64,
         u32::MAX as u64,
     ) as u32;
 
@@ -70,7 +141,32 @@ impl<'a> SplitSurface<'a> {
     }
 
     pub fn new(image: ImageView<'a>, format: Format, options: &EncodeOptions) -> Self {
-        if let Some(ranges) = split_surface_into_lines(image.size(), format, options) {
+        let ranges = split_surface_into_lines(image.size(), format, options);
+        Self::from_ranges(image, format, options, ranges)
+    }
+
+    /// Splits a surface of a bi-planar format into line groups.
+    ///
+    /// Unlike [`SplitSurface::new`], the encoded fragments can't be
+    /// concatenated. Plane 1 of all fragments has to be written before plane 2
+    /// of all fragments.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn new_bi_planar(
+        image: ImageView<'a>,
+        format: Format,
+        options: &EncodeOptions,
+    ) -> Self {
+        let ranges = split_bi_planar_surface_into_lines(image.size(), format, options);
+        Self::from_ranges(image, format, options, ranges)
+    }
+
+    fn from_ranges(
+        image: ImageView<'a>,
+        format: Format,
+        options: &EncodeOptions,
+        ranges: Option<Vec<Range<u32>>>,
+    ) -> Self {
+        if let Some(ranges) = ranges {
             let row_pitch = image.row_pitch();
 
             let fragments = ranges
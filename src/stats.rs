@@ -0,0 +1,262 @@
+use std::io::Write;
+
+use crate::{
+    cast, compare, convert_channels, decode, encode, encode::encoder_input_rgba_f32, ColorFormat,
+    DecodeOptions, EncodeOptions, EncodingError, Format, ImageView, ImageViewMut, MetricSpace,
+    Metrics, PixelInfo, Precision, Progress, Size,
+};
+
+/// The error of a single block of an encoded surface.
+///
+/// See [`EncodeStats::worst_blocks`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct BlockError {
+    /// The x coordinate of the top-left pixel of the block.
+    pub x: u32,
+    /// The y coordinate of the top-left pixel of the block.
+    pub y: u32,
+    /// The mean squared error of all pixels and channels of the block.
+    pub mse: f64,
+    /// The maximum absolute error of any pixel and channel of the block.
+    pub max_error: f32,
+}
+
+/// How often each block mode was used by BC1-BC5 formats.
+///
+/// All counts are 0 for other formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct BlockModes {
+    /// The number of BC1 blocks with 2 interpolated colors (`color0 > color1`).
+    ///
+    /// The color blocks of BC2 and BC3 always use 4 colors and are not
+    /// counted.
+    pub bc1_four_color: u64,
+    /// The number of BC1 blocks with 1 interpolated color and black or
+    /// transparent (`color0 <= color1`).
+    pub bc1_three_color: u64,
+    /// The number of BC4 blocks with 6 interpolated values (`red0 > red1`).
+    ///
+    /// This includes the alpha blocks of BC3 and both channels of BC5.
+    pub bc4_six_interpolated: u64,
+    /// The number of BC4 blocks with 4 interpolated values and explicit 0 and
+    /// 1 (or -1 and 1 for SNORM) (`red0 <= red1`).
+    pub bc4_four_interpolated: u64,
+}
+
+/// Error statistics of an encoded surface. See [`encode_with_stats`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct EncodeStats {
+    /// Error metrics for the whole surface.
+    ///
+    /// The errors are measured in the native color format of the encoded
+    /// format (see [`Format::channels`]). E.g. for `BC4_UNORM`, the encoded
+    /// surface is compared to the grayscale version of the image.
+    pub metrics: Metrics,
+    /// The size of the blocks in [`EncodeStats::worst_blocks`].
+    ///
+    /// For block-compressed formats, this is the block size of the format.
+    /// For all other formats, the surface is split into 4x4 regions.
+    pub block_size: Size,
+    /// The blocks with the largest errors, sorted by descending MSE.
+    ///
+    /// At most [`EncodeStats::WORST_BLOCKS`] blocks are reported.
+    pub worst_blocks: Vec<BlockError>,
+    /// How often each block mode was used.
+    pub modes: BlockModes,
+}
+impl EncodeStats {
+    /// The maximum number of blocks in [`EncodeStats::worst_blocks`].
+    pub const WORST_BLOCKS: usize = 16;
+
+    /// The block with the largest error, if any.
+    pub fn worst_block(&self) -> Option<BlockError> {
+        self.worst_blocks.first().copied()
+    }
+}
+
+/// Encodes a single surface like [`encode()`] and returns error statistics
+/// of the encoded data.
+///
+/// The statistics are measured by decoding the encoded data and comparing it
+/// to the given image (with [`EncodeOptions::channel_mapping`] and
+/// [`EncodeOptions::signed_snorm`] applied), so they are exactly what users
+/// of the file will see.
+/// This makes this function slightly slower than [`encode()`] and it needs
+/// to buffer the encoded surface in memory.
+///
+/// This is useful to find images that compress badly in a certain format.
+pub fn encode_with_stats(
+    writer: &mut dyn Write,
+    image: ImageView,
+    format: Format,
+    progress: Option<&mut Progress>,
+    options: &EncodeOptions,
+) -> Result<EncodeStats, EncodingError> {
+    let mut encoded = Vec::new();
+    encode(&mut encoded, image, format, progress, options)?;
+    writer.write_all(&encoded)?;
+
+    let size = image.size();
+    let (decoded, native) = decode_native(&encoded, size, format);
+
+    // convert the original image to the native channels
+    let rgba = encoder_input_rgba_f32(image, format, options);
+    let mut original = vec![0_f32; decoded.len()];
+    convert_channels::<f32>(
+        crate::Channels::Rgba,
+        native.channels,
+        cast::as_bytes(&rgba),
+        cast::as_bytes_mut(&mut original),
+    );
+
+    let metrics = compare(
+        ImageView::new(cast::as_bytes(&original), size, native).expect("invalid buffer"),
+        ImageView::new(cast::as_bytes(&decoded), size, native).expect("invalid buffer"),
+        MetricSpace::Rgb,
+    )
+    .expect("sizes match");
+
+    let block_size = match PixelInfo::from(format) {
+        PixelInfo::Block(info) => {
+            let (w, h) = info.size();
+            Size::new(w as u32, h as u32)
+        }
+        _ => Size::new(4, 4),
+    };
+    let worst_blocks = worst_blocks(
+        &original,
+        &decoded,
+        size,
+        native.channels.count() as usize,
+        block_size,
+    );
+
+    Ok(EncodeStats {
+        metrics,
+        block_size,
+        worst_blocks,
+        modes: count_modes(&encoded, format),
+    })
+}
+
+/// Decodes an encoded surface to the native channels of the format with
+/// f32 precision.
+pub(crate) fn decode_native(encoded: &[u8], size: Size, format: Format) -> (Vec<f32>, ColorFormat) {
+    let native = ColorFormat::new(format.channels(), Precision::F32);
+    let mut decoded = vec![0_f32; size.pixels() as usize * native.channels.count() as usize];
+
+    // the data was just encoded, so decoding it can't fail
+    let decode_options = DecodeOptions {
+        memory_limit: usize::MAX,
+        ..Default::default()
+    };
+    decode(
+        &mut &encoded[..],
+        ImageViewMut::new(cast::as_bytes_mut(&mut decoded), size, native).expect("invalid buffer"),
+        format,
+        &decode_options,
+    )
+    .expect("failed to decode the encoded surface");
+
+    (decoded, native)
+}
+
+fn worst_blocks(
+    original: &[f32],
+    decoded: &[f32],
+    size: Size,
+    channels: usize,
+    block_size: Size,
+) -> Vec<BlockError> {
+    let width = size.width as usize;
+    let mut blocks = Vec::new();
+
+    for y in (0..size.height).step_by(block_size.height as usize) {
+        for x in (0..size.width).step_by(block_size.width as usize) {
+            let x_end = (x + block_size.width).min(size.width);
+            let y_end = (y + block_size.height).min(size.height);
+
+            let mut sum = 0.0;
+            let mut count = 0;
+            let mut max_error: f32 = 0.0;
+            for py in y..y_end {
+                let row_start = (py as usize * width + x as usize) * channels;
+                let row_end = (py as usize * width + x_end as usize) * channels;
+                for (a, b) in original[row_start..row_end]
+                    .iter()
+                    .zip(&decoded[row_start..row_end])
+                {
+                    let diff = (a - b).abs();
+                    sum += diff as f64 * diff as f64;
+                    max_error = max_error.max(diff);
+                    count += 1;
+                }
+            }
+
+            blocks.push(BlockError {
+                x,
+                y,
+                mse: sum / count as f64,
+                max_error,
+            });
+        }
+    }
+
+    blocks.sort_by(|a, b| b.mse.total_cmp(&a.mse));
+    blocks.truncate(EncodeStats::WORST_BLOCKS);
+    blocks
+}
+
+fn count_modes(encoded: &[u8], format: Format) -> BlockModes {
+    enum Part {
+        Bc1,
+        Bc4 { snorm: bool },
+        Skip,
+    }
+    let parts: &[Part] = match format {
+        Format::BC1_UNORM => &[Part::Bc1],
+        Format::BC3_UNORM
+        | Format::BC3_UNORM_PREMULTIPLIED_ALPHA
+        | Format::BC3_UNORM_RXGB
+        | Format::BC3_UNORM_NORMAL => &[Part::Bc4 { snorm: false }, Part::Skip],
+        Format::BC4_UNORM => &[Part::Bc4 { snorm: false }],
+        Format::BC4_SNORM => &[Part::Bc4 { snorm: true }],
+        Format::BC5_UNORM => &[Part::Bc4 { snorm: false }, Part::Bc4 { snorm: false }],
+        Format::BC5_SNORM => &[Part::Bc4 { snorm: true }, Part::Bc4 { snorm: true }],
+        _ => return BlockModes::default(),
+    };
+
+    let mut modes = BlockModes::default();
+    for block in encoded.chunks_exact(parts.len() * 8) {
+        for (part, bytes) in parts.iter().zip(block.chunks_exact(8)) {
+            match part {
+                Part::Bc1 => {
+                    let color0 = u16::from_le_bytes([bytes[0], bytes[1]]);
+                    let color1 = u16::from_le_bytes([bytes[2], bytes[3]]);
+                    if color0 > color1 {
+                        modes.bc1_four_color += 1;
+                    } else {
+                        modes.bc1_three_color += 1;
+                    }
+                }
+                Part::Bc4 { snorm } => {
+                    let six = if *snorm {
+                        bytes[0] as i8 > bytes[1] as i8
+                    } else {
+                        bytes[0] > bytes[1]
+                    };
+                    if six {
+                        modes.bc4_six_interpolated += 1;
+                    } else {
+                        modes.bc4_four_interpolated += 1;
+                    }
+                }
+                Part::Skip => {}
+            }
+        }
+    }
+    modes
+}
@@ -0,0 +1,354 @@
+use crate::{
+    convert_to_rgba_f32,
+    header::{Dx9PixelFormat, DxgiFormat},
+    Format, ImageView, Precision,
+};
+
+/// How the alpha channel of an image is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlphaKind {
+    /// All pixels are fully opaque.
+    Opaque,
+    /// All pixels have the same alpha value, which is not fully opaque.
+    Constant,
+    /// All pixels are either fully opaque or fully transparent.
+    Binary,
+    /// The alpha channel contains intermediate values.
+    Gradient,
+}
+
+/// The result of [`analyze_image`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ImageAnalysis {
+    /// How the alpha channel is used.
+    pub alpha: AlphaKind,
+    /// Whether the R, G, and B channels of all pixels are (nearly) equal.
+    pub grayscale: bool,
+    /// Whether the image looks like a tangent-space normal map.
+    ///
+    /// This is the case if (almost) all pixels decode to a unit vector
+    /// pointing away from the surface (positive Z).
+    pub normal_map: bool,
+    /// Whether the color channels contain values that cannot be represented
+    /// with 8 bits per channel. Only images with a precision of 16 or 32 bits
+    /// can contain such values.
+    pub high_precision: bool,
+    /// The smallest value of any color channel (R, G, B).
+    pub min: f32,
+    /// The largest value of any color channel (R, G, B).
+    pub max: f32,
+}
+impl ImageAnalysis {
+    /// Whether the color channels contain values outside the range 0 to 1.
+    pub fn is_hdr(&self) -> bool {
+        self.min < 0.0 || self.max > 1.0
+    }
+}
+
+/// Analyzes the content of an image.
+///
+/// This is used by [`suggest_format`] to pick a suitable format.
+pub fn analyze_image(image: ImageView) -> ImageAnalysis {
+    /// The tolerance for comparing channels. This is slightly less than half
+    /// an 8-bit step, so rounding errors of 8-bit images are ignored.
+    const TOLERANCE: f32 = 0.4 / 255.0;
+
+    let mut rgba = vec![[0_f32; 4]; image.size().pixels() as usize];
+    convert_to_rgba_f32(image.color(), image.data(), &mut rgba);
+
+    let mut min = f32::INFINITY;
+    let mut max = f32::NEG_INFINITY;
+    let mut grayscale = true;
+    let mut high_precision = false;
+    let check_precision = image.color().precision != Precision::U8;
+
+    let first_alpha = rgba.first().map_or(1.0, |p| p[3]);
+    let mut alpha_constant = true;
+    let mut alpha_binary = true;
+
+    let mut normal_pixels: usize = 0;
+
+    for &[r, g, b, a] in &rgba {
+        min = min.min(r).min(g).min(b);
+        max = max.max(r).max(g).max(b);
+
+        grayscale &= (r - g).abs() <= TOLERANCE && (r - b).abs() <= TOLERANCE;
+        if check_precision && !high_precision {
+            high_precision = [r, g, b].iter().any(|&c| {
+                let scaled = c * 255.0;
+                (scaled - scaled.round()).abs() > 0.1
+            });
+        }
+
+        alpha_constant &= (a - first_alpha).abs() <= TOLERANCE;
+        alpha_binary &= a <= TOLERANCE || a >= 1.0 - TOLERANCE;
+
+        let (x, y, z) = (r * 2.0 - 1.0, g * 2.0 - 1.0, b * 2.0 - 1.0);
+        let length = (x * x + y * y + z * z).sqrt();
+        if (length - 1.0).abs() < 0.1 && z > 0.0 {
+            normal_pixels += 1;
+        }
+    }
+
+    let alpha = if alpha_constant && first_alpha >= 1.0 - TOLERANCE {
+        AlphaKind::Opaque
+    } else if alpha_constant {
+        AlphaKind::Constant
+    } else if alpha_binary {
+        AlphaKind::Binary
+    } else {
+        AlphaKind::Gradient
+    };
+
+    if rgba.is_empty() {
+        min = 0.0;
+        max = 0.0;
+    }
+
+    ImageAnalysis {
+        alpha,
+        grayscale,
+        // flat normal maps are also grayscale-ish, but no normal map is
+        // perfectly gray
+        normal_map: !rgba.is_empty() && !grayscale && normal_pixels * 100 >= rgba.len() * 95,
+        high_precision,
+        min,
+        max,
+    }
+}
+
+/// The kind of header a format suggested by [`suggest_format`] has to be
+/// representable with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HeaderRequirement {
+    /// Formats that can be represented by either kind of header.
+    #[default]
+    Any,
+    /// Formats that can be represented by a DX9 header, i.e. formats with a
+    /// pixel format or FourCC code.
+    Dx9,
+    /// Formats that can be represented by a DX10 header, i.e. formats with a
+    /// DXGI format.
+    Dx10,
+}
+impl HeaderRequirement {
+    /// Whether the given format satisfies this requirement.
+    pub fn allows(self, format: Format) -> bool {
+        let dx9 = || Dx9PixelFormat::try_from(format).is_ok();
+        let dx10 = || DxgiFormat::try_from(format).is_ok();
+        match self {
+            HeaderRequirement::Any => dx9() || dx10(),
+            HeaderRequirement::Dx9 => dx9(),
+            HeaderRequirement::Dx10 => dx10(),
+        }
+    }
+}
+
+/// Restrictions for [`suggest_format`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FormatConstraints {
+    /// The kind of header the file will be written with.
+    ///
+    /// Only formats that can be represented by this kind of header are
+    /// suggested.
+    ///
+    /// Default: [`HeaderRequirement::Any`]
+    pub header: HeaderRequirement,
+    /// Whether block-compressed formats (BC1-BC5) may be suggested.
+    ///
+    /// Default: `true`
+    pub block_compression: bool,
+}
+impl Default for FormatConstraints {
+    fn default() -> Self {
+        Self {
+            header: HeaderRequirement::Any,
+            block_compression: true,
+        }
+    }
+}
+
+/// A format recommended by [`suggest_format`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FormatSuggestion {
+    /// The suggested format.
+    pub format: Format,
+    /// A short, human-readable reason for the suggestion.
+    pub reason: &'static str,
+    /// The analysis the suggestion is based on.
+    pub analysis: ImageAnalysis,
+}
+
+/// Suggests a format for the given image based on its content.
+///
+/// The image is analyzed with [`analyze_image`] and the most compact format
+/// that preserves the relevant properties of the image is suggested. E.g.
+/// images with binary alpha are suggested `BC1_UNORM`, while images with
+/// gradient alpha are suggested `BC3_UNORM`.
+///
+/// Only formats that this crate can encode, that support the size of the
+/// image, and that satisfy the given constraints are suggested. If the
+/// preferred format is not allowed, the next best format is suggested. If no
+/// format is allowed, `None` is returned.
+pub fn suggest_format(
+    image: ImageView,
+    constraints: &FormatConstraints,
+) -> Option<FormatSuggestion> {
+    let analysis = analyze_image(image);
+
+    candidates(&analysis)
+        .iter()
+        .find(|(format, _)| is_allowed(*format, image, constraints))
+        .map(|&(format, reason)| FormatSuggestion {
+            format,
+            reason,
+            analysis,
+        })
+}
+
+fn is_allowed(format: Format, image: ImageView, constraints: &FormatConstraints) -> bool {
+    let support = match format.encoding_support() {
+        Some(support) => support,
+        None => return false,
+    };
+    let is_block_compressed = matches!(
+        format,
+        Format::BC1_UNORM
+            | Format::BC2_UNORM
+            | Format::BC2_UNORM_PREMULTIPLIED_ALPHA
+            | Format::BC3_UNORM
+            | Format::BC3_UNORM_PREMULTIPLIED_ALPHA
+            | Format::BC3_UNORM_RXGB
+            | Format::BC3_UNORM_NORMAL
+            | Format::BC4_UNORM
+            | Format::BC4_SNORM
+            | Format::BC5_UNORM
+            | Format::BC5_SNORM
+    );
+
+    constraints.header.allows(format)
+        && support.supports_size(image.size())
+        && (constraints.block_compression || !is_block_compressed)
+}
+
+/// Returns the candidate formats from most to least preferred.
+fn candidates(analysis: &ImageAnalysis) -> &'static [(Format, &'static str)] {
+    use Format::*;
+
+    let opaque = analysis.alpha == AlphaKind::Opaque;
+
+    if analysis.is_hdr() {
+        return match (analysis.grayscale && opaque, opaque && analysis.min >= 0.0) {
+            (true, _) => &[
+                (R16_FLOAT, "grayscale HDR image"),
+                (R32_FLOAT, "grayscale HDR image"),
+                (R16G16B16A16_FLOAT, "grayscale HDR image"),
+            ],
+            (false, true) => &[
+                (R11G11B10_FLOAT, "opaque HDR image without negative values"),
+                (
+                    R9G9B9E5_SHAREDEXP,
+                    "opaque HDR image without negative values",
+                ),
+                (
+                    R16G16B16A16_FLOAT,
+                    "opaque HDR image without negative values",
+                ),
+                (
+                    R32G32B32A32_FLOAT,
+                    "opaque HDR image without negative values",
+                ),
+            ],
+            (false, false) => &[
+                (
+                    R16G16B16A16_FLOAT,
+                    "HDR image with alpha or negative values",
+                ),
+                (
+                    R32G32B32A32_FLOAT,
+                    "HDR image with alpha or negative values",
+                ),
+            ],
+        };
+    }
+
+    if analysis.normal_map && opaque {
+        return &[
+            (
+                BC5_UNORM,
+                "tangent-space normal map; BC5 stores X and Y with the best quality",
+            ),
+            (
+                BC3_UNORM_NORMAL,
+                "tangent-space normal map; DXT5nm stores X and Y with good quality",
+            ),
+            (
+                R8G8_UNORM,
+                "tangent-space normal map; Z can be reconstructed from X and Y",
+            ),
+            (R8G8B8A8_UNORM, "tangent-space normal map"),
+        ];
+    }
+
+    if analysis.grayscale && opaque {
+        if analysis.high_precision {
+            return &[
+                (
+                    R16_UNORM,
+                    "grayscale image with more than 8 bits of precision",
+                ),
+                (
+                    R32_FLOAT,
+                    "grayscale image with more than 8 bits of precision",
+                ),
+                (
+                    R16G16B16A16_UNORM,
+                    "grayscale image with more than 8 bits of precision",
+                ),
+            ];
+        }
+        return &[
+            (BC4_UNORM, "opaque grayscale image"),
+            (R8_UNORM, "opaque grayscale image"),
+            (B5G6R5_UNORM, "opaque grayscale image"),
+            (R8G8B8A8_UNORM, "opaque grayscale image"),
+        ];
+    }
+
+    match analysis.alpha {
+        AlphaKind::Opaque => &[
+            (BC1_UNORM, "opaque color image"),
+            (B5G6R5_UNORM, "opaque color image"),
+            (R8G8B8A8_UNORM, "opaque color image"),
+        ],
+        AlphaKind::Binary => &[
+            (BC1_UNORM, "binary alpha fits the 1-bit alpha of BC1"),
+            (
+                B5G5R5A1_UNORM,
+                "binary alpha fits the 1-bit alpha of B5G5R5A1",
+            ),
+            (R8G8B8A8_UNORM, "binary alpha"),
+        ],
+        AlphaKind::Constant | AlphaKind::Gradient => &[
+            (
+                BC3_UNORM,
+                "alpha with intermediate values needs a separate alpha channel",
+            ),
+            (
+                BC2_UNORM,
+                "alpha with intermediate values needs a separate alpha channel",
+            ),
+            (
+                B4G4R4A4_UNORM,
+                "alpha with intermediate values needs a separate alpha channel",
+            ),
+            (
+                R8G8B8A8_UNORM,
+                "alpha with intermediate values needs a separate alpha channel",
+            ),
+        ],
+    }
+}
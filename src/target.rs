@@ -0,0 +1,179 @@
+use std::io::Write;
+
+use crate::{
+    cast, compare, encode, encode::encoder_input_rgba_f32, stats::decode_native, sub_progress,
+    ColorFormat, CompressionQuality, EncodeOptions, EncodingError, Format, ImageView, MetricSpace,
+    Metrics, PixelInfo, Progress, ProgressRange, Report,
+};
+
+/// The quality goal of [`encode_with_target`].
+///
+/// The goal has to be met by every channel (R, G, B, A) individually.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityTarget {
+    /// The minimum PSNR in dB.
+    MinPsnr(f64),
+    /// The maximum RMSE. Errors are relative to a range of 0 to 1.
+    MaxRmse(f64),
+}
+impl QualityTarget {
+    fn is_met(&self, metrics: &Metrics) -> bool {
+        metrics.channels.iter().all(|c| match *self {
+            QualityTarget::MinPsnr(psnr) => c.psnr() >= psnr,
+            QualityTarget::MaxRmse(rmse) => c.rmse() <= rmse,
+        })
+    }
+}
+
+/// The result of [`encode_with_target`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct TargetEncoding {
+    /// The format that was chosen.
+    pub format: Format,
+    /// The compression quality that was chosen.
+    ///
+    /// For formats that don't support different quality levels, this is the
+    /// quality of the given options.
+    pub quality: CompressionQuality,
+    /// The error metrics of the chosen encoding compared to the input image.
+    pub metrics: Metrics,
+    /// Whether the chosen encoding meets the quality target.
+    ///
+    /// If no candidate meets the target, the candidate with the smallest
+    /// maximum channel error is chosen and this is `false`.
+    pub target_met: bool,
+    /// The number of candidates that were encoded.
+    pub attempts: u32,
+}
+
+/// The compression qualities that are tried, from cheapest to most
+/// expensive. `Unreasonable` is only meant for reference images and not
+/// tried.
+const QUALITIES: [CompressionQuality; 3] = [
+    CompressionQuality::Fast,
+    CompressionQuality::Normal,
+    CompressionQuality::High,
+];
+
+/// Encodes a single surface with the cheapest format and quality that meets
+/// the given quality target.
+///
+/// Candidates are tried in the order of `formats` and, for block-compressed
+/// formats, from the fastest to the slowest [`CompressionQuality`] (`Fast`,
+/// `Normal`, `High`). So `formats` should be ordered from most to least
+/// preferred, typically from smallest to largest. E.g. `[BC1_UNORM,
+/// BC3_UNORM, R8G8B8A8_UNORM]`. The first candidate that meets the target is
+/// written to the writer. The quality of `options` is ignored for
+/// block-compressed formats.
+///
+/// Each candidate is encoded and decoded again to measure its quality. The
+/// error is measured against the input image with all channels converted to
+/// RGBA (see [`compare`]) and [`EncodeOptions::channel_mapping`] and
+/// [`EncodeOptions::signed_snorm`] applied.
+/// This means that e.g. `BC4_UNORM` will only meet the target for color
+/// images if they are (close to) grayscale.
+///
+/// If no candidate meets the target, the candidate with the smallest error
+/// is written and [`TargetEncoding::target_met`] is `false`.
+///
+/// If any of the formats does not support encoding,
+/// [`EncodingError::UnsupportedFormat`] is returned before anything is
+/// encoded. If `formats` is empty, [`EncodingError::NoCandidateFormats`] is
+/// returned.
+pub fn encode_with_target(
+    writer: &mut dyn Write,
+    image: ImageView,
+    formats: &[Format],
+    target: QualityTarget,
+    mut progress: Option<&mut Progress>,
+    options: &EncodeOptions,
+) -> Result<TargetEncoding, EncodingError> {
+    if formats.is_empty() {
+        return Err(EncodingError::NoCandidateFormats);
+    }
+    if let Some(&format) = formats.iter().find(|f| f.encoding_support().is_none()) {
+        return Err(EncodingError::UnsupportedFormat(format));
+    }
+
+    let candidates: Vec<(Format, CompressionQuality)> = formats
+        .iter()
+        .flat_map(|&format| {
+            let qualities: &[CompressionQuality] = if has_quality_levels(format) {
+                &QUALITIES
+            } else {
+                std::slice::from_ref(&options.quality)
+            };
+            qualities.iter().map(move |&quality| (format, quality))
+        })
+        .collect();
+
+    let mut best: Option<(TargetEncoding, Vec<u8>)> = None;
+    let mut attempts = 0;
+    for (i, &(format, quality)) in candidates.iter().enumerate() {
+        let range = ProgressRange::from_to(
+            i as f32 / candidates.len() as f32,
+            (i + 1) as f32 / candidates.len() as f32,
+        );
+
+        let mut options = options.clone();
+        options.quality = quality;
+        let mut encoded = Vec::new();
+        encode(
+            &mut encoded,
+            image,
+            format,
+            sub_progress(&mut progress, range).as_mut(),
+            &options,
+        )?;
+
+        // the encoded image is compared to the transformed input
+        let reference = encoder_input_rgba_f32(image, format, &options);
+        let reference = ImageView::new(
+            cast::as_bytes(&reference),
+            image.size(),
+            ColorFormat::RGBA_F32,
+        )
+        .expect("invalid buffer");
+
+        let (decoded, native) = decode_native(&encoded, image.size(), format);
+        let decoded =
+            ImageView::new(cast::as_bytes(&decoded), image.size(), native).expect("invalid buffer");
+        let metrics = compare(reference, decoded, MetricSpace::Rgb).expect("sizes match");
+
+        let result = TargetEncoding {
+            format,
+            quality,
+            target_met: target.is_met(&metrics),
+            metrics,
+            attempts: 0,
+        };
+        attempts += 1;
+        let is_better = match &best {
+            Some((b, _)) => max_mse(&result.metrics) < max_mse(&b.metrics),
+            None => true,
+        };
+        let target_met = result.target_met;
+        if is_better || target_met {
+            best = Some((result, encoded));
+        }
+        if target_met {
+            break;
+        }
+    }
+
+    let (mut result, encoded) = best.expect("at least one candidate");
+    result.attempts = attempts;
+    writer.write_all(&encoded)?;
+    progress.report(1.0);
+
+    Ok(result)
+}
+
+/// Whether the compression quality affects the encoding of the format.
+fn has_quality_levels(format: Format) -> bool {
+    matches!(PixelInfo::from(format), PixelInfo::Block(info) if info.size() == (4, 4))
+}
+fn max_mse(metrics: &Metrics) -> f64 {
+    metrics.channels.iter().map(|c| c.mse).fold(0.0, f64::max)
+}
@@ -0,0 +1,356 @@
+use std::io::{Read, Write};
+
+use crate::decode::bc_blocks;
+use crate::encode::{compress_bc1_block, compress_bc4_block, Bc1Options, Bc4Options};
+use crate::{
+    decode, encode, util::div_ceil, DecodeOptions, DecodingError, EncodeOptions, Format, ImageView,
+    ImageViewMut, LayoutError, PixelInfo, Size, TranscodeError,
+};
+
+/// How [`transcode`] converted the data of a surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TranscodePath {
+    /// All blocks were copied or rewritten directly. Decoding the transcoded
+    /// surface will produce exactly the same values as the original surface
+    /// (for all channels supported by the target format).
+    Lossless,
+    /// All blocks were rewritten directly, but some parts of some blocks had
+    /// to be re-encoded. E.g. when converting from premultiplied alpha to
+    /// straight alpha, the color endpoints of blocks that aren't fully opaque
+    /// have to be recomputed. The alpha (or color) part of those blocks is
+    /// still copied exactly.
+    BlockRewrite,
+    /// There is no direct path between the formats, so the surface was fully
+    /// decoded and then encoded again with [`encode()`].
+    Reencode,
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscodeOptions {
+    /// The options used to encode the surface if it has to be re-encoded.
+    ///
+    /// Default: `EncodeOptions::default()`
+    pub encode: EncodeOptions,
+    /// The maximum amount of memory that [`transcode`] is allowed to allocate.
+    ///
+    /// Both block-level conversions and re-encoding hold the entire surface
+    /// in memory. If the surface requires more memory than this limit,
+    /// [`DecodingError::MemoryLimitExceeded`] is returned.
+    ///
+    /// Default: 256 MiB
+    ///
+    /// (The default is large enough to re-encode 8K images with U8 precision
+    /// and 4K images with F32 precision.)
+    pub memory_limit: usize,
+}
+impl Default for TranscodeOptions {
+    fn default() -> Self {
+        Self {
+            encode: EncodeOptions::default(),
+            memory_limit: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Converts a single surface from one format to another.
+///
+/// Some conversions can be done (nearly) exactly at the block level without
+/// decoding and re-encoding the whole surface:
+///
+/// - `BC1_UNORM` to `BC3_UNORM` (and `BC3_UNORM_PREMULTIPLIED_ALPHA`). The
+///   color block is reused and BC1's 1-bit alpha is stored in the BC3 alpha
+///   block.
+/// - `BC4_UNORM` to `BC5_UNORM` and `BC4_SNORM` to `BC5_SNORM`. The BC4 block
+///   becomes the red channel and green is set to 0.
+/// - `BC2_UNORM` and `BC3_UNORM` to and from their premultiplied alpha
+///   variants (DXT2/DXT4). The alpha block is copied and only the color
+///   blocks of non-opaque blocks are re-encoded.
+/// - `BC3_UNORM` to and from `BC3_UNORM_RXGB`. Since these formats store the
+///   red channel in different blocks, only that block is re-encoded.
+///
+/// All other conversions fall back to decoding the surface and encoding it
+/// with [`encode()`] using [`TranscodeOptions::encode`].
+///
+/// The surface is held in memory, and the memory required for it is checked
+/// against [`TranscodeOptions::memory_limit`] before anything is read.
+///
+/// The returned [`TranscodePath`] describes which kind of path was used.
+///
+/// ## State of the reader and writer
+///
+/// The reader is expected to be positioned at the start of the encoded
+/// surface. After the operation completes successfully, the reader will be
+/// positioned at the end of the surface and the transcoded surface will have
+/// been written to the writer.
+pub fn transcode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    size: Size,
+    from: Format,
+    to: Format,
+    options: &TranscodeOptions,
+) -> Result<TranscodePath, TranscodeError> {
+    // checks that `len` bytes (`None` on overflow) can be allocated
+    let check_memory = |len: Option<u64>| -> Result<usize, DecodingError> {
+        let len = len.ok_or(LayoutError::DataLayoutTooBig)?;
+        if len > options.memory_limit as u64 {
+            return Err(DecodingError::MemoryLimitExceeded);
+        }
+        Ok(len as usize)
+    };
+
+    if let Some(rewrite) = BlockRewrite::new(from, to) {
+        let input_len = PixelInfo::from(from).surface_bytes(size);
+        let output_len = PixelInfo::from(to).surface_bytes(size);
+        // both the input and the output surface are held in memory
+        check_memory(
+            input_len
+                .zip(output_len)
+                .and_then(|(i, o)| i.checked_add(o)),
+        )?;
+
+        // PANIC SAFETY: the input length was checked above
+        let mut data = vec![0_u8; input_len.unwrap() as usize];
+        reader.read_exact(&mut data).map_err(DecodingError::Io)?;
+
+        let (encoded, exact) = rewrite.apply(&data, size);
+        writer
+            .write_all(&encoded)
+            .map_err(crate::EncodingError::Io)?;
+
+        return Ok(if exact {
+            TranscodePath::Lossless
+        } else {
+            TranscodePath::BlockRewrite
+        });
+    }
+
+    let color = from.color();
+    let len = check_memory(size.pixels().checked_mul(color.bytes_per_pixel() as u64))?;
+    let mut image = vec![0_u8; len];
+    let view = ImageViewMut::new(&mut image, size, color).expect("invalid image buffer");
+    decode(reader, view, from, &DecodeOptions::default())?;
+
+    let view = ImageView::new(&image, size, color).expect("invalid image buffer");
+    encode(writer, view, to, None, &options.encode)?;
+
+    Ok(TranscodePath::Reencode)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BlockRewrite {
+    Copy,
+    Bc1ToBc3,
+    Bc4ToBc5,
+    Premultiply { bc3: bool },
+    Unpremultiply { bc3: bool },
+    Bc3ToRxgb,
+    RxgbToBc3,
+}
+impl BlockRewrite {
+    fn new(from: Format, to: Format) -> Option<Self> {
+        use Format::*;
+
+        Some(match (from, to) {
+            _ if from == to => Self::Copy,
+            (BC1_UNORM, BC3_UNORM | BC3_UNORM_PREMULTIPLIED_ALPHA) => Self::Bc1ToBc3,
+            (BC4_UNORM, BC5_UNORM) | (BC4_SNORM, BC5_SNORM) => Self::Bc4ToBc5,
+            (BC2_UNORM, BC2_UNORM_PREMULTIPLIED_ALPHA) => Self::Premultiply { bc3: false },
+            (BC3_UNORM, BC3_UNORM_PREMULTIPLIED_ALPHA) => Self::Premultiply { bc3: true },
+            (BC2_UNORM_PREMULTIPLIED_ALPHA, BC2_UNORM) => Self::Unpremultiply { bc3: false },
+            (BC3_UNORM_PREMULTIPLIED_ALPHA, BC3_UNORM) => Self::Unpremultiply { bc3: true },
+            (BC3_UNORM, BC3_UNORM_RXGB) => Self::Bc3ToRxgb,
+            (BC3_UNORM_RXGB, BC3_UNORM) => Self::RxgbToBc3,
+            _ => return None,
+        })
+    }
+
+    /// Rewrites all blocks and returns the new data and whether the rewrite
+    /// was exact.
+    fn apply(self, data: &[u8], size: Size) -> (Vec<u8>, bool) {
+        let (input_bytes, output_bytes) = match self {
+            Self::Copy => return (data.to_vec(), true),
+            Self::Bc1ToBc3 | Self::Bc4ToBc5 => (8, 16),
+            _ => (16, 16),
+        };
+
+        let blocks = div_ceil(size.width, 4) as usize * div_ceil(size.height, 4) as usize;
+        debug_assert_eq!(data.len(), blocks * input_bytes);
+
+        let mut output = vec![0_u8; blocks * output_bytes];
+        let mut exact = true;
+        for (input, output) in data
+            .chunks_exact(input_bytes)
+            .zip(output.chunks_exact_mut(output_bytes))
+        {
+            // For BC5, `alpha` is the red and `color` is the green block.
+            let (alpha, color) = output.split_at_mut(8);
+            let alpha: &mut [u8; 8] = alpha.try_into().unwrap();
+            let color: &mut [u8; 8] = color.try_into().unwrap();
+
+            exact &= match self {
+                Self::Copy => unreachable!(),
+                Self::Bc1ToBc3 => bc1_to_bc3(input.try_into().unwrap(), alpha, color),
+                Self::Bc4ToBc5 => {
+                    // 0 is 0.0 for both UNORM and SNORM
+                    alpha.copy_from_slice(input);
+                    *color = [0; 8];
+                    true
+                }
+                Self::Premultiply { bc3 } => {
+                    convert_alpha_mode(input.try_into().unwrap(), bc3, true, alpha, color)
+                }
+                Self::Unpremultiply { bc3 } => {
+                    convert_alpha_mode(input.try_into().unwrap(), bc3, false, alpha, color)
+                }
+                Self::Bc3ToRxgb => bc3_to_rxgb(input.try_into().unwrap(), alpha, color),
+                Self::RxgbToBc3 => rxgb_to_bc3(input.try_into().unwrap(), alpha, color),
+            };
+        }
+
+        (output, exact)
+    }
+}
+
+/// A BC4 block that decodes to 255 for all pixels.
+const OPAQUE_ALPHA_BLOCK: [u8; 8] = [255, 255, 0, 0, 0, 0, 0, 0];
+
+fn bc1_options() -> Bc1Options {
+    // BC2 and BC3 only support the 4-color mode
+    Bc1Options {
+        no_default: true,
+        ..Bc1Options::default()
+    }
+}
+fn bc4_options() -> Bc4Options {
+    Bc4Options {
+        dither: false,
+        snorm: false,
+        brute_force: false,
+        use_inter4: true,
+        use_inter4_heuristic: true,
+        high_quality_quantize: true,
+        fast_iter: false,
+    }
+}
+fn to_f32(pixels: [[u8; 4]; 16]) -> [[f32; 4]; 16] {
+    pixels.map(|p| p.map(|v| v as f32 / 255.0))
+}
+fn is_opaque(pixels: &[[u8; 4]; 16]) -> bool {
+    pixels.iter().all(|p| p[3] == 255)
+}
+
+fn bc1_to_bc3(input: [u8; 8], alpha: &mut [u8; 8], color: &mut [u8; 8]) -> bool {
+    let c0 = u16::from_le_bytes([input[0], input[1]]);
+    let c1 = u16::from_le_bytes([input[2], input[3]]);
+    let indexes = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
+    let used = (0..16).fold(0_u8, |used, i| used | 1 << ((indexes >> (i * 2)) & 3));
+
+    *alpha = OPAQUE_ALPHA_BLOCK;
+    if c0 > c1 {
+        // already in 4-color mode
+        *color = input;
+        return true;
+    }
+
+    // The 3-color mode isn't supported by BC3. Some blocks can still be
+    // represented exactly in 4-color mode.
+    if used & 0b1100 == 0 && c0 < c1 {
+        // swap endpoints and indexes 0 and 1
+        color[0..2].copy_from_slice(&c1.to_le_bytes());
+        color[2..4].copy_from_slice(&c0.to_le_bytes());
+        color[4..8].copy_from_slice(&(indexes ^ 0x5555_5555).to_le_bytes());
+        return true;
+    }
+    if used & 0b1000 == 0 && c0 == c1 {
+        // indexes 0, 1, and 2 all have the same color
+        let (new_c0, new_c1, index) = if c0 > 0 {
+            (c0, 0_u16, 0_u8)
+        } else {
+            (u16::MAX, 0, 1)
+        };
+        color[0..2].copy_from_slice(&new_c0.to_le_bytes());
+        color[2..4].copy_from_slice(&new_c1.to_le_bytes());
+        color[4..8].copy_from_slice(&[index * 0x55; 4]);
+        return true;
+    }
+
+    // re-encode the color block and store the transparency in the alpha block
+    let pixels = bc_blocks::bc1_u8_rgba(input);
+    if !is_opaque(&pixels) {
+        alpha[0] = 255;
+        alpha[1] = 0;
+        // index 0 is 255 and index 1 is 0
+        let alpha_indexes = (0..16).fold(0_u64, |acc, i| {
+            acc | ((pixels[i][3] == 0) as u64) << (i * 3)
+        });
+        alpha[2..8].copy_from_slice(&alpha_indexes.to_le_bytes()[..6]);
+    }
+    *color = compress_bc1_block(to_f32(pixels), bc1_options());
+    false
+}
+
+/// Converts a BC2/BC3 block between straight and premultiplied alpha.
+fn convert_alpha_mode(
+    input: [u8; 16],
+    bc3: bool,
+    premultiply: bool,
+    alpha: &mut [u8; 8],
+    color: &mut [u8; 8],
+) -> bool {
+    alpha.copy_from_slice(&input[..8]);
+    color.copy_from_slice(&input[8..]);
+
+    // decode to straight alpha
+    let mut pixels = match (bc3, premultiply) {
+        (false, true) => bc_blocks::bc2_u8_rgba(input),
+        (false, false) => bc_blocks::bc2_premultiplied_alpha_u8_rgba(input),
+        (true, true) => bc_blocks::bc3_u8_rgba(input),
+        (true, false) => bc_blocks::bc3_premultiplied_alpha_u8_rgba(input),
+    };
+    if is_opaque(&pixels) {
+        // premultiplied and straight alpha are the same
+        return true;
+    }
+
+    if premultiply {
+        for pixel in pixels.iter_mut() {
+            let a = pixel[3] as u16;
+            for c in &mut pixel[..3] {
+                *c = ((*c as u16 * a + 127) / 255) as u8;
+            }
+        }
+    }
+    // the alpha channel is ignored by BC1 with `no_default`
+    *color = compress_bc1_block(to_f32(pixels), bc1_options());
+    false
+}
+
+fn bc3_to_rxgb(input: [u8; 16], alpha: &mut [u8; 8], color: &mut [u8; 8]) -> bool {
+    // G and B are stored in the color block as before
+    color.copy_from_slice(&input[8..]);
+
+    // R is stored in the alpha block
+    let pixels = bc_blocks::bc3_u8_rgba(input);
+    let red = pixels.map(|p| p[0]);
+    *alpha = compress_bc4_block(red.map(|r| r as f32 / 255.0), bc4_options());
+
+    let decoded = bc_blocks::bc4u_gray::<u8>(*alpha);
+    decoded.iter().zip(red).all(|(&[d], r)| d == r)
+}
+
+fn rxgb_to_bc3(input: [u8; 16], alpha: &mut [u8; 8], color: &mut [u8; 8]) -> bool {
+    *alpha = OPAQUE_ALPHA_BLOCK;
+    color.copy_from_slice(&input[8..]);
+
+    let rgb = bc_blocks::bc3_rxgb_u8_rgb(input);
+    let color_block = bc_blocks::bc3_u8_rgb(input);
+    if rgb == color_block {
+        // the color block already contains the correct R channel
+        return true;
+    }
+
+    let pixels = rgb.map(|[r, g, b]| [r, g, b, 255]);
+    *color = compress_bc1_block(to_f32(pixels), bc1_options());
+    false
+}
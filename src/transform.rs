@@ -0,0 +1,280 @@
+use crate::{util::div_ceil, CubeMapFaces, Format, PixelInfo, Size, TransformError};
+
+/// A lossless geometric transformation of a surface.
+///
+/// Rotations are clockwise. [`Transform::Rotate90`] and
+/// [`Transform::Rotate270`] swap the width and height of the surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transform {
+    /// Mirrors the surface horizontally (left becomes right).
+    FlipX,
+    /// Mirrors the surface vertically (top becomes bottom).
+    FlipY,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+impl Transform {
+    /// Returns the size of a surface of the given size after the transform.
+    pub const fn transformed_size(self, size: Size) -> Size {
+        match self {
+            Transform::FlipX | Transform::FlipY | Transform::Rotate180 => size,
+            Transform::Rotate90 | Transform::Rotate270 => Size::new(size.height, size.width),
+        }
+    }
+
+    /// Returns the inverse transform.
+    const fn inverse(self) -> Self {
+        match self {
+            Transform::Rotate90 => Transform::Rotate270,
+            Transform::Rotate270 => Transform::Rotate90,
+            _ => self,
+        }
+    }
+
+    /// Returns the face of the source cube map that becomes the given face of
+    /// the transformed cube map, and the transform to apply to it.
+    ///
+    /// Cube maps are transformed as a whole, so that the faces still join up
+    /// afterward. Flips mirror the cube along the X or Y axis and rotations
+    /// rotate it around the Z axis. This way, the +Z face is transformed like
+    /// a regular texture. Mirroring an axis swaps its two faces, and
+    /// rotations move the 4 faces around the Z axis. Since the -Z face is
+    /// looked at from behind, rotations are reversed for it.
+    pub(crate) fn cube_map_face_source(self, face: CubeMapFaces) -> (CubeMapFaces, Transform) {
+        const PX: CubeMapFaces = CubeMapFaces::POSITIVE_X;
+        const NX: CubeMapFaces = CubeMapFaces::NEGATIVE_X;
+        const PY: CubeMapFaces = CubeMapFaces::POSITIVE_Y;
+        const NY: CubeMapFaces = CubeMapFaces::NEGATIVE_Y;
+
+        let source = match (self, face) {
+            (Transform::FlipX | Transform::Rotate180, PX) => NX,
+            (Transform::FlipX | Transform::Rotate180, NX) => PX,
+            (Transform::FlipY | Transform::Rotate180, PY) => NY,
+            (Transform::FlipY | Transform::Rotate180, NY) => PY,
+            (Transform::Rotate90, PX) => PY,
+            (Transform::Rotate90, NX) => NY,
+            (Transform::Rotate90, PY) => NX,
+            (Transform::Rotate90, NY) => PX,
+            (Transform::Rotate270, PX) => NY,
+            (Transform::Rotate270, NX) => PY,
+            (Transform::Rotate270, PY) => PX,
+            (Transform::Rotate270, NY) => NX,
+            _ => face,
+        };
+        let transform = if face == CubeMapFaces::NEGATIVE_Z {
+            self.inverse()
+        } else {
+            self
+        };
+        (source, transform)
+    }
+
+    /// Returns the source position of the pixel at the given position in the
+    /// transformed surface. `size` is the size of the source surface.
+    fn source(self, size: Size, x: u32, y: u32) -> (u32, u32) {
+        let (w, h) = (size.width, size.height);
+        match self {
+            Transform::FlipX => (w - 1 - x, y),
+            Transform::FlipY => (x, h - 1 - y),
+            Transform::Rotate90 => (y, h - 1 - x),
+            Transform::Rotate180 => (w - 1 - x, h - 1 - y),
+            Transform::Rotate270 => (w - 1 - y, x),
+        }
+    }
+}
+
+/// Losslessly transforms the raw (encoded) data of a single surface.
+///
+/// `data` must contain exactly one surface of the given size and format. The
+/// returned data contains the transformed surface, which has the size
+/// [`Transform::transformed_size`].
+///
+/// Uncompressed formats support all transforms for all sizes. BC1-BC5 are
+/// transformed in the block domain by reordering blocks and permuting the
+/// indexes within each block, so no quality is lost. Since blocks cannot be
+/// split, a transform that mirrors an axis requires the size along that axis
+/// to be a multiple of 4 or at most 4. Otherwise,
+/// [`TransformError::NotBlockAligned`] is returned.
+///
+/// All other formats (sub-sampled, bi-planar, BC6H, BC7, and ASTC) cannot be
+/// transformed losslessly and will return
+/// [`TransformError::UnsupportedFormat`]. BC6H and BC7 partition shapes and
+/// ASTC block modes are generally not symmetric, so these formats would have
+/// to be re-encoded.
+pub fn transform(
+    data: &[u8],
+    size: Size,
+    format: Format,
+    transform: Transform,
+) -> Result<Vec<u8>, TransformError> {
+    let layout = TransformLayout::from_format(format)?;
+
+    let blocks_w = div_ceil(size.width, layout.block_size) as usize;
+    let blocks_h = div_ceil(size.height, layout.block_size) as usize;
+    let expected = blocks_w as u64 * blocks_h as u64 * layout.bytes_per_block as u64;
+    if data.len() as u64 != expected {
+        return Err(TransformError::UnexpectedDataLength {
+            expected,
+            actual: data.len() as u64,
+        });
+    }
+
+    let new_size = transform.transformed_size(size);
+    let new_blocks_w = div_ceil(new_size.width, layout.block_size) as usize;
+    let new_blocks_h = div_ceil(new_size.height, layout.block_size) as usize;
+    let bytes_per_block = layout.bytes_per_block as usize;
+    let mut output = vec![0_u8; data.len()];
+
+    for by in 0..new_blocks_h {
+        for bx in 0..new_blocks_w {
+            let (src_block, permutation) =
+                layout.map_block(size, new_size, transform, bx as u32, by as u32)?;
+            let src_index = src_block.1 as usize * blocks_w + src_block.0 as usize;
+            let dst_index = by * new_blocks_w + bx;
+
+            let src = &data[src_index * bytes_per_block..(src_index + 1) * bytes_per_block];
+            let dst = &mut output[dst_index * bytes_per_block..(dst_index + 1) * bytes_per_block];
+            dst.copy_from_slice(src);
+            for &(offset, kind) in layout.parts {
+                let part = &mut dst[offset..offset + 8];
+                match kind {
+                    BlockPart::Color => permute_indexes::<2, 32>(part, 4, &permutation),
+                    BlockPart::Alpha4 => permute_indexes::<4, 64>(part, 0, &permutation),
+                    BlockPart::Alpha3 => permute_indexes::<3, 48>(part, 2, &permutation),
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// The different kinds of 8-byte parts of BC1-BC5 blocks.
+#[derive(Debug, Clone, Copy)]
+enum BlockPart {
+    /// A BC1 color block with 2-bit indexes starting at byte 4.
+    Color,
+    /// A BC2 alpha block with 4-bit values starting at byte 0.
+    Alpha4,
+    /// A BC4 block with 3-bit indexes starting at byte 2.
+    Alpha3,
+}
+
+struct TransformLayout {
+    block_size: u32,
+    bytes_per_block: u8,
+    /// The byte offset and kind of each 8-byte part of a block.
+    parts: &'static [(usize, BlockPart)],
+}
+impl TransformLayout {
+    fn from_format(format: Format) -> Result<Self, TransformError> {
+        const fn bc(parts: &'static [(usize, BlockPart)]) -> TransformLayout {
+            TransformLayout {
+                block_size: 4,
+                bytes_per_block: parts.len() as u8 * 8,
+                parts,
+            }
+        }
+
+        Ok(match format {
+            Format::BC1_UNORM => bc(&[(0, BlockPart::Color)]),
+            Format::BC2_UNORM | Format::BC2_UNORM_PREMULTIPLIED_ALPHA => {
+                bc(&[(0, BlockPart::Alpha4), (8, BlockPart::Color)])
+            }
+            Format::BC3_UNORM
+            | Format::BC3_UNORM_PREMULTIPLIED_ALPHA
+            | Format::BC3_UNORM_RXGB
+            | Format::BC3_UNORM_NORMAL => bc(&[(0, BlockPart::Alpha3), (8, BlockPart::Color)]),
+            Format::BC4_UNORM | Format::BC4_SNORM => bc(&[(0, BlockPart::Alpha3)]),
+            Format::BC5_UNORM | Format::BC5_SNORM => {
+                bc(&[(0, BlockPart::Alpha3), (8, BlockPart::Alpha3)])
+            }
+            _ => match PixelInfo::from(format) {
+                PixelInfo::Fixed { bytes_per_pixel } => TransformLayout {
+                    block_size: 1,
+                    bytes_per_block: bytes_per_pixel,
+                    parts: &[],
+                },
+                _ => return Err(TransformError::UnsupportedFormat(format)),
+            },
+        })
+    }
+
+    /// Returns the source block of the block at the given position in the
+    /// transformed surface, and the source pixel index (within the source
+    /// block) of each pixel of the transformed block.
+    fn map_block(
+        &self,
+        size: Size,
+        new_size: Size,
+        transform: Transform,
+        bx: u32,
+        by: u32,
+    ) -> Result<((u32, u32), [u8; 16]), TransformError> {
+        let block_size = self.block_size;
+        let mut src_block = None;
+        let mut permutation = [0_u8; 16];
+        let mut is_padding = [true; 16];
+        let mut is_used = [false; 16];
+
+        for y in 0..block_size {
+            for x in 0..block_size {
+                let (px, py) = (bx * block_size + x, by * block_size + y);
+                if px >= new_size.width || py >= new_size.height {
+                    continue;
+                }
+
+                let (sx, sy) = transform.source(size, px, py);
+                let block = (sx / block_size, sy / block_size);
+                if *src_block.get_or_insert(block) != block {
+                    return Err(TransformError::NotBlockAligned);
+                }
+                let dst = (y * block_size + x) as usize;
+                let src = (sy % block_size) * block_size + sx % block_size;
+                permutation[dst] = src as u8;
+                is_padding[dst] = false;
+                is_used[src as usize] = true;
+            }
+        }
+
+        // Pixels outside the surface are padding. Their values are
+        // irrelevant, but we still map them to the unused source pixels in
+        // order. This makes the permutation a bijection, so inverse
+        // transforms restore the original data exactly.
+        let pixels = (block_size * block_size) as usize;
+        let mut unused = (0..pixels as u8).filter(|&i| !is_used[i as usize]);
+        for dst in (0..pixels).filter(|&i| is_padding[i]) {
+            permutation[dst] = unused.next().unwrap_or(0);
+        }
+
+        // only surfaces with a size of 0 have blocks without pixels, but
+        // those surfaces don't have any blocks
+        Ok((src_block.unwrap_or((bx, by)), permutation))
+    }
+}
+
+/// Permutes the `BITS`-bit indexes of a 4x4 block stored as a little-endian
+/// integer of `TOTAL_BITS` bits starting at byte `offset`.
+fn permute_indexes<const BITS: u32, const TOTAL_BITS: u32>(
+    part: &mut [u8],
+    offset: usize,
+    permutation: &[u8; 16],
+) {
+    let bytes = &mut part[offset..offset + TOTAL_BITS as usize / 8];
+    let mut indexes: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        indexes |= (*byte as u64) << (i * 8);
+    }
+
+    let mask = (1_u64 << BITS) - 1;
+    let mut result: u64 = 0;
+    for (dst, &src) in permutation.iter().enumerate() {
+        let index = (indexes >> (src as u32 * BITS)) & mask;
+        result |= index << (dst as u32 * BITS);
+    }
+
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (result >> (i * 8)) as u8;
+    }
+}
@@ -0,0 +1,215 @@
+use std::io::Cursor;
+
+use dds::{header::*, *};
+
+mod util;
+
+fn run(file: &[u8], target: Format, options: &ConvertOptions) -> Result<Vec<u8>, ConvertError> {
+    let mut decoder = Decoder::new(Cursor::new(file)).unwrap();
+    let mut output = Vec::new();
+    convert(&mut decoder, &mut output, target, None, options)?;
+    assert!(decoder.is_done());
+    Ok(output)
+}
+/// Decodes all surfaces as RGBA U8.
+fn decode_all(file: &[u8]) -> Vec<Vec<u8>> {
+    let mut decoder = Decoder::new(Cursor::new(file)).unwrap();
+    let mut surfaces = Vec::new();
+    while let Some(info) = decoder.surface_info() {
+        let size = info.size();
+        let mut image = vec![0_u8; size.pixels() as usize * 4];
+        let view = ImageViewMut::new(&mut image, size, ColorFormat::RGBA_U8).unwrap();
+        decoder.read_surface(view).unwrap();
+        surfaces.push(image);
+    }
+    surfaces
+}
+
+#[test]
+fn convert_preserves_layout() {
+    let headers = [
+        Header::new_image(13, 7, Format::R8G8B8A8_UNORM).with_mipmaps(),
+        Header::new_cube_map(8, 8, Format::R8G8B8A8_UNORM).with_mipmaps(),
+        Header::new_volume(8, 4, 5, Format::R8G8B8A8_UNORM).with_mipmaps(),
+        Header::Dx10(
+            Header::new_image(16, 16, Format::R8G8B8A8_UNORM)
+                .with_mipmaps()
+                .to_dx10()
+                .unwrap()
+                .with_array_size(3),
+        ),
+        Header::Dx9(
+            Header::new_cube_map(4, 4, Format::R8G8B8A8_UNORM)
+                .to_dx9()
+                .unwrap()
+                .with_cube_map_faces(CubeMapFaces::POSITIVE_X | CubeMapFaces::NEGATIVE_Z),
+        ),
+    ];
+
+    for header in headers {
+        let file = util::random_file(header.clone());
+        let output = run(&file, Format::B8G8R8A8_UNORM, &ConvertOptions::default()).unwrap();
+
+        let decoder = Decoder::new(Cursor::new(&output)).unwrap();
+        assert_eq!(decoder.format(), Format::B8G8R8A8_UNORM);
+        assert_eq!(
+            decoder.layout(),
+            Decoder::new(Cursor::new(&file)).unwrap().layout()
+        );
+        assert_eq!(
+            matches!(decoder.header(), Header::Dx10(_)),
+            matches!(header, Header::Dx10(_))
+        );
+
+        // the conversion is lossless
+        assert!(decode_all(&output) == decode_all(&file), "{:?}", header);
+    }
+}
+
+#[test]
+fn convert_header_version() {
+    let file = util::random_file(Header::new_image(8, 8, Format::R8G8B8A8_UNORM));
+
+    let options = ConvertOptions {
+        header: HeaderVersion::Dx9,
+        ..Default::default()
+    };
+    let output = run(&file, Format::BC1_UNORM, &options).unwrap();
+    let decoder = Decoder::new(Cursor::new(&output)).unwrap();
+    assert!(matches!(decoder.header(), Header::Dx9(_)));
+    assert_eq!(decoder.format(), Format::BC1_UNORM);
+
+    // formats without DXGI equivalent fall back to DX9
+    let output = run(&file, Format::BC3_UNORM_RXGB, &ConvertOptions::default()).unwrap();
+    let decoder = Decoder::new(Cursor::new(&output)).unwrap();
+    assert!(matches!(decoder.header(), Header::Dx9(_)));
+    assert_eq!(decoder.format(), Format::BC3_UNORM_RXGB);
+
+    // DX9 doesn't support texture arrays
+    let header = Header::new_image(8, 8, Format::R8G8B8A8_UNORM)
+        .to_dx10()
+        .unwrap()
+        .with_array_size(2);
+    let file = util::random_file(Header::Dx10(header));
+    assert!(matches!(
+        run(&file, Format::BC1_UNORM, &options),
+        Err(ConvertError::UnsupportedHeader(Format::BC1_UNORM))
+    ));
+}
+
+#[test]
+fn convert_mipmaps() {
+    let file = util::random_file(Header::new_cube_map(16, 16, Format::R8G8B8A8_UNORM));
+
+    let options = ConvertOptions {
+        mipmaps: MipmapMode::Full,
+        ..Default::default()
+    };
+    let output = run(&file, Format::R8G8B8A8_UNORM, &options).unwrap();
+    let decoder = Decoder::new(Cursor::new(&output)).unwrap();
+    assert_eq!(decoder.header().mipmap_count().get(), 5);
+    let surfaces = decode_all(&output);
+    let original = decode_all(&file);
+    assert_eq!(surfaces.len(), 6 * 5);
+    for face in 0..6 {
+        assert!(surfaces[face * 5] == original[face]);
+    }
+
+    // regenerating mipmaps of a file without mipmaps changes nothing
+    let options = ConvertOptions {
+        mipmaps: MipmapMode::Regenerate,
+        ..Default::default()
+    };
+    let output = run(&file, Format::R8G8B8A8_UNORM, &options).unwrap();
+    assert!(output == file);
+}
+
+#[test]
+fn convert_progress() {
+    let file =
+        util::random_file(Header::new_cube_map(16, 16, Format::R8G8B8A8_UNORM).with_mipmaps());
+    for mipmaps in [MipmapMode::Convert, MipmapMode::Regenerate] {
+        let options = ConvertOptions {
+            mipmaps,
+            ..Default::default()
+        };
+
+        let mut reports = Vec::new();
+        let mut report = |p: f32| reports.push(p);
+        let mut progress = Progress::new(&mut report);
+        let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+        let mut output = Vec::new();
+        convert(
+            &mut decoder,
+            &mut output,
+            Format::BC1_UNORM,
+            Some(&mut progress),
+            &options,
+        )
+        .unwrap();
+
+        assert!(reports.windows(2).all(|w| w[0] <= w[1]), "{:?}", reports);
+        assert_eq!(reports.last(), Some(&1.0));
+        // progress is reported across the whole file
+        assert!(reports.iter().any(|&p| p > 0.1 && p < 0.9));
+    }
+}
+
+#[test]
+fn convert_memory_limit() {
+    let file = util::random_file(Header::new_image(16, 8, Format::R8G8B8A8_UNORM));
+    let convert_with_limit = |memory_limit: usize| {
+        let mut decoder = Decoder::new(Cursor::new(&file)).unwrap();
+        decoder.options.memory_limit = memory_limit;
+        let mut output = Vec::new();
+        convert(
+            &mut decoder,
+            &mut output,
+            Format::BC1_UNORM,
+            None,
+            &ConvertOptions::default(),
+        )
+    };
+
+    // the decoded surface counts towards the memory limit of the decoder
+    assert!(convert_with_limit(16 * 8 * 4).is_ok());
+    assert!(matches!(
+        convert_with_limit(16 * 8 * 4 - 1),
+        Err(ConvertError::Decoding(DecodingError::MemoryLimitExceeded))
+    ));
+}
+
+#[test]
+fn convert_pitch_or_linear_size() {
+    let read_raw = |file: &[u8]| RawHeader::read(&mut &file[4..]).unwrap();
+
+    // DX9 headers get the pitch or linear size of the new format
+    let header = Header::Dx9(
+        Header::new_image(32, 16, Format::R8G8B8A8_UNORM)
+            .to_dx9()
+            .unwrap(),
+    );
+    let file = util::random_file(header);
+    let raw = read_raw(&file);
+    assert!(raw.flags.contains(DdsFlags::PITCH));
+    assert_eq!(raw.pitch_or_linear_size, 32 * 4);
+
+    let options = ConvertOptions {
+        header: HeaderVersion::Dx9,
+        ..Default::default()
+    };
+    let output = run(&file, Format::BC1_UNORM, &options).unwrap();
+    let raw = read_raw(&output);
+    assert!(matches!(
+        Decoder::new(Cursor::new(&output)).unwrap().header(),
+        Header::Dx9(_)
+    ));
+    assert!(raw.flags.contains(DdsFlags::LINEAR_SIZE));
+    assert!(!raw.flags.contains(DdsFlags::PITCH));
+    assert_eq!(raw.pitch_or_linear_size, 8 * 4 * 8);
+
+    let output = run(&output, Format::B5G6R5_UNORM, &options).unwrap();
+    let raw = read_raw(&output);
+    assert!(raw.flags.contains(DdsFlags::PITCH));
+    assert_eq!(raw.pitch_or_linear_size, 32 * 2);
+}
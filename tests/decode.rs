@@ -383,6 +383,247 @@ fn test_unaligned() {
     }
 }
 
+#[test]
+fn read_volume_box() {
+    let size = Size::new(13, 10);
+    let depth = 7;
+    let format = Format::R8G8B8A8_UNORM;
+    let color = ColorFormat::RGBA_U8;
+
+    let header = Header::new_volume(size.width, size.height, depth, format).with_mipmaps();
+    let layout = DataLayout::from_header(&header).unwrap();
+    let mut data = vec![0_u8; layout.data_len() as usize];
+    util::create_rng().fill_bytes(&mut data);
+
+    // decode all depth slices of all levels as reference
+    let mut decoder = Decoder::from_header(Cursor::new(data.as_slice()), header.clone()).unwrap();
+    let mut levels = Vec::new();
+    for mip in layout.volume().unwrap().iter_mips() {
+        let mut slices = Vec::new();
+        for _ in 0..mip.depth() {
+            let mut image = util::Image::<u8>::new_empty(Channels::Rgba, mip.size());
+            decoder.read_surface(image.view_mut()).unwrap();
+            slices.push(image);
+        }
+        levels.push(slices);
+    }
+
+    let mut decoder = Decoder::from_header(Cursor::new(data.as_slice()), header).unwrap();
+    let mut test = |level: u8, b: Box3| {
+        let row_pitch = b.width as usize * 4 + 3;
+        let slice_pitch = row_pitch * b.height as usize + 5;
+        let mut buffer = vec![0_u8; slice_pitch * b.depth as usize];
+        decoder
+            .read_volume_box(level, &mut buffer, row_pitch, slice_pitch, b, color)
+            .unwrap();
+
+        for z in 0..b.depth {
+            let slice = &levels[level as usize][(b.z + z) as usize];
+            for y in 0..b.height {
+                let start = (b.y + y) as usize * slice.stride() + b.x as usize * 4;
+                let expected = &slice.data[start..start + b.width as usize * 4];
+                let offset = z as usize * slice_pitch + y as usize * row_pitch;
+                let actual = &buffer[offset..offset + b.width as usize * 4];
+                assert_eq!(expected, actual, "level {level}, box {b:?}, z {z}, y {y}");
+            }
+        }
+
+        // the decoder is positioned at the start of the next level
+        match decoder.surface_info() {
+            Some(info) => assert_eq!(info.size(), size.get_mipmap(level + 1)),
+            None => assert_eq!(level + 1, layout.volume().unwrap().mipmaps()),
+        }
+    };
+
+    test(0, Box3::new(0, 0, 0, 13, 10, 7));
+    test(1, Box3::new(1, 2, 1, 4, 3, 2));
+    // seeking backwards is allowed too
+    test(0, Box3::new(5, 3, 2, 7, 6, 4));
+    test(0, Box3::new(12, 9, 6, 1, 1, 1));
+    test(2, Box3::new(0, 0, 0, 3, 2, 1));
+    test(3, Box3::new(0, 0, 0, 1, 1, 1));
+}
+
+#[test]
+fn read_surface_rows() {
+    fn test(format: Format, size: Size, rows_per_chunk: u32) {
+        let color = ColorFormat::RGBA_U8;
+        let header = Header::new_image(size.width, size.height, format);
+        let layout = DataLayout::from_header(&header).unwrap();
+        let mut data = vec![0_u8; layout.data_len() as usize];
+        util::create_rng().fill_bytes(&mut data);
+
+        let mut decoder =
+            Decoder::from_header(Cursor::new(data.as_slice()), header.clone()).unwrap();
+        let mut expected = util::Image::<u8>::new_empty(Channels::Rgba, size);
+        decoder.read_surface(expected.view_mut()).unwrap();
+
+        let mut decoder = Decoder::from_header(Cursor::new(data.as_slice()), header).unwrap();
+        let mut actual = util::Image::<u8>::new_empty(Channels::Rgba, size);
+        let mut next_y = 0;
+        decoder
+            .read_surface_rows(color, rows_per_chunk, |y, rows| {
+                assert_eq!(y, next_y);
+                assert_eq!(rows.width(), size.width);
+                next_y += rows.height();
+
+                let start = y as usize * actual.stride();
+                actual.data[start..start + rows.data().len()].copy_from_slice(rows.data());
+            })
+            .unwrap();
+        assert_eq!(next_y, size.height);
+        assert!(decoder.is_done());
+
+        assert!(
+            expected.data == actual.data,
+            "Failed for {:?} with {} rows per chunk",
+            format,
+            rows_per_chunk
+        );
+    }
+
+    for format in [
+        Format::R8G8B8A8_UNORM,
+        Format::R1_UNORM,
+        Format::YUY2,
+        Format::NV12,
+        Format::BC1_UNORM,
+        Format::BC7_UNORM,
+    ] {
+        test(format, Size::new(37, 29), 0);
+        test(format, Size::new(37, 29), 1);
+        test(format, Size::new(37, 29), 5);
+        test(format, Size::new(37, 29), 100);
+    }
+}
+
+#[test]
+fn read_surface_rows_memory_limit() {
+    let size = Size::new(256, 256);
+    let header = Header::new_image(size.width, size.height, Format::NV12);
+    let len = DataLayout::from_header(&header).unwrap().data_len();
+    let data = vec![0_u8; len as usize];
+    let mut decoder = Decoder::from_header(Cursor::new(data), header).unwrap();
+    decoder.options.memory_limit = 16 * 1024;
+
+    // the whole Y plane doesn't fit into the memory limit
+    let mut image = util::Image::<u8>::new_empty(Channels::Rgb, size);
+    let result = decoder.read_surface(image.view_mut());
+    assert!(matches!(result, Err(DecodingError::MemoryLimitExceeded)));
+    decoder.rewind_to_start().unwrap();
+
+    // but a few rows at a time do
+    let mut rows_read = 0;
+    decoder
+        .read_surface_rows(ColorFormat::RGB_U8, 8, |_, rows| rows_read += rows.height())
+        .unwrap();
+    assert_eq!(rows_read, size.height);
+
+    // and the strip buffer itself counts towards the limit
+    decoder.rewind_to_start().unwrap();
+    let result = decoder.read_surface_rows(ColorFormat::RGB_U8, 64, |_, _| {});
+    assert!(matches!(result, Err(DecodingError::MemoryLimitExceeded)));
+}
+
+#[test]
+fn read_thumbnail() {
+    fn random_decoder(header: Header) -> Decoder<Cursor<Vec<u8>>> {
+        let layout = DataLayout::from_header(&header).unwrap();
+        let mut data = vec![0_u8; layout.data_len() as usize];
+        util::create_rng().fill_bytes(&mut data);
+        Decoder::from_header(Cursor::new(data), header).unwrap()
+    }
+
+    // textures
+    let mut decoder =
+        random_decoder(Header::new_image(64, 32, Format::R8G8B8A8_UNORM).with_mipmaps());
+    assert_eq!(decoder.thumbnail_size(256), Size::new(64, 32));
+    assert_eq!(decoder.thumbnail_size(16), Size::new(16, 8));
+    assert_eq!(decoder.thumbnail_size(1), Size::new(1, 1));
+
+    // if a mipmap has the exact size, it's used as is
+    let mut level_2 = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(16, 8));
+    decoder.skip_surface().unwrap();
+    decoder.skip_surface().unwrap();
+    decoder.read_surface(level_2.view_mut()).unwrap();
+    decoder.rewind_to_start().unwrap();
+
+    let mut thumbnail = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(16, 8));
+    decoder
+        .read_thumbnail_into(thumbnail.view_mut(), ResizeFilter::Box)
+        .unwrap();
+    assert!(thumbnail.data == level_2.data);
+    // the decoder is left at the start
+    assert_eq!(decoder.surface_info().unwrap().size(), Size::new(64, 32));
+
+    // the requested size and color
+    let (data, size) = decoder.read_thumbnail(16, ColorFormat::RGBA_U8).unwrap();
+    assert_eq!(size, Size::new(16, 8));
+    assert!(data == level_2.data);
+
+    // the position of the decoder is kept
+    let mut level_1 = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(32, 16));
+    decoder.skip_surface().unwrap();
+    decoder.read_thumbnail(16, ColorFormat::RGB_U8).unwrap();
+    assert_eq!(decoder.surface_info().unwrap().size(), Size::new(32, 16));
+    decoder.read_surface(level_1.view_mut()).unwrap();
+    decoder.read_thumbnail(16, ColorFormat::RGB_U8).unwrap();
+    assert_eq!(decoder.surface_info().unwrap().size(), Size::new(16, 8));
+    decoder.rewind_to_start().unwrap();
+
+    // other sizes are resized
+    let mut thumbnail = util::Image::<u8>::new_empty(Channels::Rgb, Size::new(20, 7));
+    decoder
+        .read_thumbnail_into(thumbnail.view_mut(), ResizeFilter::Triangle)
+        .unwrap();
+
+    // the resize buffers count towards the memory limit
+    decoder.options.memory_limit = 32 * 16 * 3;
+    let result = decoder.read_thumbnail_into(thumbnail.view_mut(), ResizeFilter::Triangle);
+    assert!(matches!(result, Err(DecodingError::MemoryLimitExceeded)));
+    decoder.options.memory_limit = 32 * 16 * 3 + 20 * 7 * 3 + 20 * 16 * 16 + 32 * 16 * 3;
+    decoder
+        .read_thumbnail_into(thumbnail.view_mut(), ResizeFilter::Triangle)
+        .unwrap();
+
+    // cube maps
+    let mut decoder =
+        random_decoder(Header::new_cube_map(8, 8, Format::R8G8B8A8_UNORM).with_mipmaps());
+    assert_eq!(decoder.thumbnail_size(1000), Size::new(32, 24));
+    assert_eq!(decoder.thumbnail_size(16), Size::new(16, 12));
+
+    let mut cube_map = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(32, 24));
+    decoder.read_cube_map(cube_map.view_mut()).unwrap();
+    decoder.rewind_to_start().unwrap();
+
+    let mut thumbnail = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(32, 24));
+    decoder
+        .read_thumbnail_into(thumbnail.view_mut(), ResizeFilter::Box)
+        .unwrap();
+    assert!(thumbnail.data == cube_map.data);
+
+    let mut thumbnail = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(16, 12));
+    decoder
+        .read_thumbnail_into(thumbnail.view_mut(), ResizeFilter::Box)
+        .unwrap();
+
+    // volumes
+    let mut decoder =
+        random_decoder(Header::new_volume(16, 16, 4, Format::R8G8B8A8_UNORM).with_mipmaps());
+    let mut level_1 = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(8, 8));
+    for _ in 0..4 {
+        decoder.skip_surface().unwrap();
+    }
+    decoder.read_surface(level_1.view_mut()).unwrap();
+    decoder.rewind_to_start().unwrap();
+
+    let mut thumbnail = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(8, 8));
+    decoder
+        .read_thumbnail_into(thumbnail.view_mut(), ResizeFilter::Box)
+        .unwrap();
+    assert!(thumbnail.data == level_1.data);
+}
+
 mod errors {
     use super::*;
 
@@ -482,6 +723,87 @@ mod errors {
         assert_eq!(err.to_string(), "Memory limit exceeded");
     }
 
+    #[test]
+    fn volume_box() {
+        let mut buffer = vec![0_u8; 16 * 16 * 16 * 4];
+        let color = ColorFormat::RGBA_U8;
+
+        let mut decoder = new_decoder_32x32();
+        let result =
+            decoder.read_volume_box(0, &mut buffer, 64, 1024, Box3::new(0, 0, 0, 1, 1, 1), color);
+        assert!(matches!(result, Err(DecodingError::NotAVolume)));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The DDS file is not a volume"
+        );
+
+        let mut decoder = new_decoder_16x16x16();
+        let result =
+            decoder.read_volume_box(1, &mut buffer, 64, 1024, Box3::new(0, 0, 0, 1, 1, 1), color);
+        assert!(matches!(
+            result,
+            Err(DecodingError::MipmapLevelOutOfBounds(1))
+        ));
+
+        let result = decoder.read_volume_box(
+            0,
+            &mut buffer,
+            64,
+            1024,
+            Box3::new(0, 0, 10, 1, 1, 7),
+            color,
+        );
+        assert!(matches!(result, Err(DecodingError::BoxOutOfBounds)));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Box is out of bounds of the volume size"
+        );
+
+        let result = decoder.read_volume_box(
+            0,
+            &mut buffer,
+            64,
+            1000,
+            Box3::new(0, 0, 0, 16, 16, 2),
+            color,
+        );
+        assert!(matches!(
+            result,
+            Err(DecodingError::SlicePitchTooSmall {
+                required_minimum: 1024
+            })
+        ));
+
+        let result = decoder.read_volume_box(
+            0,
+            &mut buffer[..2000],
+            64,
+            1024,
+            Box3::new(0, 0, 0, 16, 16, 2),
+            color,
+        );
+        assert!(matches!(
+            result,
+            Err(DecodingError::RectBufferTooSmall {
+                required_minimum: 2048
+            })
+        ));
+
+        // none of the errors moved the decoder
+        assert!(decoder.surface_info().is_some());
+        decoder
+            .read_volume_box(
+                0,
+                &mut buffer,
+                64,
+                1024,
+                Box3::new(0, 0, 0, 16, 16, 16),
+                color,
+            )
+            .unwrap();
+        assert!(decoder.is_done());
+    }
+
     #[test]
     fn rect_out_of_bounds() {
         // decodes a dummy 2x3 image to GRAY U8
@@ -517,3 +839,128 @@ mod errors {
         assert!(matches!(result, Ok(())));
     }
 }
+
+#[test]
+fn decode_tone_mapping() {
+    let size = Size::new(6, 1);
+    let values = [0.0_f32, 0.5, 1.0, 3.0, 8.0, -1.0];
+    let encoded: Vec<u8> = values
+        .iter()
+        .flat_map(|&v| [v, v, v, 0.7])
+        .flat_map(f32::to_le_bytes)
+        .collect();
+    let format = Format::R32G32B32A32_FLOAT;
+
+    let decode_u8 = |options: &DecodeOptions| {
+        let mut output = vec![0_u8; size.pixels() as usize * 4];
+        let view = ImageViewMut::new(&mut output, size, ColorFormat::RGBA_U8).unwrap();
+        decode(&mut encoded.as_slice(), view, format, options).unwrap();
+        output
+    };
+    let red = |output: &[u8]| output.chunks_exact(4).map(|p| p[0]).collect::<Vec<_>>();
+
+    // no tone mapping clamps
+    let mut options = DecodeOptions::default();
+    let clamped = decode_u8(&options);
+    assert_eq!(red(&clamped), [0, 128, 255, 255, 255, 0]);
+
+    options.tone_mapping = Some(ToneMapping::new(ToneMapOperator::Clamp));
+    assert_eq!(decode_u8(&options), clamped);
+
+    // Reinhard with and without exposure and sRGB
+    let mut tone_mapping = ToneMapping::new(ToneMapOperator::Reinhard);
+    options.tone_mapping = Some(tone_mapping);
+    let reinhard = decode_u8(&options);
+    assert_eq!(red(&reinhard), [0, 85, 128, 191, 227, 0]);
+    // alpha is unchanged
+    assert!(reinhard.chunks_exact(4).all(|p| p[3] == 179));
+
+    tone_mapping.exposure = 1.0;
+    options.tone_mapping = Some(tone_mapping);
+    assert_eq!(red(&decode_u8(&options)), [0, 128, 170, 219, 240, 0]);
+
+    tone_mapping.exposure = 0.0;
+    tone_mapping.srgb = true;
+    options.tone_mapping = Some(tone_mapping);
+    assert_eq!(red(&decode_u8(&options))[2], 188);
+
+    // ACES is monotonic and maps bright values to white
+    options.tone_mapping = Some(ToneMapping::new(ToneMapOperator::AcesFilmic));
+    let aces = red(&decode_u8(&options));
+    assert!(aces.windows(2).take(4).all(|w| w[0] < w[1]));
+    assert_eq!(aces[4], 255);
+    assert_eq!(aces[5], 0);
+
+    // F32 outputs are never tone mapped
+    let mut output = vec![0_f32; size.pixels() as usize * 4];
+    let view =
+        ImageViewMut::new(util::as_bytes_mut(&mut output), size, ColorFormat::RGBA_F32).unwrap();
+    decode(&mut encoded.as_slice(), view, format, &options).unwrap();
+    assert_eq!(output[12], 3.0);
+
+    // rectangles with other channels and precisions
+    let mut full = vec![0_u16; size.pixels() as usize];
+    let view = ImageViewMut::new(
+        util::as_bytes_mut(&mut full),
+        size,
+        ColorFormat::GRAYSCALE_U16,
+    )
+    .unwrap();
+    decode(&mut encoded.as_slice(), view, format, &options).unwrap();
+    assert!(full[3] > 0 && full[3] < u16::MAX);
+
+    let mut rect = vec![0_u16; 3];
+    dds::decode_rect(
+        &mut Cursor::new(&encoded),
+        util::as_bytes_mut(&mut rect),
+        6,
+        ColorFormat::GRAYSCALE_U16,
+        size,
+        Rect::new(2, 0, 3, 1),
+        format,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(rect, full[2..5]);
+}
+
+#[test]
+fn decode_tone_mapping_large() {
+    let mut options = DecodeOptions::default();
+    options.tone_mapping = Some(ToneMapping::new(ToneMapOperator::Reinhard));
+
+    // a 2048x2048 HDR face needs 64 MiB in F32, which is more than the
+    // default memory limit, so it must be decoded in strips
+    let cases = [
+        (Format::R16G16B16A16_FLOAT, Size::new(2048, 2048)),
+        (Format::BC6H_UF16, Size::new(1024, 518)),
+    ];
+    for (format, size) in cases {
+        let pixel_info = PixelInfo::from(format);
+        let mut encoded = vec![0_u8; pixel_info.surface_bytes(size).unwrap() as usize];
+        util::create_rng().fill_bytes(&mut encoded);
+
+        let mut output = vec![0_u8; size.pixels() as usize * 4];
+        let view = ImageViewMut::new(&mut output, size, ColorFormat::RGBA_U8).unwrap();
+        decode(&mut encoded.as_slice(), view, format, &options).unwrap();
+
+        // decode one row of blocks at a time as reference
+        let rows = match pixel_info {
+            PixelInfo::Block(info) => info.size().1 as u32,
+            _ => 1,
+        };
+        let mut reader = encoded.as_slice();
+        let row_bytes = size.width as usize * 4;
+        for (y, expected) in (0..size.height)
+            .step_by(rows as usize)
+            .zip(output.chunks(rows as usize * row_bytes))
+        {
+            let strip = Size::new(size.width, rows.min(size.height - y));
+            let mut actual = vec![0_u8; strip.pixels() as usize * 4];
+            let view = ImageViewMut::new(&mut actual, strip, ColorFormat::RGBA_U8).unwrap();
+            decode(&mut reader, view, format, &options).unwrap();
+            assert!(actual == expected, "{format:?} differs at y={y}");
+        }
+        assert!(reader.is_empty());
+    }
+}
@@ -189,6 +189,143 @@ fn encode_dither() {
     summaries.snapshot_or_fail()
 }
 
+#[test]
+fn encode_dither_algorithms() {
+    let algorithms = [
+        DitheringAlgorithm::FloydSteinberg,
+        DitheringAlgorithm::Atkinson,
+        DitheringAlgorithm::Sierra,
+        DitheringAlgorithm::Ordered,
+        DitheringAlgorithm::BlueNoise,
+    ];
+
+    // A flat color between 2 representable values. Dithering should preserve
+    // the average color.
+    let size = Size::new(64, 64);
+    let value = 10.3 / 31.0;
+    let flat = vec![value; size.pixels() as usize * 3];
+    let flat = ImageView::new(util::as_bytes(&flat), size, ColorFormat::RGB_F32).unwrap();
+    let decode_mean_red = |encoded: &[u8]| {
+        let mut decoded = vec![0_f32; size.pixels() as usize * 3];
+        let view = ImageViewMut::new(util::as_bytes_mut(&mut decoded), size, ColorFormat::RGB_F32)
+            .unwrap();
+        decode(
+            &mut &encoded[..],
+            view,
+            Format::B5G6R5_UNORM,
+            &DecodeOptions::default(),
+        )
+        .unwrap();
+        decoded.iter().step_by(3).sum::<f32>() / size.pixels() as f32
+    };
+
+    for algorithm in algorithms {
+        let mut options = EncodeOptions::default();
+        options.dithering = Dithering::Color;
+        options.dithering_algorithm = algorithm;
+
+        let mut encoded = Vec::new();
+        encode(&mut encoded, flat, Format::B5G6R5_UNORM, None, &options).unwrap();
+        let mean = decode_mean_red(&encoded);
+        assert!(
+            (mean - value).abs() < 0.1 / 31.0,
+            "{:?}: {} vs {}",
+            algorithm,
+            mean * 31.0,
+            value * 31.0
+        );
+    }
+
+    // Position-based algorithms allow splitting without seams
+    // a gradient large enough to be split
+    let size = Size::new(256, 1024);
+    let gradient: Vec<f32> = (0..size.height)
+        .flat_map(|y| (0..size.width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let x = x as f32 / size.width as f32;
+            let y = y as f32 / size.height as f32;
+            [x, y, 1.0 - x * y]
+        })
+        .collect();
+    let view = ImageView::new(util::as_bytes(&gradient), size, ColorFormat::RGB_F32).unwrap();
+    for algorithm in algorithms {
+        let mut options = EncodeOptions::default();
+        options.dithering = Dithering::Color;
+        options.dithering_algorithm = algorithm;
+
+        let split = SplitSurface::new(view, Format::B5G6R5_UNORM, &options);
+        assert_eq!(
+            split.single().is_none(),
+            algorithm.is_position_based(),
+            "{:?}",
+            algorithm
+        );
+        if !algorithm.is_position_based() {
+            continue;
+        }
+
+        options.parallel = false;
+        let mut expected = Vec::new();
+        encode(&mut expected, view, Format::B5G6R5_UNORM, None, &options).unwrap();
+
+        let mut actual = Vec::new();
+        for fragment in split.fragments() {
+            encode(&mut actual, *fragment, Format::B5G6R5_UNORM, None, &options).unwrap();
+        }
+        assert!(actual == expected, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn encode_dither_lossy_formats() {
+    // A flat gray between 2 representable values of each format. Dithering
+    // should preserve the average color.
+    let size = Size::new(64, 64);
+    let cases = [
+        (Format::R8_UNORM, 100.3 / 255.0, 1.0 / 255.0),
+        (Format::R8G8B8A8_UNORM, 100.3 / 255.0, 1.0 / 255.0),
+        (Format::R16_UNORM, 1000.3 / 65535.0, 1.0 / 65535.0),
+        (Format::R8G8_B8G8_UNORM, 100.3 / 255.0, 1.0 / 255.0),
+        (Format::AYUV, 0.4, 1.164 / 255.0),
+        (Format::YUY2, 0.4, 1.164 / 255.0),
+        (Format::R1_UNORM, 0.3, 1.0),
+    ];
+    let algorithms = [
+        DitheringAlgorithm::FloydSteinberg,
+        DitheringAlgorithm::BlueNoise,
+    ];
+
+    for (format, value, step) in cases {
+        let flat = vec![value; size.pixels() as usize * 3];
+        let flat = ImageView::new(util::as_bytes(&flat), size, ColorFormat::RGB_F32).unwrap();
+
+        for algorithm in algorithms {
+            let mut options = EncodeOptions::default();
+            options.dithering = Dithering::Color;
+            options.dithering_algorithm = algorithm;
+
+            let mut encoded = Vec::new();
+            encode(&mut encoded, flat, format, None, &options).unwrap();
+
+            let mut decoded = vec![0_f32; size.pixels() as usize * 3];
+            let view =
+                ImageViewMut::new(util::as_bytes_mut(&mut decoded), size, ColorFormat::RGB_F32)
+                    .unwrap();
+            decode(&mut &encoded[..], view, format, &DecodeOptions::default()).unwrap();
+            let mean = decoded.iter().step_by(3).sum::<f32>() / size.pixels() as f32;
+
+            assert!(
+                (mean - value).abs() < 0.1 * step,
+                "{:?} {:?}: {} vs {}",
+                format,
+                algorithm,
+                mean / step,
+                value / step
+            );
+        }
+    }
+}
+
 // Don't run this on big endian targets, it's just too slow
 #[cfg(not(target_endian = "big"))]
 // Don't run when doing code coverage, it's just too slow
@@ -796,6 +933,944 @@ fn test_unaligned() {
     }
 }
 
+#[test]
+fn encode_rows() {
+    let size = Size::new(18, 22);
+    let mut image = vec![0_u8; size.pixels() as usize * 4];
+    util::create_rng().fill_bytes(&mut image);
+    let view = ImageView::new(&image, size, ColorFormat::RGBA_U8).unwrap();
+    let row_pitch = view.row_pitch();
+
+    for format in [
+        Format::R8G8B8A8_UNORM,
+        Format::B5G6R5_UNORM,
+        Format::BC1_UNORM,
+        Format::BC4_UNORM,
+        Format::YUY2,
+    ] {
+        let header = Header::new_image(size.width, size.height, format);
+
+        let mut expected = Vec::new();
+        let mut encoder = Encoder::new(&mut expected, format, &header).unwrap();
+        encoder.write_surface(view).unwrap();
+        encoder.finish().unwrap();
+
+        // write rows in chunks of varying size
+        for chunk_sizes in [[1, 1, 1], [3, 2, 1], [5, 7, 2], [22, 0, 0]] {
+            let mut actual = Vec::new();
+            let mut encoder = Encoder::new(&mut actual, format, &header).unwrap();
+            let mut writer = encoder.begin_surface(ColorFormat::RGBA_U8).unwrap();
+            let mut y = 0;
+            for &rows in chunk_sizes.iter().cycle() {
+                let rows = rows.min(size.height as usize - y);
+                writer
+                    .write_rows(&image[y * row_pitch..(y + rows) * row_pitch])
+                    .unwrap();
+                y += rows;
+                if y == size.height as usize {
+                    break;
+                }
+            }
+            assert_eq!(writer.rows_written(), size.height);
+            writer.finish().unwrap();
+            encoder.finish().unwrap();
+
+            assert!(
+                actual == expected,
+                "{:?} with chunks {:?}",
+                format,
+                chunk_sizes
+            );
+        }
+    }
+
+    // bi-planar formats would have to buffer their entire second plane
+    for format in [Format::NV12, Format::P016] {
+        let header = Header::new_image(size.width, size.height, format);
+        let mut encoder = Encoder::new(std::io::sink(), format, &header).unwrap();
+        let result = encoder.begin_surface(ColorFormat::RGBA_U8);
+        assert!(matches!(result, Err(EncodingError::UnsupportedFormat(f)) if f == format));
+        // the encoder is not poisoned
+        encoder.write_surface(view).unwrap();
+        encoder.finish().unwrap();
+    }
+}
+
+#[test]
+fn encode_surfaces() {
+    let format = Format::BC1_UNORM;
+    let size = Size::new(20, 12);
+    let header = Header::new_image(size.width, size.height, format).with_mipmaps();
+    let header = Header::Dx10(header.to_dx10().unwrap().with_array_size(9));
+
+    let mut rng = util::create_rng();
+    let images: Vec<Vec<u8>> = (0..9)
+        .map(|_| {
+            let mut data = vec![0_u8; size.pixels() as usize * 4];
+            rng.fill_bytes(&mut data);
+            data
+        })
+        .collect();
+    let views: Vec<ImageView> = images
+        .iter()
+        .map(|data| ImageView::new(data, size, ColorFormat::RGBA_U8).unwrap())
+        .collect();
+    let write_options = WriteOptions {
+        generate_mipmaps: true,
+        ..Default::default()
+    };
+
+    let mut expected = Vec::new();
+    let mut encoder = Encoder::new(&mut expected, format, &header).unwrap();
+    for view in &views {
+        encoder
+            .write_surface_with(*view, None, &write_options)
+            .unwrap();
+    }
+    encoder.finish().unwrap();
+
+    for parallel in [false, true] {
+        let mut actual = Vec::new();
+        let mut encoder = Encoder::new(&mut actual, format, &header).unwrap();
+        encoder.options.parallel = parallel;
+
+        let mut last_progress = 0.0;
+        let mut report = |progress: f32| {
+            assert!(progress >= last_progress);
+            last_progress = progress;
+        };
+        let mut progress = Progress::new(&mut report);
+
+        // write the first element separately
+        encoder
+            .write_surface_with(views[0], None, &write_options)
+            .unwrap();
+        encoder
+            .write_surfaces(&views[1..], Some(&mut progress), &write_options)
+            .unwrap();
+        encoder.finish().unwrap();
+
+        assert_eq!(last_progress, 1.0);
+        assert!(actual == expected, "parallel={}", parallel);
+    }
+
+    // invalid surfaces are detected before anything is written
+    let mut encoder = Encoder::new(std::io::sink(), format, &header).unwrap();
+    let mut views = views;
+    views[5] = ImageView::new(&images[5][..16 * 4], Size::new(4, 4), ColorFormat::RGBA_U8).unwrap();
+    assert!(matches!(
+        encoder.write_surfaces(&views, None, &write_options),
+        Err(EncodingError::UnexpectedSurfaceSize)
+    ));
+    assert_eq!(encoder.surface_info().unwrap().size(), size);
+}
+
+#[test]
+fn encode_bi_planar_parallel() {
+    let mut rng = util::create_rng();
+
+    // large enough to be split into several line groups, with a small last
+    // line group
+    let size = Size::new(1024, 1026);
+    let mut image: Image<u8> = Image::new_empty(Channels::Rgb, size);
+    rng.fill_bytes(&mut image.data);
+
+    for format in [Format::NV12, Format::P010, Format::P016] {
+        // the surface can't be split into fragments that are concatenated
+        let support = format.encoding_support().unwrap();
+        assert_eq!(support.split_height(), None);
+        assert!(
+            SplitSurface::new(image.view(), format, &EncodeOptions::default())
+                .single()
+                .is_some()
+        );
+
+        let mut expected = Vec::new();
+        let mut options = EncodeOptions::default();
+        options.parallel = false;
+        encode_image(&image, format, &mut expected, &options).unwrap();
+
+        let mut actual = Vec::new();
+        options.parallel = true;
+        encode_image(&image, format, &mut actual, &options).unwrap();
+
+        assert!(actual == expected, "{:?}", format);
+    }
+}
+
+#[test]
+fn encode_rdo() {
+    fn unique_words(encoded: &[u8], block_bytes: usize) -> usize {
+        let data = &encoded[encoded.len() - 32 * 16 * block_bytes..];
+        let words: std::collections::HashSet<&[u8]> = data.chunks(4).collect();
+        words.len()
+    }
+    fn mse(a: &Image<f32>, b: &Image<f32>) -> f32 {
+        let sum: f32 = a
+            .data
+            .iter()
+            .zip(&b.data)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum();
+        sum / a.data.len() as f32 * 255.0 * 255.0
+    }
+
+    let image = util::read_png_u8(&get_sample("clovers-d.png"))
+        .unwrap()
+        .to_f32()
+        .to_channels(Channels::Rgba)
+        .cropped(Size::new(128, 64));
+
+    let formats = [
+        (Format::BC1_UNORM, 8),
+        (Format::BC2_UNORM, 16),
+        (Format::BC3_UNORM, 16),
+        (Format::BC3_UNORM_NORMAL, 16),
+        (Format::BC4_UNORM, 8),
+        (Format::BC4_SNORM, 8),
+        (Format::BC5_UNORM, 16),
+        (Format::BC5_SNORM, 16),
+    ];
+    for (format, block_bytes) in formats {
+        let mut options = EncodeOptions::default();
+        let (encoded, decoded) = encode_decode(format, &options, &image);
+
+        // lambda=0 must not increase the error relative to the source
+        options.rdo = Some(RdoOptions {
+            lambda: 0.0,
+            ..Default::default()
+        });
+        let (_, decoded_lossless) = encode_decode(format, &options, &image);
+        let image = image.to_channels(decoded.channels);
+        assert!(
+            mse(&image, &decoded_lossless) <= mse(&image, &decoded) + 1e-3,
+            "{:?}",
+            format
+        );
+
+        options.rdo = Some(RdoOptions {
+            lambda: 64.0,
+            ..Default::default()
+        });
+        let (encoded_rdo, decoded_rdo) = encode_decode(format, &options, &image);
+        assert_eq!(encoded.len(), encoded_rdo.len());
+
+        // RDO output should be more repetitive while staying close to the
+        // normal output
+        let before = unique_words(&encoded, block_bytes);
+        let after = unique_words(&encoded_rdo, block_bytes);
+        let error = mse(&decoded, &decoded_rdo);
+        assert!(after < before * 9 / 10, "{:?}", format);
+        assert!(error < 40.0, "{:?}", format);
+    }
+}
+
+#[test]
+fn encode_weighted() {
+    fn channel_mse(a: &Image<f32>, b: &Image<f32>, channel: usize) -> f32 {
+        let channels = a.channels.count() as usize;
+        let sum: f32 = a
+            .data
+            .iter()
+            .zip(&b.data)
+            .skip(channel)
+            .step_by(channels)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum();
+        sum / (a.data.len() / channels) as f32 * 255.0 * 255.0
+    }
+
+    let image = util::read_png_u8(&get_sample("clovers-d.png"))
+        .unwrap()
+        .to_f32()
+        .to_channels(Channels::Rgba)
+        .cropped(Size::new(128, 64));
+
+    let uniform = EncodeOptions::default();
+    let mut weighted = EncodeOptions::default();
+
+    // a weight of 1 for all channels is the same as uniform
+    weighted.channel_weights = ChannelWeights([2.0; 4]);
+    for format in [Format::BC1_UNORM, Format::BC3_UNORM, Format::BC5_UNORM] {
+        let (a, _) = encode_decode(format, &uniform, &image);
+        let (b, _) = encode_decode(format, &weighted, &image);
+        assert!(a == b, "{:?}", format);
+    }
+
+    // color weights are normalized separately from alpha, so equal color
+    // weights are uniform for color blocks
+    weighted.channel_weights = ChannelWeights([0.1, 0.1, 0.1, 1.0]);
+    for format in [Format::BC1_UNORM, Format::BC3_UNORM] {
+        let (a, _) = encode_decode(format, &uniform, &image);
+        let (b, _) = encode_decode(format, &weighted, &image);
+        assert!(a == b, "{:?}", format);
+    }
+
+    // prioritize G and ignore B
+    weighted.channel_weights = ChannelWeights([0.5, 4.0, 0.0, 1.0]);
+    for format in [Format::BC1_UNORM, Format::BC2_UNORM, Format::BC3_UNORM] {
+        let (_, decoded_uniform) = encode_decode(format, &uniform, &image);
+        let (_, decoded_weighted) = encode_decode(format, &weighted, &image);
+
+        let g_uniform = channel_mse(&image, &decoded_uniform, 1);
+        let g_weighted = channel_mse(&image, &decoded_weighted, 1);
+        let b_uniform = channel_mse(&image, &decoded_uniform, 2);
+        let b_weighted = channel_mse(&image, &decoded_weighted, 2);
+        assert!(g_weighted < g_uniform * 0.8, "{:?}", format);
+        assert!(b_weighted > b_uniform, "{:?}", format);
+    }
+
+    // BC5 channels are independent, so channels with weight are unaffected
+    weighted.channel_weights = ChannelWeights([1.0, 0.0, 0.0, 0.0]);
+    let mut high = EncodeOptions::default();
+    high.quality = CompressionQuality::High;
+    weighted.quality = CompressionQuality::High;
+    let (_, decoded_uniform) = encode_decode(Format::BC5_UNORM, &high, &image);
+    let (_, decoded_weighted) = encode_decode(Format::BC5_UNORM, &weighted, &image);
+    assert_eq!(
+        channel_mse(&image, &decoded_uniform, 0),
+        channel_mse(&image, &decoded_weighted, 0)
+    );
+    assert!(channel_mse(&image, &decoded_weighted, 1) < 40.0);
+
+    // independent channels are encoded with less effort the lower their weight
+    let with_quality = |quality| {
+        let mut options = EncodeOptions::default();
+        options.quality = quality;
+        options
+    };
+    let halves = |encoded: &[u8], second: bool| -> Vec<u8> {
+        let range = if second { 8..16 } else { 0..8 };
+        let data_len = image.size.pixels() as usize;
+        encoded[encoded.len() - data_len..]
+            .chunks_exact(16)
+            .flat_map(|block| block[range.clone()].to_vec())
+            .collect()
+    };
+    // the image is opaque, so use red as alpha
+    let mut alpha_image = image.clone();
+    for pixel in alpha_image.data.chunks_exact_mut(4) {
+        pixel[3] = pixel[0];
+    }
+    let cases = [
+        // (format, weights, second half?, quality of that half)
+        (
+            Format::BC5_UNORM,
+            [1.0, 0.2, 0.0, 0.0],
+            true,
+            CompressionQuality::Normal,
+        ),
+        (
+            Format::BC5_UNORM,
+            [1.0, 0.05, 0.0, 0.0],
+            true,
+            CompressionQuality::Fast,
+        ),
+        (
+            Format::BC5_UNORM,
+            [0.2, 1.0, 0.0, 0.0],
+            false,
+            CompressionQuality::Normal,
+        ),
+        (
+            Format::BC3_UNORM,
+            [1.0, 1.0, 1.0, 0.2],
+            false,
+            CompressionQuality::Normal,
+        ),
+    ];
+    for (format, weights, second, quality) in cases {
+        weighted.channel_weights = ChannelWeights(weights);
+        weighted.quality = CompressionQuality::High;
+        let image = if format == Format::BC3_UNORM {
+            &alpha_image
+        } else {
+            &image
+        };
+        let (encoded, _) = encode_decode(format, &weighted, image);
+        let (expected, _) = encode_decode(format, &with_quality(quality), image);
+        let (full_effort, _) = encode_decode(format, &high, image);
+
+        assert!(
+            halves(&encoded, second) == halves(&expected, second),
+            "{format:?} {weights:?}"
+        );
+        assert!(
+            halves(&encoded, second) != halves(&full_effort, second),
+            "{format:?} {weights:?}"
+        );
+        // the other half is encoded with full effort
+        assert!(
+            halves(&encoded, !second) == halves(&full_effort, !second),
+            "{format:?} {weights:?}"
+        );
+    }
+}
+
+#[test]
+fn encode_bc1_options() {
+    let mut rng = util::create_rng();
+    let size = Size::new(64, 64);
+    let encode_stats = |data: &[u8], options: &EncodeOptions| {
+        let image = ImageView::new(data, size, ColorFormat::RGBA_U8).unwrap();
+        let mut encoded = Vec::new();
+        let stats =
+            encode_with_stats(&mut encoded, image, Format::BC1_UNORM, None, options).unwrap();
+        (encoded, stats)
+    };
+    let decode_alpha = |encoded: &[u8]| {
+        let mut decoded = vec![0_u8; size.pixels() as usize * 4];
+        let view = ImageViewMut::new(&mut decoded, size, ColorFormat::RGBA_U8).unwrap();
+        decode(
+            &mut &encoded[..],
+            view,
+            Format::BC1_UNORM,
+            &DecodeOptions::default(),
+        )
+        .unwrap();
+        decoded.chunks_exact(4).map(|p| p[3]).collect::<Vec<_>>()
+    };
+
+    // alpha threshold
+    let mut data = vec![0_u8; size.pixels() as usize * 4];
+    for pixel in data.chunks_exact_mut(4) {
+        rng.fill_bytes(&mut pixel[..3]);
+        pixel[3] = 80;
+    }
+    let mut options = EncodeOptions::default();
+    let (encoded, _) = encode_stats(&data, &options);
+    assert!(decode_alpha(&encoded).iter().all(|&a| a == 0));
+    options.bc.alpha_threshold = 0.25;
+    let (encoded, _) = encode_stats(&data, &options);
+    assert!(decode_alpha(&encoded).iter().all(|&a| a == 255));
+
+    // opaque blocks with near-black pixels
+    let mut data = vec![0_u8; size.pixels() as usize * 4];
+    for pixel in data.chunks_exact_mut(4) {
+        if rng.gen_bool(0.3) {
+            pixel[..3].fill(1);
+        } else {
+            rng.fill_bytes(&mut pixel[..3]);
+        }
+        pixel[3] = 255;
+    }
+
+    let mut options = EncodeOptions::default();
+    options.quality = CompressionQuality::High;
+    let (_, with_p3) = encode_stats(&data, &options);
+    assert!(with_p3.modes.bc1_three_color > 0);
+
+    options.bc.opaque_three_color = false;
+    options.bc.three_color_black = true;
+    let (encoded, without_p3) = encode_stats(&data, &options);
+    assert_eq!(without_p3.modes.bc1_three_color, 0);
+    assert!(decode_alpha(&encoded).iter().all(|&a| a == 255));
+
+    options.bc.opaque_three_color = true;
+    let (encoded, black) = encode_stats(&data, &options);
+    assert!(black.metrics.color().mse < with_p3.metrics.color().mse);
+    assert!(decode_alpha(&encoded).contains(&0));
+}
+
+#[test]
+fn encode_channel_mapping_mipmaps() {
+    use ChannelSource::*;
+
+    let mut rng = util::create_rng();
+    let size = Size::new(16, 16);
+    let mut input: Image<f32> = Image::new_empty(Channels::Rgba, size);
+    rng.fill(input.data.as_mut_slice());
+    // transparent pixels must not affect the mipmaps of the opaque output
+    input
+        .data
+        .chunks_exact_mut(4)
+        .step_by(3)
+        .for_each(|p| p[3] = 0.0);
+    let image = input.view();
+
+    let mapping = ChannelMapping::new(Red, Green, Blue, One);
+    let mut opaque = input.clone();
+    opaque.data.chunks_exact_mut(4).for_each(|p| p[3] = 1.0);
+    let opaque = opaque.view();
+
+    let format = Format::R8G8B8A8_UNORM;
+    let header = Header::new_image(size.width, size.height, format).with_mipmaps();
+    let header = Header::Dx10(header.to_dx10().unwrap().with_array_size(2));
+    let write_options = WriteOptions {
+        generate_mipmaps: true,
+        ..Default::default()
+    };
+
+    let mut expected = Vec::new();
+    let mut encoder = Encoder::new(&mut expected, format, &header).unwrap();
+    encoder
+        .write_surfaces(&[opaque, opaque], None, &write_options)
+        .unwrap();
+    encoder.finish().unwrap();
+
+    for parallel in [false, true] {
+        let mut actual = Vec::new();
+        let mut encoder = Encoder::new(&mut actual, format, &header).unwrap();
+        encoder.options.channel_mapping = mapping;
+        encoder.options.parallel = parallel;
+        encoder
+            .write_surface_with(image, None, &write_options)
+            .unwrap();
+        encoder
+            .write_surfaces(&[image], None, &write_options)
+            .unwrap();
+        encoder.finish().unwrap();
+
+        assert!(actual == expected, "parallel={}", parallel);
+    }
+}
+
+#[test]
+fn encode_channel_mapping() {
+    use ChannelSource::*;
+
+    let mut rng = util::create_rng();
+    let size = Size::new(16, 16);
+    let mut data = vec![0_u8; size.pixels() as usize * 4];
+    rng.fill_bytes(&mut data);
+    let image = ImageView::new(&data, size, ColorFormat::RGBA_U8).unwrap();
+
+    // mapping on the fly must be the same as swizzling the input manually
+    let mapping = ChannelMapping::new(Alpha, Green, Zero, One);
+    let swizzled: Vec<u8> = data
+        .chunks_exact(4)
+        .flat_map(|p| [p[3], p[1], 0, 255])
+        .collect();
+    let swizzled = ImageView::new(&swizzled, size, ColorFormat::RGBA_U8).unwrap();
+
+    for &format in util::ALL_FORMATS {
+        match format.encoding_support() {
+            Some(support) if support.supports_size(size) => {}
+            _ => continue,
+        }
+
+        let mut options = EncodeOptions::default();
+        options.parallel = false;
+        let mut expected = Vec::new();
+        encode(&mut expected, swizzled, format, None, &options).unwrap();
+
+        options.channel_mapping = mapping;
+        let mut actual = Vec::new();
+        encode(&mut actual, image, format, None, &options).unwrap();
+
+        assert!(expected == actual, "mapping differs for {format:?}");
+    }
+
+    // inverted channels
+    let mut options = EncodeOptions::default();
+    options.channel_mapping = ChannelMapping::new(InvertedRed, Blue, Green, InvertedAlpha);
+    let mut encoded = Vec::new();
+    encode(&mut encoded, image, Format::R8G8B8A8_UNORM, None, &options).unwrap();
+    let expected: Vec<u8> = data
+        .chunks_exact(4)
+        .flat_map(|p| [255 - p[0], p[2], p[1], 255 - p[3]])
+        .collect();
+    assert_eq!(encoded, expected);
+
+    // decoding with the inverse mapping restores the original image
+    let mut decode_options = DecodeOptions::default();
+    decode_options.channel_mapping = ChannelMapping::new(InvertedRed, Blue, Green, InvertedAlpha);
+    let mut decoded = vec![0_u8; data.len()];
+    decode(
+        &mut &encoded[..],
+        ImageViewMut::new(&mut decoded, size, ColorFormat::RGBA_U8).unwrap(),
+        Format::R8G8B8A8_UNORM,
+        &decode_options,
+    )
+    .unwrap();
+    assert_eq!(decoded, data);
+
+    // non-RGBA outputs and rectangles
+    decode_options.channel_mapping = ChannelMapping::new(InvertedAlpha, Zero, One, One);
+    let mut gray = vec![0_u8; size.pixels() as usize];
+    decode(
+        &mut &encoded[..],
+        ImageViewMut::new(&mut gray, size, ColorFormat::GRAYSCALE_U8).unwrap(),
+        Format::R8G8B8A8_UNORM,
+        &decode_options,
+    )
+    .unwrap();
+    let alpha: Vec<u8> = data.chunks_exact(4).map(|p| p[3]).collect();
+    assert_eq!(gray, alpha);
+
+    let rect = Rect::new(3, 5, 7, 4);
+    let row_pitch = 10;
+    let mut gray_rect = vec![0_u8; row_pitch * rect.height as usize];
+    decode_rect(
+        &mut std::io::Cursor::new(&encoded),
+        &mut gray_rect,
+        row_pitch,
+        ColorFormat::GRAYSCALE_U8,
+        size,
+        rect,
+        Format::R8G8B8A8_UNORM,
+        &decode_options,
+    )
+    .unwrap();
+    for y in 0..rect.height as usize {
+        let start = (rect.y as usize + y) * size.width as usize + rect.x as usize;
+        assert_eq!(
+            gray_rect[y * row_pitch..][..rect.width as usize],
+            alpha[start..start + rect.width as usize]
+        );
+    }
+
+    // the RGBA buffer counts towards the memory limit
+    decode_options.memory_limit = 100;
+    let result = decode(
+        &mut &encoded[..],
+        ImageViewMut::new(&mut gray, size, ColorFormat::GRAYSCALE_U8).unwrap(),
+        Format::R8G8B8A8_UNORM,
+        &decode_options,
+    );
+    assert!(matches!(result, Err(DecodingError::MemoryLimitExceeded)));
+}
+
+#[test]
+fn encode_signed_snorm() {
+    let mut rng = util::create_rng();
+
+    let decode_f32 = |encoded: &[u8], size: Size, format: Format, signed: bool| {
+        let mut options = DecodeOptions::default();
+        options.signed_snorm = signed;
+        let mut decoded = vec![0_f32; size.pixels() as usize * 4];
+        let view = ImageViewMut::new(
+            util::as_bytes_mut(&mut decoded),
+            size,
+            ColorFormat::RGBA_F32,
+        )
+        .unwrap();
+        decode(&mut &encoded[..], view, format, &options).unwrap();
+        decoded
+    };
+    let encode_f32 = |decoded: &[f32], size: Size, format: Format, signed: bool| {
+        let mut options = EncodeOptions::default();
+        options.signed_snorm = signed;
+        let image = ImageView::new(util::as_bytes(decoded), size, ColorFormat::RGBA_F32).unwrap();
+        let mut encoded = Vec::new();
+        encode(&mut encoded, image, format, None, &options).unwrap();
+        encoded
+    };
+
+    // uncompressed formats decode to exact signed values and round-trip
+    let snorm8 = |x: u8| (x as i8).max(-127) as f32 / 127.0;
+    let snorm16 = |x: u16| (x as i16).max(-32767) as f32 / 32767.0;
+    let cases: [(Format, u32, bool); 6] = [
+        (Format::R8_SNORM, 1, false),
+        (Format::R8G8_SNORM, 2, false),
+        (Format::R8G8B8A8_SNORM, 4, false),
+        (Format::R16_SNORM, 1, true),
+        (Format::R16G16_SNORM, 2, true),
+        (Format::R16G16B16A16_SNORM, 4, true),
+    ];
+    for (format, channels, is_16) in cases {
+        let size = Size::new(256, 4);
+        let mut encoded =
+            vec![0_u8; size.pixels() as usize * channels as usize * (1 + is_16 as usize)];
+        if is_16 {
+            rng.fill_bytes(&mut encoded);
+        } else {
+            // all possible values
+            encoded
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = i as u8);
+        }
+        // -128 and -127 both decode to -1
+        let canonical: Vec<u8> = if is_16 {
+            encoded
+                .chunks_exact(2)
+                .flat_map(|x| (i16::from_le_bytes([x[0], x[1]]).max(-32767)).to_le_bytes())
+                .collect()
+        } else {
+            encoded.iter().map(|&x| (x as i8).max(-127) as u8).collect()
+        };
+
+        let decoded = decode_f32(&encoded, size, format, true);
+        for (i, pixel) in decoded.chunks_exact(4).enumerate() {
+            for (c, &value) in pixel.iter().enumerate().take(channels as usize) {
+                let index = i * channels as usize + c;
+                let expected = if is_16 {
+                    snorm16(u16::from_le_bytes([
+                        encoded[index * 2],
+                        encoded[index * 2 + 1],
+                    ]))
+                } else {
+                    snorm8(encoded[index])
+                };
+                assert_eq!(value, expected, "{format:?}");
+            }
+            // missing channels are 0 and 1
+            if channels == 2 {
+                assert_eq!(pixel[2], 0.0, "{format:?}");
+            }
+            if channels != 4 {
+                assert_eq!(pixel[3], 1.0, "{format:?}");
+            }
+        }
+
+        assert_eq!(
+            encode_f32(&decoded, size, format, true),
+            canonical,
+            "{format:?}"
+        );
+
+        // the default is unchanged
+        let unsigned = decode_f32(&encoded, size, format, false);
+        assert!(unsigned.iter().all(|&x| (0.0..=1.0).contains(&x)));
+    }
+
+    // block-compressed formats
+    for format in [Format::BC4_SNORM, Format::BC5_SNORM] {
+        let size = Size::new(16, 16);
+        let bytes = PixelInfo::from(format).surface_bytes(size).unwrap();
+        let mut encoded = vec![0_u8; bytes as usize];
+        rng.fill_bytes(&mut encoded);
+
+        let signed = decode_f32(&encoded, size, format, true);
+        let unsigned = decode_f32(&encoded, size, format, false);
+        for (s, u) in signed.iter().zip(&unsigned) {
+            assert!((s - (u * 2.0 - 1.0)).abs() < 1e-6, "{format:?}");
+        }
+
+        // encoding signed values is the same as encoding their unsigned equivalents
+        let from_signed = decode_f32(
+            &encode_f32(&signed, size, format, true),
+            size,
+            format,
+            false,
+        );
+        let from_unsigned = decode_f32(
+            &encode_f32(&unsigned, size, format, false),
+            size,
+            format,
+            false,
+        );
+        for (a, b) in from_signed.iter().zip(&from_unsigned) {
+            assert!((a - b).abs() < 0.01, "{format:?}");
+        }
+    }
+}
+
+#[test]
+fn encode_signed_snorm_channel_mapping() {
+    use ChannelSource::*;
+
+    let size = Size::new(16, 16);
+    let decode_f32 = |encoded: &[u8], format: Format, mapping: ChannelMapping| {
+        let mut options = DecodeOptions::default();
+        options.signed_snorm = true;
+        options.channel_mapping = mapping;
+        let mut decoded = vec![[0_f32; 4]; size.pixels() as usize];
+        let view = ImageViewMut::new(
+            util::as_bytes_mut(&mut decoded),
+            size,
+            ColorFormat::RGBA_F32,
+        )
+        .unwrap();
+        decode(&mut &encoded[..], view, format, &options).unwrap();
+        decoded
+    };
+
+    // the constants of a mapping are signed values
+    let mapping = ChannelMapping::new(Green, Zero, One, Red);
+    for format in [Format::R8G8_SNORM, Format::R16G16_SNORM, Format::BC5_SNORM] {
+        let encoded = util::random_surface(size, format);
+        let plain = decode_f32(&encoded, format, ChannelMapping::IDENTITY);
+        let mapped = decode_f32(&encoded, format, mapping);
+        for (p, m) in plain.iter().zip(&mapped) {
+            assert_eq!(*m, [p[1], 0.0, 1.0, p[0]], "{format:?}");
+        }
+
+        // encoding with Zero and One stores 0 and 1
+        let mut options = EncodeOptions::default();
+        options.signed_snorm = true;
+        options.channel_mapping = ChannelMapping::new(Red, Zero, One, One);
+        let image = ImageView::new(util::as_bytes(&plain), size, ColorFormat::RGBA_F32).unwrap();
+        let mut encoded = Vec::new();
+        encode(&mut encoded, image, format, None, &options).unwrap();
+        let decoded = decode_f32(&encoded, format, ChannelMapping::IDENTITY);
+        assert!(decoded.iter().all(|d| d[1] == 0.0), "{format:?}");
+    }
+}
+
+#[test]
+fn encode_stats() {
+    // a smooth gradient with one noisy block at (8, 4)
+    let size = Size::new(20, 12);
+    let mut rng = util::create_rng();
+    let mut data = vec![0_u8; size.pixels() as usize * 4];
+    for y in 0..size.height {
+        for x in 0..size.width {
+            let i = (y * size.width + x) as usize * 4;
+            let pixel = if (8..12).contains(&x) && (4..8).contains(&y) {
+                rng.gen()
+            } else {
+                [(x * 10) as u8, (y * 20) as u8, 128, 255]
+            };
+            data[i..i + 4].copy_from_slice(&pixel);
+        }
+    }
+    let image = ImageView::new(&data, size, ColorFormat::RGBA_U8).unwrap();
+    let options = EncodeOptions::default();
+
+    for format in [Format::BC1_UNORM, Format::BC3_UNORM, Format::BC5_UNORM] {
+        let mut expected = Vec::new();
+        encode(&mut expected, image, format, None, &options).unwrap();
+        let mut actual = Vec::new();
+        let stats = encode_with_stats(&mut actual, image, format, None, &options).unwrap();
+
+        assert!(actual == expected, "{:?}", format);
+        assert_eq!(stats.block_size, Size::new(4, 4));
+        assert_eq!(stats.worst_blocks.len(), 15);
+        let worst = stats.worst_block().unwrap();
+        assert_eq!((worst.x, worst.y), (8, 4), "{:?}", format);
+        assert!(stats.worst_blocks.windows(2).all(|w| w[0].mse >= w[1].mse));
+        assert!(worst.mse > stats.metrics.all().mse);
+        assert!(worst.max_error <= stats.metrics.all().max_error);
+
+        let blocks = 5 * 3;
+        let modes = stats.modes;
+        match format {
+            Format::BC1_UNORM => {
+                assert_eq!(modes.bc1_four_color + modes.bc1_three_color, blocks);
+                assert_eq!(modes.bc4_six_interpolated + modes.bc4_four_interpolated, 0);
+            }
+            Format::BC3_UNORM => {
+                assert_eq!(modes.bc1_four_color + modes.bc1_three_color, 0);
+                assert_eq!(
+                    modes.bc4_six_interpolated + modes.bc4_four_interpolated,
+                    blocks
+                );
+            }
+            _ => {
+                assert_eq!(
+                    modes.bc4_six_interpolated + modes.bc4_four_interpolated,
+                    blocks * 2
+                );
+            }
+        }
+    }
+
+    // uncompressed formats are split into 4x4 regions
+    let stats = encode_with_stats(
+        &mut std::io::sink(),
+        image,
+        Format::B5G6R5_UNORM,
+        None,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(stats.block_size, Size::new(4, 4));
+    assert_eq!(stats.modes, BlockModes::default());
+    assert!(stats.metrics.all().max_error <= 5.0 / 255.0);
+
+    // lossless formats have no error
+    let stats = encode_with_stats(
+        &mut std::io::sink(),
+        image,
+        Format::R8G8B8A8_UNORM,
+        None,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(stats.metrics.all().mse, 0.0);
+    assert!(stats.worst_blocks.iter().all(|b| b.mse == 0.0));
+}
+
+#[test]
+fn encode_target() {
+    let image = util::read_png_u8(&get_sample("base.png"))
+        .unwrap()
+        .to_channels(Channels::Rgba)
+        .cropped(Size::new(64, 64));
+    let view = image.view();
+    let options = EncodeOptions::default();
+    let formats = [Format::BC1_UNORM, Format::BC3_UNORM, Format::R8G8B8A8_UNORM];
+
+    // an easy target is met by the cheapest candidate that supports the alpha
+    // channel of the image
+    let mut output = Vec::new();
+    let result = encode_with_target(
+        &mut output,
+        view,
+        &formats,
+        QualityTarget::MinPsnr(20.0),
+        None,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(result.format, Format::BC3_UNORM);
+    assert_eq!(result.quality, CompressionQuality::Fast);
+    assert_eq!(result.attempts, 4);
+    assert!(result.target_met);
+    let mut expected = Vec::new();
+    let mut fast = options.clone();
+    fast.quality = CompressionQuality::Fast;
+    encode(&mut expected, view, Format::BC3_UNORM, None, &fast).unwrap();
+    assert!(output == expected);
+
+    // only lossless formats meet a very strict target
+    let result = encode_with_target(
+        &mut std::io::sink(),
+        view,
+        &formats,
+        QualityTarget::MaxRmse(0.0),
+        None,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(result.format, Format::R8G8B8A8_UNORM);
+    assert_eq!(result.attempts, 7);
+    assert!(result.target_met);
+    assert_eq!(result.metrics.all().mse, 0.0);
+
+    // the best candidate is used if the target can't be met
+    let result = encode_with_target(
+        &mut std::io::sink(),
+        view,
+        &formats[..1],
+        QualityTarget::MinPsnr(100.0),
+        None,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(result.format, Format::BC1_UNORM);
+    assert_eq!(result.attempts, 3);
+    assert!(!result.target_met);
+
+    // unsupported formats are rejected
+    assert!(matches!(
+        encode_with_target(
+            &mut std::io::sink(),
+            view,
+            &[Format::BC1_UNORM, Format::BC7_UNORM],
+            QualityTarget::MinPsnr(20.0),
+            None,
+            &options,
+        ),
+        Err(EncodingError::UnsupportedFormat(Format::BC7_UNORM))
+    ));
+
+    // at least one format is required
+    assert!(matches!(
+        encode_with_target(
+            &mut std::io::sink(),
+            view,
+            &[],
+            QualityTarget::MinPsnr(20.0),
+            None,
+            &options,
+        ),
+        Err(EncodingError::NoCandidateFormats)
+    ));
+}
+
 mod errors {
     use super::*;
 
@@ -892,4 +1967,57 @@ mod errors {
         assert!(matches!(err, EncodingError::MissingSurfaces));
         assert_eq!(err.to_string(), "Not enough surfaces have been written");
     }
+
+    #[test]
+    fn row_errors() {
+        let mut encoder = Encoder::new(
+            std::io::sink(),
+            Format::BC1_UNORM,
+            &Header::new_image(8, 8, Format::BC1_UNORM),
+        )
+        .unwrap();
+        let mut writer = encoder.begin_surface(ColorFormat::RGB_U8).unwrap();
+
+        // partial rows
+        let result = writer.write_rows(&[0; 8 * 3 + 1]);
+        assert!(matches!(result, Err(EncodingError::UnexpectedSurfaceSize)));
+        // too many rows
+        let result = writer.write_rows(&[0; 8 * 3 * 9]);
+        assert!(matches!(result, Err(EncodingError::UnexpectedSurfaceSize)));
+
+        writer.write_rows(&[0; 8 * 3 * 5]).unwrap();
+        let err = writer.finish().err().unwrap();
+        assert!(matches!(err, EncodingError::MissingRows));
+        assert_eq!(err.to_string(), "Not enough rows have been written");
+    }
+
+    #[test]
+    fn poisoned() {
+        let header = Header::new_image(8, 8, Format::BC1_UNORM).with_mipmaps();
+        let mut encoder = Encoder::new(std::io::sink(), Format::BC1_UNORM, &header).unwrap();
+        let image = util::Image::<u8>::new_empty(Channels::Rgb, Size::new(8, 8));
+
+        // a finished surface writer does not poison the encoder
+        let mut writer = encoder.begin_surface(ColorFormat::RGB_U8).unwrap();
+        writer.write_rows(&image.data).unwrap();
+        writer.finish().unwrap();
+
+        // dropping the surface writer early does
+        let mut writer = encoder.begin_surface(ColorFormat::RGB_U8).unwrap();
+        writer.write_rows(&[0; 4 * 3 * 2]).unwrap();
+        drop(writer);
+
+        let image = util::Image::<u8>::new_empty(Channels::Rgb, Size::new(4, 4));
+        let err = encoder.write_surface(image.view()).err().unwrap();
+        assert!(matches!(err, EncodingError::Poisoned));
+        assert_eq!(
+            err.to_string(),
+            "The encoder is poisoned by a partially written surface"
+        );
+        assert!(matches!(
+            encoder.begin_surface(ColorFormat::RGB_U8),
+            Err(EncodingError::Poisoned)
+        ));
+        assert!(matches!(encoder.finish(), Err(EncodingError::Poisoned)));
+    }
 }
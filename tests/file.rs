@@ -0,0 +1,368 @@
+use std::io::Cursor;
+
+use dds::{header::*, *};
+
+mod util;
+
+fn to_bytes(file: &DdsFile) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    file.write(&mut bytes).unwrap();
+    bytes
+}
+fn array_header(size: u32, array_size: u32) -> Header {
+    let header = Header::new_image(size, size, Format::BC1_UNORM).with_mipmaps();
+    Header::Dx10(header.to_dx10().unwrap().with_array_size(array_size))
+}
+
+#[test]
+fn round_trip() {
+    let headers = [
+        Header::new_image(13, 7, Format::R8G8B8A8_UNORM).with_mipmaps(),
+        Header::new_image(13, 7, Format::BC1_UNORM).with_mipmaps(),
+        Header::new_cube_map(8, 8, Format::BC7_UNORM).with_mipmaps(),
+        Header::new_volume(8, 4, 5, Format::R8G8B8A8_UNORM).with_mipmaps(),
+        array_header(16, 3),
+        Header::Dx9(
+            Header::new_cube_map(4, 4, Format::R8G8B8A8_UNORM)
+                .to_dx9()
+                .unwrap()
+                .with_cube_map_faces(CubeMapFaces::POSITIVE_X | CubeMapFaces::NEGATIVE_Z),
+        ),
+    ];
+
+    for header in headers {
+        let bytes = util::random_file(header);
+        let file =
+            DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+        assert!(to_bytes(&file) == bytes);
+    }
+}
+
+#[test]
+fn read_memory_limit() {
+    let read = |header: &Header, data_len: usize, memory_limit: usize| {
+        let mut bytes = Vec::new();
+        header.write(&mut bytes).unwrap();
+        bytes.extend(util::random_bytes(data_len));
+        DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), memory_limit)
+    };
+
+    // headers that describe far more data than the file contains
+    let huge = Header::new_image(60000, 60000, Format::R32G32B32A32_FLOAT);
+    let many = Header::Dx10(
+        Header::new_image(4, 4, Format::BC1_UNORM)
+            .to_dx10()
+            .unwrap()
+            .with_array_size(u32::MAX),
+    );
+    for header in [&huge, &many] {
+        let result = read(header, 64, 1 << 30);
+        assert!(matches!(result, Err(DecodingError::MemoryLimitExceeded)));
+        // without a limit, the truncated data section is an IO error
+        let result = read(header, 64, usize::MAX);
+        assert!(matches!(result, Err(DecodingError::Io(_))));
+    }
+
+    // the limit is inclusive
+    let header = Header::new_image(4, 4, Format::R8G8B8A8_UNORM);
+    assert!(read(&header, 64, 64).is_ok());
+    let result = read(&header, 64, 63);
+    assert!(matches!(result, Err(DecodingError::MemoryLimitExceeded)));
+    let result = read(&header, 63, 64);
+    assert!(matches!(result, Err(DecodingError::Io(_))));
+}
+
+#[test]
+fn surfaces() {
+    let header = array_header(16, 3);
+    let bytes = util::random_file(header.clone());
+    let mut file =
+        DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+
+    let layout = file.layout();
+    let array = layout.texture_array().unwrap();
+    let data_start = bytes.len() - layout.data_len() as usize;
+    for (array_index, texture) in array.iter().enumerate() {
+        for (mipmap, surface) in texture.iter_mips().enumerate() {
+            let start = data_start + surface.data_offset() as usize;
+            let expected = &bytes[start..start + surface.data_len() as usize];
+            let index = SurfaceIndex::texture(array_index, mipmap as u8);
+            assert_eq!(file.surface(index), Some(expected));
+        }
+    }
+    assert_eq!(file.surface(SurfaceIndex::texture(3, 0)), None);
+    assert_eq!(file.surface(SurfaceIndex::texture(0, 5)), None);
+    assert_eq!(file.surface(SurfaceIndex::depth_slice(0, 1)), None);
+
+    // replace a surface
+    let index = SurfaceIndex::texture(1, 2);
+    let new_surface = vec![0xAB; file.surface(index).unwrap().len()];
+    file.replace_surface(index, &new_surface).unwrap();
+    assert_eq!(file.surface(index), Some(new_surface.as_slice()));
+    assert!(matches!(
+        file.replace_surface(index, &[0; 3]),
+        Err(DdsFileError::UnexpectedDataLength { .. })
+    ));
+
+    // cube maps
+    let header = Header::new_cube_map(8, 8, Format::R8G8B8A8_UNORM).with_mipmaps();
+    let bytes = util::random_file(header.clone());
+    let file = DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+
+    let mut decoder = Decoder::new(Cursor::new(&bytes)).unwrap();
+    let mut face = util::Image::<u8>::new_empty(Channels::Rgba, Size::new(4, 4));
+    // skip +X and its mipmaps, then level 0 of -X, then read level 1 of -X
+    for _ in 0..5 {
+        decoder.skip_surface().unwrap();
+    }
+    decoder.read_surface(face.view_mut()).unwrap();
+    let index = SurfaceIndex::cube_face(0, CubeMapFaces::NEGATIVE_X, 1);
+    assert_eq!(file.surface(index), Some(face.data.as_slice()));
+    assert_eq!(file.surface(SurfaceIndex::texture(0, 0)), None);
+
+    // volumes
+    let header = Header::new_volume(8, 4, 5, Format::R8G8B8A8_UNORM).with_mipmaps();
+    let bytes = util::random_file(header.clone());
+    let file = DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+    let slice_len = 8 * 4 * 4;
+    let data_start = bytes.len() - file.layout().data_len() as usize;
+    let start = data_start + 2 * slice_len;
+    assert_eq!(
+        file.surface(SurfaceIndex::depth_slice(0, 2)),
+        Some(&bytes[start..start + slice_len])
+    );
+    assert_eq!(file.surface(SurfaceIndex::depth_slice(0, 5)), None);
+}
+
+#[test]
+fn array_elements() {
+    let bytes = util::random_file(array_header(16, 3));
+    let mut file =
+        DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+    assert_eq!(file.element_count(), 3);
+
+    // remove the middle element and insert it again
+    let removed = file.remove_element(1).unwrap();
+    assert_eq!(removed.len() as u64, file.element_len());
+    assert_eq!(file.element_count(), 2);
+    assert_eq!(file.header().array_size(), 2);
+    assert_eq!(file.layout().texture_array().unwrap().len(), 2);
+    file.insert_element(1, removed).unwrap();
+    assert!(to_bytes(&file) == bytes);
+
+    // push
+    let new_element = util::random_bytes(file.element_len() as usize);
+    file.push_element(new_element.clone()).unwrap();
+    assert_eq!(file.element_count(), 4);
+    assert_eq!(file.element(3), Some(new_element));
+    assert!(
+        DdsFile::read(
+            Cursor::new(to_bytes(&file)),
+            &ParseOptions::default(),
+            usize::MAX
+        )
+        .unwrap()
+            == file
+    );
+
+    // errors
+    assert!(matches!(
+        file.insert_element(6, vec![0; file.element_len() as usize]),
+        Err(DdsFileError::IndexOutOfBounds)
+    ));
+    assert!(matches!(
+        file.push_element(vec![0; 3]),
+        Err(DdsFileError::UnexpectedDataLength { .. })
+    ));
+    assert!(matches!(
+        file.remove_element(4),
+        Err(DdsFileError::IndexOutOfBounds)
+    ));
+
+    // cube maps
+    let mut file =
+        DdsFile::new(Header::new_cube_map(4, 4, Format::BC1_UNORM).with_mipmaps()).unwrap();
+    assert_eq!(file.element_count(), 1);
+    file.push_element(vec![1; file.element_len() as usize])
+        .unwrap();
+    assert_eq!(file.element_count(), 2);
+    assert_eq!(file.layout().texture_array().unwrap().len(), 12);
+    let index = SurfaceIndex::cube_face(1, CubeMapFaces::POSITIVE_Z, 0);
+    assert_eq!(file.surface(index), Some([1; 8].as_slice()));
+
+    // volumes and DX9 textures don't have array elements
+    let mut file = DdsFile::new(Header::new_volume(4, 4, 4, Format::BC1_UNORM)).unwrap();
+    assert!(matches!(
+        file.remove_element(0),
+        Err(DdsFileError::UnsupportedLayout)
+    ));
+}
+
+#[test]
+fn cube_map_faces() {
+    let header = Header::Dx9(
+        Header::new_cube_map(4, 4, Format::R8G8B8A8_UNORM)
+            .to_dx9()
+            .unwrap(),
+    );
+    let bytes = util::random_file(header);
+    let mut file =
+        DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+
+    let removed = file.remove_cube_map_face(CubeMapFaces::POSITIVE_Y).unwrap();
+    assert_eq!(
+        file.header().to_dx9().unwrap().cube_map_faces(),
+        Some(CubeMapFaces::ALL - CubeMapFaces::POSITIVE_Y)
+    );
+    let index = SurfaceIndex::cube_face(0, CubeMapFaces::NEGATIVE_Y, 0);
+    assert_eq!(
+        file.surface(index),
+        Some(&bytes[128 + 3 * 64..128 + 4 * 64])
+    );
+    assert_eq!(
+        file.surface(SurfaceIndex::cube_face(0, CubeMapFaces::POSITIVE_Y, 0)),
+        None
+    );
+
+    assert!(matches!(
+        file.remove_cube_map_face(CubeMapFaces::POSITIVE_Y),
+        Err(DdsFileError::IndexOutOfBounds)
+    ));
+    file.insert_cube_map_face(CubeMapFaces::POSITIVE_Y, removed)
+        .unwrap();
+    assert!(to_bytes(&file) == bytes);
+
+    // DX10 cube maps always have all faces
+    let mut file = DdsFile::new(Header::new_cube_map(4, 4, Format::R8G8B8A8_UNORM)).unwrap();
+    assert!(matches!(
+        file.remove_cube_map_face(CubeMapFaces::POSITIVE_Y),
+        Err(DdsFileError::UnsupportedLayout)
+    ));
+}
+
+#[test]
+fn mipmap_count() {
+    let bytes = util::random_file(array_header(16, 2));
+    let mut file =
+        DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+    assert_eq!(file.header().mipmap_count().get(), 5);
+
+    let level_1 = file.surface(SurfaceIndex::texture(1, 1)).unwrap().to_vec();
+    file.set_mipmap_count(2).unwrap();
+    assert_eq!(file.header().mipmap_count().get(), 2);
+    assert_eq!(file.layout().texture_array().unwrap().mipmaps(), 2);
+    assert_eq!(
+        file.surface(SurfaceIndex::texture(1, 1)),
+        Some(level_1.as_slice())
+    );
+    assert_eq!(file.surface(SurfaceIndex::texture(1, 2)), None);
+
+    // new mipmaps are zero
+    file.set_mipmap_count(3).unwrap();
+    assert_eq!(
+        file.surface(SurfaceIndex::texture(1, 1)),
+        Some(level_1.as_slice())
+    );
+    assert_eq!(
+        file.surface(SurfaceIndex::texture(1, 2)),
+        Some([0; 8].as_slice())
+    );
+    assert!(
+        DdsFile::read(
+            Cursor::new(to_bytes(&file)),
+            &ParseOptions::default(),
+            usize::MAX
+        )
+        .unwrap()
+            == file
+    );
+
+    assert!(matches!(
+        file.set_mipmap_count(0),
+        Err(DdsFileError::IndexOutOfBounds)
+    ));
+}
+
+#[test]
+fn merge_and_split() {
+    fn decoder(bytes: &[u8]) -> Decoder<Cursor<&[u8]>> {
+        Decoder::new(Cursor::new(bytes)).unwrap()
+    }
+
+    // split an array into its elements and merge them again
+    let bytes = util::random_file(array_header(16, 3));
+    let file = DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+    let elements: Vec<Vec<u8>> = (0..3)
+        .map(|i| to_bytes(&file.extract_element(i).unwrap()))
+        .collect();
+    let element = DdsFile::read(
+        Cursor::new(&elements[1]),
+        &ParseOptions::default(),
+        usize::MAX,
+    )
+    .unwrap();
+    assert!(element.layout().texture().is_some());
+    assert_eq!(element.element(0), file.element(1));
+    assert!(matches!(
+        file.extract_element(3),
+        Err(DdsFileError::IndexOutOfBounds)
+    ));
+
+    let mut decoders: Vec<_> = elements.iter().map(|e| decoder(e)).collect();
+    let merged = DdsFile::merge_array(&mut decoders).unwrap();
+    assert!(to_bytes(&merged) == bytes);
+    // decoders are left at the start, so they can be read again
+    assert!(DdsFile::merge_array(&mut decoders).unwrap() == merged);
+
+    // arrays can be merged with arrays
+    let mut decoders = [decoder(&bytes), decoder(&elements[0])];
+    let merged = DdsFile::merge_array(&mut decoders).unwrap();
+    assert_eq!(merged.element_count(), 4);
+    assert_eq!(merged.element(3), file.element(0));
+
+    // split a cube map into its faces and merge them again
+    let bytes = util::random_file(Header::new_cube_map(8, 8, Format::BC1_UNORM).with_mipmaps());
+    let file = DdsFile::read(Cursor::new(&bytes), &ParseOptions::default(), usize::MAX).unwrap();
+    let faces: Vec<Vec<u8>> = [
+        CubeMapFaces::POSITIVE_X,
+        CubeMapFaces::NEGATIVE_X,
+        CubeMapFaces::POSITIVE_Y,
+        CubeMapFaces::NEGATIVE_Y,
+        CubeMapFaces::POSITIVE_Z,
+        CubeMapFaces::NEGATIVE_Z,
+    ]
+    .iter()
+    .map(|&face| to_bytes(&file.extract_cube_map_face(0, face).unwrap()))
+    .collect();
+    let face = DdsFile::read(Cursor::new(&faces[0]), &ParseOptions::default(), usize::MAX).unwrap();
+    assert!(!face.layout().is_cube_map());
+
+    let mut decoders = [0, 1, 2, 3, 4, 5].map(|i| decoder(&faces[i]));
+    let merged = DdsFile::merge_cube_map(&mut decoders).unwrap();
+    assert!(to_bytes(&merged) == bytes);
+
+    // cube maps can be merged into cube map arrays
+    let mut decoders = [decoder(&bytes), decoder(&bytes)];
+    let merged = DdsFile::merge_array(&mut decoders).unwrap();
+    assert_eq!(merged.element_count(), 2);
+    assert_eq!(merged.header().array_size(), 2);
+    assert!(merged.layout().is_cube_map());
+
+    // errors
+    let mut decoders: [Decoder<Cursor<&[u8]>>; 0] = [];
+    assert!(matches!(
+        DdsFile::merge_array(&mut decoders),
+        Err(DdsFileError::EmptyInput)
+    ));
+    let mut decoders = [decoder(&bytes), decoder(&faces[0])];
+    assert!(matches!(
+        DdsFile::merge_array(&mut decoders),
+        Err(DdsFileError::MismatchedInputs)
+    ));
+    let other = util::random_file(Header::new_image(8, 8, Format::BC3_UNORM).with_mipmaps());
+    let mut decoders = [0, 1, 2, 3, 4, 5].map(|i| decoder(if i == 3 { &other } else { &faces[i] }));
+    assert!(matches!(
+        DdsFile::merge_cube_map(&mut decoders),
+        Err(DdsFileError::MismatchedInputs)
+    ));
+}
@@ -0,0 +1,150 @@
+use dds::*;
+use rand::Rng;
+
+mod util;
+
+fn random_rgba(size: Size) -> Vec<u8> {
+    let mut rng = util::create_rng();
+    (0..size.pixels() * 4).map(|_| rng.gen()).collect()
+}
+
+#[test]
+fn identical() {
+    let size = Size::new(23, 17);
+    let data = random_rgba(size);
+    let view = ImageView::new(&data, size, ColorFormat::RGBA_U8).unwrap();
+
+    for space in [MetricSpace::Rgb, MetricSpace::Oklab] {
+        let metrics = compare(view, view, space).unwrap();
+        assert_eq!(metrics.space, space);
+        for channel in metrics.channels.iter().chain([&metrics.all()]) {
+            assert_eq!(channel.mse, 0.0);
+            assert_eq!(channel.rmse(), 0.0);
+            assert_eq!(channel.max_error, 0.0);
+            assert_eq!(channel.psnr(), f64::INFINITY);
+            assert!((channel.ssim - 1.0).abs() < 1e-6, "{:?}", channel);
+        }
+    }
+
+    // different color formats of the same image are identical
+    let data_f32: Vec<f32> = data.iter().map(|&x| x as f32 / 255.0).collect();
+    let view_f32 = ImageView::new(util::as_bytes(&data_f32), size, ColorFormat::RGBA_F32).unwrap();
+    let metrics = compare(view, view_f32, MetricSpace::Rgb).unwrap();
+    assert!(metrics.all().mse < 1e-12);
+
+    // missing alpha is opaque
+    let rgb: Vec<u8> = data.chunks(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+    let opaque: Vec<u8> = data
+        .chunks(4)
+        .flat_map(|p| [p[0], p[1], p[2], 255])
+        .collect();
+    let metrics = compare(
+        ImageView::new(&rgb, size, ColorFormat::RGB_U8).unwrap(),
+        ImageView::new(&opaque, size, ColorFormat::RGBA_U8).unwrap(),
+        MetricSpace::Rgb,
+    )
+    .unwrap();
+    assert_eq!(metrics.all().mse, 0.0);
+}
+
+#[test]
+fn known_error() {
+    let size = Size::new(16, 16);
+    let a = vec![100_u8; size.pixels() as usize * 4];
+    let mut b = a.clone();
+    // change the green channel of every pixel by 2 and one alpha value by 51
+    b.chunks_mut(4).for_each(|p| p[1] += 2);
+    b[3] = 151;
+
+    let metrics = compare(
+        ImageView::new(&a, size, ColorFormat::RGBA_U8).unwrap(),
+        ImageView::new(&b, size, ColorFormat::RGBA_U8).unwrap(),
+        MetricSpace::Rgb,
+    )
+    .unwrap();
+    let [r, g, b, alpha] = metrics.channels;
+
+    assert_eq!(r.mse, 0.0);
+    assert_eq!(b.mse, 0.0);
+    assert!((g.rmse() - 2.0 / 255.0).abs() < 1e-6);
+    assert!((g.max_error - 2.0 / 255.0).abs() < 1e-6);
+    assert!((g.psnr() - 42.11).abs() < 0.01, "{}", g.psnr());
+    // a constant offset barely changes the structure
+    assert!(g.ssim > 0.99);
+    assert!((alpha.max_error - 51.0 / 255.0).abs() < 1e-6);
+    assert!((alpha.mse - (51.0 / 255.0_f64).powi(2) / 256.0).abs() < 1e-6);
+    assert!(alpha.ssim < g.ssim);
+
+    let color = metrics.color();
+    assert!((color.mse - g.mse / 3.0).abs() < 1e-9);
+    assert_eq!(color.max_error, g.max_error);
+}
+
+#[test]
+fn ssim_detects_structure() {
+    let size = Size::new(32, 32);
+    let base = random_rgba(size);
+    let view = ImageView::new(&base, size, ColorFormat::RGBA_U8).unwrap();
+
+    // a constant offset and random noise with a similar MSE
+    let offset: Vec<u8> = base.iter().map(|&x| x.saturating_add(8)).collect();
+    let mut rng = util::create_rng();
+    let noise: Vec<u8> = base
+        .iter()
+        .map(|&x| {
+            if rng.gen() {
+                x.saturating_add(8)
+            } else {
+                x.saturating_sub(8)
+            }
+        })
+        .collect();
+
+    let offset = compare(
+        view,
+        ImageView::new(&offset, size, ColorFormat::RGBA_U8).unwrap(),
+        MetricSpace::Rgb,
+    )
+    .unwrap();
+    let noise = compare(
+        view,
+        ImageView::new(&noise, size, ColorFormat::RGBA_U8).unwrap(),
+        MetricSpace::Rgb,
+    )
+    .unwrap();
+
+    assert!(offset.all().ssim > noise.all().ssim);
+}
+
+#[test]
+fn oklab() {
+    let size = Size::new(8, 8);
+    let gray = |v: u8| vec![v; size.pixels() as usize];
+    let (a, b) = (gray(100), gray(110));
+
+    let metrics = compare(
+        ImageView::new(&a, size, ColorFormat::GRAYSCALE_U8).unwrap(),
+        ImageView::new(&b, size, ColorFormat::GRAYSCALE_U8).unwrap(),
+        MetricSpace::Oklab,
+    )
+    .unwrap();
+    let [l, a, b, alpha] = metrics.channels;
+
+    // changing brightness of a gray image only affects L
+    assert!(l.mse > 0.0);
+    assert!(a.max_error < 1e-4, "{:?}", a);
+    assert!(b.max_error < 1e-4, "{:?}", b);
+    assert_eq!(alpha.mse, 0.0);
+}
+
+#[test]
+fn size_mismatch() {
+    let data = vec![0_u8; 64];
+    let a = ImageView::new(&data, Size::new(4, 4), ColorFormat::RGBA_U8).unwrap();
+    let b = ImageView::new(&data, Size::new(2, 8), ColorFormat::RGBA_U8).unwrap();
+
+    assert!(matches!(
+        compare(a, b, MetricSpace::Rgb),
+        Err(MetricsError::SizeMismatch { .. })
+    ));
+}
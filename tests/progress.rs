@@ -232,3 +232,165 @@ fn forward_progress() {
         }
     }
 }
+
+#[test]
+fn cancel() {
+    use std::ops::ControlFlow;
+
+    let mut image: util::Image<u8> = util::Image::new_empty(Channels::Rgba, Size::new(512, 512));
+    util::create_rng().fill(image.data.as_mut_slice());
+
+    for format in [Format::BC1_UNORM, Format::B5G6R5_UNORM, Format::NV12] {
+        for parallel in [false, true] {
+            let mut options = EncodeOptions::default();
+            options.quality = CompressionQuality::Fast;
+            options.parallel = parallel;
+
+            // cancel on the first report
+            let mut reports = 0;
+            let mut report = |_| {
+                reports += 1;
+                ControlFlow::Break(())
+            };
+            let mut progress = Progress::new(&mut report);
+
+            let result = encode(
+                &mut std::io::sink(),
+                image.view(),
+                format,
+                Some(&mut progress),
+                &options,
+            );
+            assert!(
+                matches!(result, Err(EncodingError::Cancelled)),
+                "{:?} parallel={}",
+                format,
+                parallel
+            );
+            assert_eq!(reports, 1);
+        }
+    }
+
+    // reports that return () never cancel
+    let mut report = |_| {};
+    let mut progress = Progress::new_single_threaded(&mut report);
+    encode(
+        &mut std::io::sink(),
+        image.view(),
+        Format::BC1_UNORM,
+        Some(&mut progress),
+        &EncodeOptions::default(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn progress_events() {
+    let format = Format::BC1_UNORM;
+    let header = Header::new_cube_map(16, 16, format).with_mipmaps();
+    let header = Header::Dx10(header.to_dx10().unwrap().with_array_size(2));
+
+    let image: util::Image<u8> = util::Image::new_empty(Channels::Rgba, Size::new(16, 16));
+    let mut encoder = Encoder::new(std::io::sink(), format, &header).unwrap();
+
+    let mut events = Vec::new();
+    for _ in 0..2 * 6 {
+        let mut report = |_| {};
+        let mut on_event = |event: &ProgressEvent| events.push(*event);
+        let mut progress = Progress::new(&mut report).with_events(&mut on_event);
+        encoder
+            .write_surface_with(
+                image.view(),
+                Some(&mut progress),
+                &WriteOptions {
+                    generate_mipmaps: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+    }
+    encoder.finish().unwrap();
+
+    // 2 events for each mipmap of each face
+    assert_eq!(events.len(), 2 * 6 * 5 * 2);
+    let faces = [
+        CubeMapFaces::POSITIVE_X,
+        CubeMapFaces::NEGATIVE_X,
+        CubeMapFaces::POSITIVE_Y,
+        CubeMapFaces::NEGATIVE_Y,
+        CubeMapFaces::POSITIVE_Z,
+        CubeMapFaces::NEGATIVE_Z,
+    ];
+    let mut pairs = events.chunks_exact(2);
+    for array_index in 0..2 {
+        for face in faces {
+            for mipmap in 0..5 {
+                let pair = pairs.next().unwrap();
+                let (started, finished) = (&pair[0], &pair[1]);
+                let surface = SurfaceIndex::cube_face(array_index, face, mipmap);
+                assert_eq!(started.surface, surface);
+                assert_eq!(finished.surface, surface);
+                assert_eq!(started.state, SurfaceState::Started);
+                assert_eq!(finished.state, SurfaceState::Finished);
+                assert_eq!(started.size, Size::new(16, 16).get_mipmap(mipmap));
+                assert_eq!(
+                    finished.processed_pixels - started.processed_pixels,
+                    started.size.pixels()
+                );
+                assert!(finished.remaining.is_some());
+            }
+        }
+    }
+
+    let last = events.last().unwrap();
+    assert_eq!(last.processed_pixels, last.total_pixels);
+    assert_eq!(last.remaining, Some(std::time::Duration::ZERO));
+    assert!(events.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+}
+
+#[test]
+fn surface_writer_progress() {
+    use std::ops::ControlFlow;
+
+    let format = Format::BC1_UNORM;
+    let size = Size::new(16, 20);
+    let header = Header::new_image(size.width, size.height, format);
+    let image: util::Image<u8> = util::Image::new_empty(Channels::Rgba, size);
+    let row_pitch = image.view().row_pitch();
+
+    // report progress and events for each strip
+    let mut reports = Vec::new();
+    let mut events = Vec::new();
+    {
+        let mut report = |p: f32| reports.push(p);
+        let mut on_event = |event: &ProgressEvent| events.push(event.state);
+        let mut progress = Progress::new(&mut report).with_events(&mut on_event);
+
+        let mut encoder = Encoder::new(std::io::sink(), format, &header).unwrap();
+        let mut writer = encoder
+            .begin_surface_with(ColorFormat::RGBA_U8, Some(&mut progress))
+            .unwrap();
+        for row in image.data.chunks(row_pitch) {
+            writer.write_rows(row).unwrap();
+        }
+        writer.finish().unwrap();
+        encoder.finish().unwrap();
+    }
+    assert_eq!(reports, [0.2, 0.4, 0.6, 0.8, 1.0, 1.0]);
+    assert_eq!(events, [SurfaceState::Started, SurfaceState::Finished]);
+
+    // cancel on the first report
+    let mut report = |_| ControlFlow::Break(());
+    let mut progress = Progress::new(&mut report);
+    let mut encoder = Encoder::new(std::io::sink(), format, &header).unwrap();
+    let mut writer = encoder
+        .begin_surface_with(ColorFormat::RGBA_U8, Some(&mut progress))
+        .unwrap();
+    let result = writer.write_rows(&image.data);
+    assert!(matches!(result, Err(EncodingError::Cancelled)));
+    drop(writer);
+    assert!(matches!(
+        encoder.write_surface(image.view()),
+        Err(EncodingError::Poisoned)
+    ));
+}
@@ -0,0 +1,141 @@
+use dds::*;
+
+mod util;
+
+fn image_rgba(size: Size, f: impl Fn(u32, u32) -> [f32; 4]) -> Vec<f32> {
+    let mut data = Vec::new();
+    for y in 0..size.height {
+        for x in 0..size.width {
+            data.extend_from_slice(&f(x, y));
+        }
+    }
+    data
+}
+fn suggest(data: &[f32], size: Size, constraints: &FormatConstraints) -> FormatSuggestion {
+    let view = ImageView::new(util::as_bytes(data), size, ColorFormat::RGBA_F32).unwrap();
+    suggest_format(view, constraints).unwrap()
+}
+
+#[test]
+fn alpha() {
+    let size = Size::new(16, 16);
+    let default = FormatConstraints::default();
+    let color = |x: u32, y: u32| [x as f32 / 15.0, y as f32 / 15.0, 0.5];
+
+    let opaque = image_rgba(size, |x, y| {
+        let [r, g, b] = color(x, y);
+        [r, g, b, 1.0]
+    });
+    let suggestion = suggest(&opaque, size, &default);
+    assert_eq!(suggestion.analysis.alpha, AlphaKind::Opaque);
+    assert_eq!(suggestion.format, Format::BC1_UNORM);
+
+    let binary = image_rgba(size, |x, y| {
+        let [r, g, b] = color(x, y);
+        [r, g, b, (x % 2) as f32]
+    });
+    let suggestion = suggest(&binary, size, &default);
+    assert_eq!(suggestion.analysis.alpha, AlphaKind::Binary);
+    assert_eq!(suggestion.format, Format::BC1_UNORM);
+
+    let constant = image_rgba(size, |x, y| {
+        let [r, g, b] = color(x, y);
+        [r, g, b, 0.5]
+    });
+    let suggestion = suggest(&constant, size, &default);
+    assert_eq!(suggestion.analysis.alpha, AlphaKind::Constant);
+    assert_eq!(suggestion.format, Format::BC3_UNORM);
+
+    let gradient = image_rgba(size, |x, y| {
+        let [r, g, b] = color(x, y);
+        [r, g, b, x as f32 / 15.0]
+    });
+    let suggestion = suggest(&gradient, size, &default);
+    assert_eq!(suggestion.analysis.alpha, AlphaKind::Gradient);
+    assert_eq!(suggestion.format, Format::BC3_UNORM);
+    assert!(!suggestion.reason.is_empty());
+
+    // without block compression
+    let mut uncompressed = FormatConstraints::default();
+    uncompressed.block_compression = false;
+    assert_eq!(
+        suggest(&opaque, size, &uncompressed).format,
+        Format::B5G6R5_UNORM
+    );
+    assert_eq!(
+        suggest(&binary, size, &uncompressed).format,
+        Format::B5G5R5A1_UNORM
+    );
+    assert_eq!(
+        suggest(&gradient, size, &uncompressed).format,
+        Format::B4G4R4A4_UNORM
+    );
+}
+
+#[test]
+fn content() {
+    let size = Size::new(16, 16);
+    let default = FormatConstraints::default();
+
+    // grayscale
+    let gray = image_rgba(size, |x, _| {
+        let v = x as f32 / 15.0;
+        [v, v, v, 1.0]
+    });
+    let suggestion = suggest(&gray, size, &default);
+    assert!(suggestion.analysis.grayscale);
+    assert!(!suggestion.analysis.high_precision);
+    assert_eq!(suggestion.format, Format::BC4_UNORM);
+
+    // 16-bit grayscale
+    let height: Vec<u16> = (0..size.pixels()).map(|i| (i * 97) as u16).collect();
+    let view = ImageView::new(util::as_bytes(&height), size, ColorFormat::GRAYSCALE_U16).unwrap();
+    let suggestion = suggest_format(view, &default).unwrap();
+    assert!(suggestion.analysis.high_precision);
+    assert_eq!(suggestion.format, Format::R16_UNORM);
+
+    // normal map
+    let normals = image_rgba(size, |x, y| {
+        let nx = (x as f32 - 7.5) / 20.0;
+        let ny = (y as f32 - 7.5) / 20.0;
+        let nz = (1.0 - nx * nx - ny * ny).sqrt();
+        [nx * 0.5 + 0.5, ny * 0.5 + 0.5, nz * 0.5 + 0.5, 1.0]
+    });
+    let suggestion = suggest(&normals, size, &default);
+    assert!(suggestion.analysis.normal_map);
+    assert_eq!(suggestion.format, Format::BC5_UNORM);
+
+    // HDR
+    let hdr = image_rgba(size, |x, _| [x as f32, 0.5, 0.25, 1.0]);
+    let suggestion = suggest(&hdr, size, &default);
+    assert!(suggestion.analysis.is_hdr());
+    assert_eq!(suggestion.analysis.max, 15.0);
+    assert_eq!(suggestion.format, Format::R11G11B10_FLOAT);
+
+    let negative = image_rgba(size, |x, _| [x as f32 - 4.0, 0.5, 0.25, 1.0]);
+    let suggestion = suggest(&negative, size, &default);
+    assert_eq!(suggestion.analysis.min, -4.0);
+    assert_eq!(suggestion.format, Format::R16G16B16A16_FLOAT);
+}
+
+#[test]
+fn header_constraints() {
+    let size = Size::new(16, 16);
+    let normals = image_rgba(size, |x, _| {
+        let nx = (x as f32 - 7.5) / 10.0;
+        let nz = (1.0 - nx * nx).sqrt();
+        [nx * 0.5 + 0.5, 0.5, nz * 0.5 + 0.5, 1.0]
+    });
+
+    for header in [HeaderRequirement::Dx9, HeaderRequirement::Dx10] {
+        let mut constraints = FormatConstraints::default();
+        constraints.header = header;
+        let suggestion = suggest(&normals, size, &constraints);
+        assert!(header.allows(suggestion.format), "{:?}", suggestion);
+    }
+
+    let rxgb = Format::BC3_UNORM_RXGB;
+    assert!(HeaderRequirement::Dx9.allows(rxgb));
+    assert!(!HeaderRequirement::Dx10.allows(rxgb));
+    assert!(HeaderRequirement::Any.allows(rxgb));
+}
@@ -0,0 +1,201 @@
+use std::io::Cursor;
+
+use dds::*;
+
+mod util;
+
+fn run(data: &[u8], size: Size, from: Format, to: Format) -> (Vec<u8>, TranscodePath) {
+    let mut reader = Cursor::new(data);
+    let mut output = Vec::new();
+    let path = transcode(
+        &mut reader,
+        &mut output,
+        size,
+        from,
+        to,
+        &TranscodeOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(reader.position(), data.len() as u64);
+    assert_eq!(
+        output.len() as u64,
+        PixelInfo::from(to).surface_bytes(size).unwrap()
+    );
+    (output, path)
+}
+
+#[test]
+fn identity() {
+    let size = Size::new(13, 7);
+    for &format in util::ALL_FORMATS {
+        let data = util::random_surface(size, format);
+        let (output, path) = run(&data, size, format, format);
+        assert_eq!(path, TranscodePath::Lossless);
+        assert_eq!(output, data);
+    }
+}
+
+#[test]
+fn bc1_to_bc3() {
+    let size = Size::new(32, 32);
+    let data = util::random_surface(size, Format::BC1_UNORM);
+    let expected = util::decode_surface(&data, size, Format::BC1_UNORM, ColorFormat::RGBA_U8);
+
+    let (output, path) = run(&data, size, Format::BC1_UNORM, Format::BC3_UNORM);
+    // random data contains many 3-color blocks
+    assert_eq!(path, TranscodePath::BlockRewrite);
+    let actual = util::decode_surface(&output, size, Format::BC3_UNORM, ColorFormat::RGBA_U8);
+
+    // alpha is always preserved exactly
+    for (e, a) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        assert_eq!(e[3], a[3]);
+    }
+
+    // 4-color blocks are copied
+    let mut block = [0_u8; 8];
+    block[0..2].copy_from_slice(&0xF800_u16.to_le_bytes());
+    block[2..4].copy_from_slice(&0x001F_u16.to_le_bytes());
+    block[4..8].copy_from_slice(&0x1B1B_1B1B_u32.to_le_bytes());
+    let size = Size::new(4, 4);
+    let (output, path) = run(&block, size, Format::BC1_UNORM, Format::BC3_UNORM);
+    assert_eq!(path, TranscodePath::Lossless);
+    assert_eq!(&output[8..], &block);
+    assert_eq!(
+        util::decode_surface(&output, size, Format::BC3_UNORM, ColorFormat::RGBA_U8),
+        util::decode_surface(&block, size, Format::BC1_UNORM, ColorFormat::RGBA_U8)
+    );
+
+    // 3-color blocks that only use the endpoints are rewritten exactly
+    block[0..2].copy_from_slice(&0x001F_u16.to_le_bytes());
+    block[2..4].copy_from_slice(&0xF800_u16.to_le_bytes());
+    block[4..8].copy_from_slice(&0x1414_1414_u32.to_le_bytes());
+    let (output, path) = run(&block, size, Format::BC1_UNORM, Format::BC3_UNORM);
+    assert_eq!(path, TranscodePath::Lossless);
+    assert_eq!(
+        util::decode_surface(&output, size, Format::BC3_UNORM, ColorFormat::RGBA_U8),
+        util::decode_surface(&block, size, Format::BC1_UNORM, ColorFormat::RGBA_U8)
+    );
+}
+
+#[test]
+fn bc4_to_bc5() {
+    let size = Size::new(9, 17);
+    for (from, to) in [
+        (Format::BC4_UNORM, Format::BC5_UNORM),
+        (Format::BC4_SNORM, Format::BC5_SNORM),
+    ] {
+        let data = util::random_surface(size, from);
+        let (output, path) = run(&data, size, from, to);
+        assert_eq!(path, TranscodePath::Lossless);
+
+        let gray = util::decode_surface(&data, size, from, ColorFormat::RGBA_U8);
+        let rg = util::decode_surface(&output, size, to, ColorFormat::RGBA_U8);
+        for (g, c) in gray.chunks_exact(4).zip(rg.chunks_exact(4)) {
+            assert_eq!(g[0], c[0]);
+        }
+    }
+}
+
+#[test]
+fn alpha_modes() {
+    let size = Size::new(16, 12);
+    for (straight, premultiplied) in [
+        (Format::BC2_UNORM, Format::BC2_UNORM_PREMULTIPLIED_ALPHA),
+        (Format::BC3_UNORM, Format::BC3_UNORM_PREMULTIPLIED_ALPHA),
+    ] {
+        for (from, to) in [(straight, premultiplied), (premultiplied, straight)] {
+            let data = util::random_surface(size, from);
+            let (output, path) = run(&data, size, from, to);
+            assert_eq!(path, TranscodePath::BlockRewrite);
+
+            // the alpha block is copied
+            for (input, output) in data.chunks_exact(16).zip(output.chunks_exact(16)) {
+                assert_eq!(input[..8], output[..8]);
+            }
+        }
+    }
+}
+
+#[test]
+fn rxgb() {
+    let size = Size::new(8, 8);
+    let data = util::random_surface(size, Format::BC3_UNORM);
+    let (output, path) = run(&data, size, Format::BC3_UNORM, Format::BC3_UNORM_RXGB);
+    assert_ne!(path, TranscodePath::Reencode);
+
+    // G and B are preserved exactly
+    let expected = util::decode_surface(&data, size, Format::BC3_UNORM, ColorFormat::RGBA_U8);
+    let actual = util::decode_surface(&output, size, Format::BC3_UNORM_RXGB, ColorFormat::RGBA_U8);
+    for (e, a) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        assert_eq!(e[1..3], a[1..3]);
+    }
+
+    let (_, path) = run(&output, size, Format::BC3_UNORM_RXGB, Format::BC3_UNORM);
+    assert_ne!(path, TranscodePath::Reencode);
+}
+
+#[test]
+fn reencode() {
+    let size = Size::new(8, 8);
+    let data = util::random_surface(size, Format::R8G8B8A8_UNORM);
+    let (_, path) = run(&data, size, Format::R8G8B8A8_UNORM, Format::BC1_UNORM);
+    assert_eq!(path, TranscodePath::Reencode);
+
+    let data = util::random_surface(size, Format::BC7_UNORM);
+    let (_, path) = run(&data, size, Format::BC7_UNORM, Format::BC3_UNORM);
+    assert_eq!(path, TranscodePath::Reencode);
+}
+
+#[test]
+fn memory_limit() {
+    let size = Size::new(8, 8);
+    let transcode_with_limit = |from: Format, to: Format, size: Size, memory_limit: usize| {
+        let data = util::random_surface(Size::new(8, 8), from);
+        let options = TranscodeOptions {
+            memory_limit,
+            ..Default::default()
+        };
+        transcode(
+            &mut Cursor::new(&data),
+            &mut Vec::new(),
+            size,
+            from,
+            to,
+            &options,
+        )
+    };
+
+    // re-encoding needs the decoded surface
+    let (from, to) = (Format::BC7_UNORM, Format::BC1_UNORM);
+    assert!(transcode_with_limit(from, to, size, 8 * 8 * 4).is_ok());
+    let result = transcode_with_limit(from, to, size, 8 * 8 * 4 - 1);
+    assert!(matches!(
+        result,
+        Err(TranscodeError::Decoding(DecodingError::MemoryLimitExceeded))
+    ));
+
+    // block rewrites need the input and output surfaces
+    let (from, to) = (Format::BC1_UNORM, Format::BC3_UNORM);
+    assert!(transcode_with_limit(from, to, size, 32 + 64).is_ok());
+    let result = transcode_with_limit(from, to, size, 32 + 64 - 1);
+    assert!(matches!(
+        result,
+        Err(TranscodeError::Decoding(DecodingError::MemoryLimitExceeded))
+    ));
+
+    // sizes that overflow are layout errors
+    let huge = Size::new(u32::MAX, u32::MAX);
+    for (from, to) in [
+        (Format::R32G32B32A32_FLOAT, Format::BC1_UNORM),
+        (Format::BC1_UNORM, Format::BC3_UNORM),
+    ] {
+        let result = transcode_with_limit(from, to, huge, usize::MAX);
+        assert!(matches!(
+            result,
+            Err(TranscodeError::Decoding(DecodingError::Layout(
+                LayoutError::DataLayoutTooBig
+            )))
+        ));
+    }
+}
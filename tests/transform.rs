@@ -0,0 +1,277 @@
+use std::io::Cursor;
+
+use dds::{header::*, *};
+use rand::RngCore;
+
+mod util;
+
+const TRANSFORMS: [Transform; 5] = [
+    Transform::FlipX,
+    Transform::FlipY,
+    Transform::Rotate90,
+    Transform::Rotate180,
+    Transform::Rotate270,
+];
+
+/// Transforms a decoded image pixel by pixel.
+fn transform_pixels(
+    image: &[u8],
+    size: Size,
+    bytes_per_pixel: usize,
+    transform: Transform,
+) -> Vec<u8> {
+    let new_size = transform.transformed_size(size);
+    let (w, h) = (size.width as usize, size.height as usize);
+    let mut output = vec![0_u8; image.len()];
+    for y in 0..new_size.height as usize {
+        for x in 0..new_size.width as usize {
+            let (sx, sy) = match transform {
+                Transform::FlipX => (w - 1 - x, y),
+                Transform::FlipY => (x, h - 1 - y),
+                Transform::Rotate90 => (y, h - 1 - x),
+                Transform::Rotate180 => (w - 1 - x, h - 1 - y),
+                Transform::Rotate270 => (w - 1 - y, x),
+            };
+            let dst = (y * new_size.width as usize + x) * bytes_per_pixel;
+            let src = (sy * w + sx) * bytes_per_pixel;
+            output[dst..dst + bytes_per_pixel].copy_from_slice(&image[src..src + bytes_per_pixel]);
+        }
+    }
+    output
+}
+
+#[test]
+fn transform_matches_decoded() {
+    let sizes = [Size::new(16, 8), Size::new(3, 2), Size::new(8, 1)];
+
+    for &format in util::ALL_FORMATS {
+        let size = sizes[0];
+        let data = util::random_surface(size, format);
+        if let Err(err) = transform(&data, size, format, Transform::FlipY) {
+            assert!(
+                matches!(err, TransformError::UnsupportedFormat(f) if f == format),
+                "{:?}: {}",
+                format,
+                err
+            );
+            continue;
+        }
+
+        for size in sizes {
+            let data = util::random_surface(size, format);
+            let decoded = util::decode_surface(&data, size, format, format.color());
+            let bytes_per_pixel = format.color().bytes_per_pixel() as usize;
+
+            for t in TRANSFORMS {
+                let new_size = t.transformed_size(size);
+                let transformed = transform(&data, size, format, t).unwrap();
+                assert_eq!(
+                    util::decode_surface(&transformed, new_size, format, format.color()),
+                    transform_pixels(&decoded, size, bytes_per_pixel, t),
+                    "{:?} {:?} {:?}",
+                    format,
+                    size,
+                    t
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn transform_errors() {
+    // BC6H, BC7, and ASTC are not supported
+    for format in [
+        Format::BC6H_UF16,
+        Format::BC7_UNORM,
+        Format::ASTC_4X4_UNORM,
+        Format::NV12,
+        Format::YUY2,
+    ] {
+        let size = Size::new(8, 8);
+        assert!(matches!(
+            transform(
+                &util::random_surface(size, format),
+                size,
+                format,
+                Transform::FlipX
+            ),
+            Err(TransformError::UnsupportedFormat(_))
+        ));
+    }
+
+    // blocks cannot be split
+    let size = Size::new(6, 8);
+    let data = util::random_surface(size, Format::BC1_UNORM);
+    assert!(matches!(
+        transform(&data, size, Format::BC1_UNORM, Transform::FlipX),
+        Err(TransformError::NotBlockAligned)
+    ));
+    assert!(transform(&data, size, Format::BC1_UNORM, Transform::FlipY).is_ok());
+    assert!(transform(&data, size, Format::BC1_UNORM, Transform::Rotate90).is_ok());
+
+    assert!(matches!(
+        transform(&data[1..], size, Format::BC1_UNORM, Transform::FlipY),
+        Err(TransformError::UnexpectedDataLength { .. })
+    ));
+}
+
+#[test]
+fn transform_file() {
+    let header = Header::new_image(16, 8, Format::BC3_UNORM).with_mipmaps();
+    let layout = DataLayout::from_header(&header).unwrap();
+    let mut data = vec![0_u8; layout.data_len() as usize];
+    util::create_rng().fill_bytes(&mut data);
+
+    let mut file = DdsFile::from_header(Cursor::new(&data), header, usize::MAX).unwrap();
+    let original = file.clone();
+
+    file.transform(Transform::Rotate90).unwrap();
+    assert_eq!(file.header().size(), Size::new(8, 16));
+    assert_eq!(file.layout().main_size(), Size::new(8, 16));
+    let index = SurfaceIndex::texture(0, 1);
+    assert_eq!(
+        file.surface(index).unwrap(),
+        transform(
+            original.surface(index).unwrap(),
+            Size::new(8, 4),
+            Format::BC3_UNORM,
+            Transform::Rotate90
+        )
+        .unwrap()
+    );
+
+    file.transform(Transform::Rotate270).unwrap();
+    assert!(file == original);
+
+    // failed transforms leave the file unchanged
+    let header = Header::new_image(6, 8, Format::BC1_UNORM);
+    let mut file = DdsFile::new(header).unwrap();
+    assert!(file.transform(Transform::FlipX).is_err());
+    assert_eq!(file.header().size(), Size::new(6, 8));
+}
+
+/// The direction of the center of a pixel of a cube map face. Coordinates are
+/// scaled by the face size, so they are all integers.
+fn cube_map_direction(face: CubeMapFaces, size: i32, x: i32, y: i32) -> [i32; 3] {
+    let (s, t) = (2 * x + 1 - size, 2 * y + 1 - size);
+    match face {
+        CubeMapFaces::POSITIVE_X => [size, -t, -s],
+        CubeMapFaces::NEGATIVE_X => [-size, -t, s],
+        CubeMapFaces::POSITIVE_Y => [s, size, t],
+        CubeMapFaces::NEGATIVE_Y => [s, -size, -t],
+        CubeMapFaces::POSITIVE_Z => [s, -t, size],
+        _ => [-s, -t, -size],
+    }
+}
+/// The face and pixel of a cube map in the given direction.
+fn cube_map_pixel([x, y, z]: [i32; 3], size: i32) -> (CubeMapFaces, i32, i32) {
+    let (face, s, t) = if x == size {
+        (CubeMapFaces::POSITIVE_X, -z, -y)
+    } else if x == -size {
+        (CubeMapFaces::NEGATIVE_X, z, -y)
+    } else if y == size {
+        (CubeMapFaces::POSITIVE_Y, x, z)
+    } else if y == -size {
+        (CubeMapFaces::NEGATIVE_Y, x, -z)
+    } else if z == size {
+        (CubeMapFaces::POSITIVE_Z, x, -y)
+    } else {
+        (CubeMapFaces::NEGATIVE_Z, -x, -y)
+    };
+    (face, (s + size - 1) / 2, (t + size - 1) / 2)
+}
+fn cube_map_faces(file: &DdsFile) -> CubeMapFaces {
+    match file.layout().texture_array().unwrap().kind() {
+        TextureArrayKind::CubeMaps => CubeMapFaces::ALL,
+        TextureArrayKind::PartialCubeMap(faces) => faces,
+        TextureArrayKind::Textures => panic!("not a cube map"),
+    }
+}
+/// Checks that every pixel of the transformed cube map shows the pixel of the
+/// original cube map in the mirrored or rotated direction.
+fn check_cube_map(original: &DdsFile, transformed: &DdsFile, transform: Transform) {
+    let format = original.format();
+    let bytes_per_pixel = format.color().bytes_per_pixel() as usize;
+    let mipmaps = original.layout().texture_array().unwrap().mipmaps();
+    let original_faces = cube_map_faces(original);
+
+    for face in cube_map_faces(transformed).iter() {
+        for mipmap in 0..mipmaps {
+            let size = original.layout().main_size().get_mipmap(mipmap);
+            let decode_face = |file: &DdsFile, face| {
+                let index = SurfaceIndex::cube_face(0, face, mipmap);
+                util::decode_surface(file.surface(index).unwrap(), size, format, format.color())
+            };
+            let actual = decode_face(transformed, face);
+
+            let n = size.width as i32;
+            for y in 0..n {
+                for x in 0..n {
+                    let [dx, dy, dz] = cube_map_direction(face, n, x, y);
+                    let direction = match transform {
+                        Transform::FlipX => [-dx, dy, dz],
+                        Transform::FlipY => [dx, -dy, dz],
+                        Transform::Rotate90 => [-dy, dx, dz],
+                        Transform::Rotate180 => [-dx, -dy, dz],
+                        Transform::Rotate270 => [dy, -dx, dz],
+                    };
+                    let (source_face, sx, sy) = cube_map_pixel(direction, n);
+                    assert!(original_faces.contains(source_face));
+                    let expected = decode_face(original, source_face);
+
+                    let i = (y * n + x) as usize * bytes_per_pixel;
+                    let j = (sy * n + sx) as usize * bytes_per_pixel;
+                    assert_eq!(
+                        actual[i..i + bytes_per_pixel],
+                        expected[j..j + bytes_per_pixel],
+                        "{transform:?} {format:?} {face:?} mip {mipmap} at ({x}, {y})"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn transform_cube_map() {
+    let partial = Header::Dx9(
+        Header::new_cube_map(8, 8, Format::R8G8B8A8_UNORM)
+            .to_dx9()
+            .unwrap()
+            .with_cube_map_faces(CubeMapFaces::POSITIVE_X | CubeMapFaces::POSITIVE_Z),
+    );
+    let headers = [
+        Header::new_cube_map(8, 8, Format::R8G8B8A8_UNORM).with_mipmaps(),
+        Header::new_cube_map(8, 8, Format::BC1_UNORM).with_mipmaps(),
+        partial.clone(),
+    ];
+
+    for header in headers {
+        let layout = DataLayout::from_header(&header).unwrap();
+        let mut data = vec![0_u8; layout.data_len() as usize];
+        util::create_rng().fill_bytes(&mut data);
+        let original = DdsFile::from_header(Cursor::new(&data), header, usize::MAX).unwrap();
+
+        for transform in TRANSFORMS {
+            let mut file = original.clone();
+            file.transform(transform).unwrap();
+            check_cube_map(&original, &file, transform);
+        }
+    }
+
+    // partial cube maps keep the faces that were transformed
+    let mut data = vec![0_u8; DataLayout::from_header(&partial).unwrap().data_len() as usize];
+    util::create_rng().fill_bytes(&mut data);
+    let mut file = DdsFile::from_header(Cursor::new(&data), partial, usize::MAX).unwrap();
+    file.transform(Transform::FlipX).unwrap();
+    assert_eq!(
+        cube_map_faces(&file),
+        CubeMapFaces::NEGATIVE_X | CubeMapFaces::POSITIVE_Z
+    );
+    file.transform(Transform::Rotate90).unwrap();
+    assert_eq!(
+        cube_map_faces(&file),
+        CubeMapFaces::POSITIVE_Y | CubeMapFaces::POSITIVE_Z
+    );
+}
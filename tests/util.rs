@@ -1,12 +1,12 @@
 #![allow(unused)]
 
 use dds::{header::*, *};
-use rand::SeedableRng;
+use rand::{RngCore, SeedableRng};
 use sha2::{Digest, Sha256};
 use std::{
     collections::HashMap,
     fs::File,
-    io::Seek,
+    io::{Cursor, Seek},
     path::{Path, PathBuf},
 };
 use zerocopy::{FromBytes, Immutable, IntoBytes};
@@ -15,6 +15,36 @@ use Precision::*;
 pub fn create_rng() -> impl rand::Rng {
     rand_chacha::ChaChaRng::seed_from_u64(123456789)
 }
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut data = vec![0_u8; len];
+    create_rng().fill_bytes(&mut data);
+    data
+}
+/// Returns a DDS file with the given header and random data.
+pub fn random_file(header: Header) -> Vec<u8> {
+    let layout = DataLayout::from_header(&header).unwrap();
+    let mut file = Vec::new();
+    header.write(&mut file).unwrap();
+    file.extend(random_bytes(layout.data_len() as usize));
+    file
+}
+/// Returns the encoded data of a surface with random bytes.
+pub fn random_surface(size: Size, format: Format) -> Vec<u8> {
+    random_bytes(PixelInfo::from(format).surface_bytes(size).unwrap() as usize)
+}
+/// Decodes the given surface data with the default options.
+pub fn decode_surface(data: &[u8], size: Size, format: Format, color: ColorFormat) -> Vec<u8> {
+    let mut image = vec![0_u8; size.pixels() as usize * color.bytes_per_pixel() as usize];
+    let view = ImageViewMut::new(&mut image, size, color).unwrap();
+    decode(
+        &mut Cursor::new(data),
+        view,
+        format,
+        &DecodeOptions::default(),
+    )
+    .unwrap();
+    image
+}
 
 pub fn hash_hex(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
@@ -251,13 +281,13 @@ impl<T> Image<T> {
         ColorFormat::new(self.channels, T::PRECISION)
     }
 
-    pub fn view(&self) -> ImageView
+    pub fn view(&self) -> ImageView<'_>
     where
         T: Castable + WithPrecision,
     {
         ImageView::new(self.as_bytes(), self.size, self.color()).unwrap()
     }
-    pub fn view_mut(&mut self) -> ImageViewMut
+    pub fn view_mut(&mut self) -> ImageViewMut<'_>
     where
         T: Castable + WithPrecision,
     {